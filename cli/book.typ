@@ -26,9 +26,14 @@
 #let data = json_data.data
 #let doc_metadata = json_data.metadata
 
+// Skip-gram models record which positions before the next word their entries use
+#let context_positions = doc_metadata.at("context_positions", default: ())
+
 // Function to get model type string from n value
 #let model-type(n) = {
-  if n == 1 {
+  if context_positions.len() > 0 {
+    "skip-gram"
+  } else if n == 1 {
     "unigram"
   } else if n == 2 {
     "bigram"
@@ -88,6 +93,9 @@
           styled-punct,
         ),
       )
+    } else if part == "_" {
+      // A position skipped by a skip-gram model: a blank to fill with any word
+      box(width: 1.2em, height: 0.6em, stroke: (bottom: 0.5pt + black))
     } else if part == "—" {
       // Em dash separator
       text(" — ", size: size, weight: weight)
@@ -268,8 +276,12 @@
 
 // Function to format a complete entry (prefix + dice indicator + followers)
 #let format-entry(prefix, total_count, followers) = {
-  // Format the prefix
-  display-with-punctuation(prefix, size: 1.5em, weight: "bold")
+  // Format the prefix (unigram models have a single entry with an empty prefix)
+  if prefix == "" {
+    text("any word", size: 1.5em, weight: "bold", style: "italic")
+  } else {
+    display-with-punctuation(prefix, size: 1.5em, weight: "bold")
+  }
 
   // Add dice indicator
   h(0.2em)
@@ -292,31 +304,56 @@
     paper to write down the generated text, according to the following
     algorithm.
 
+    #if context_positions.len() > 0 [
+      This model is a _skip-gram_ model: rather than always looking at the words
+      just before the next word, each entry uses the words at positions
+      #context_positions.map(str).join(", ") (where -1 is the word just before
+      the next word, -2 the word before that, and so on). Each blank
+      (#display-with-punctuation("_", size: 1em)) in an entry stands for a word
+      the model skips, so when looking up an entry match the words in your text
+      at the other positions and ignore whatever word fills the blank.
+    ]
+
     == Algorithm
 
-    To generate new text using the #context model-type(doc_metadata.n) model in
-    this book:
+    #if doc_metadata.n == 1 [
+      A unigram model doesn't look at the previous word: every word is chosen
+      from the same single entry (marked _any word_), so to generate new text
+      using this book:
 
-    + *choose a starting word*---pick any bold word from the book (note that
-      punctuation e.g. #punct-box(".") count as words in this model) and write
-      it down
+      + *roll your d10(s)*: the diamonds next to _any word_ show how many d10s
+        to roll; read the dice from left to right as a single number
 
-    + *look up the word's entry* (i.e. use this book like a dictionary) to find
-      all possible _next_ words according to the model
+      + *find your next word*: scan through the entry until you find the first
+        number ≥ your roll (write it down)
 
-    + *roll your d10(s)*: check the diamonds next to the word---this shows how
-      many d10s to roll (e.g., #display-with-punctuation("the")#h(
-        0.2em,
-      )#format-dice-indicator(100)#h(0.2em) means roll 3 d10s). Read the dice
-      from left to right as a single number (e.g., rolling 2, 1 and 7 means your
-      roll is 217)
+      + repeat until you reach a natural stopping point (like #punct-box(".")) or
+        reach your desired text length
+    ] else [
+      To generate new text using the #context model-type(doc_metadata.n) model in
+      this book:
 
-    + *find your next word*: scan through the followers until you find the first
-      number ≥ your roll (write it down)
+      + *choose a starting word*---pick any bold word from the book (note that
+        punctuation e.g. #punct-box(".") count as words in this model) and write
+        it down
 
-    + repeat from step 2 using this word as your new word, continuing this loop
-      until you reach a natural stopping point (like #punct-box(".")) or reach
-      your desired text length
+      + *look up the word's entry* (i.e. use this book like a dictionary) to find
+        all possible _next_ words according to the model
+
+      + *roll your d10(s)*: check the diamonds next to the word---this shows how
+        many d10s to roll (e.g., #display-with-punctuation("the")#h(
+          0.2em,
+        )#format-dice-indicator(100)#h(0.2em) means roll 3 d10s). Read the dice
+        from left to right as a single number (e.g., rolling 2, 1 and 7 means your
+        roll is 217)
+
+      + *find your next word*: scan through the followers until you find the first
+        number ≥ your roll (write it down)
+
+      + repeat from step 2 using this word as your new word, continuing this loop
+        until you reach a natural stopping point (like #punct-box(".")) or reach
+        your desired text length
+    ]
 
     === Example 1: single d10
 
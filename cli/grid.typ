@@ -0,0 +1,62 @@
+// Copyright (c) 2025 Ben Swift
+// Licensed under CC BY-NC-SA 4.0. See handouts/LICENSE for details.
+
+// Training grid worksheet (mode: "blank") or answer key (mode: "key")
+#let grid_path = sys.inputs.at("grid_path", default: "grid.json")
+#let mode = sys.inputs.at("mode", default: "key")
+#let show_labels = mode == "key" or sys.inputs.at("labels", default: "false") == "true"
+
+#let grid_data = json(grid_path)
+#let words = grid_data.words
+#let counts = grid_data.counts
+// Optional "after verb"/"after pronoun"/... columns (see the Context Columns handout)
+#let context_columns = grid_data.at("context_columns", default: ())
+#let context_counts = grid_data.at("context_counts", default: ())
+
+#set page(paper: sys.inputs.at("paper_size", default: "a4"), flipped: true, margin: 1.5cm)
+#set text(font: "Libertinus Serif", size: 11pt)
+
+// Tally marks, grouped in fives (matches handouts/utils.typ)
+#let tally(n) = {
+  if n == 0 { return [] }
+  let marks = ""
+  for i in range(int(n / 5)) {
+    marks += "卌 "
+  }
+  for i in range(calc.rem(n, 5)) {
+    marks += "|"
+  }
+  marks
+}
+
+#let header-cell(word) = if show_labels { raw(word) } else { [] }
+
+#text(size: 1.4em, weight: "bold")[
+  #if mode == "key" [Training grid: answer key] else [Training grid]
+]
+
+#if mode == "key" [
+  Text: #emph(grid_data.text)
+
+  Preprocessed: #grid_data.tokens.map(t => raw(t)).join(" ")
+]
+
+#v(0.5em)
+
+#let context-header(name) = rotate(-90deg, reflow: true)[after~#name]
+#let cell(c) = if mode == "key" { tally(c) } else { [] }
+
+#table(
+  columns: (1fr,) * (words.len() + context_columns.len() + 1),
+  rows: (auto, 2.4em),
+  align: (col, row) => if row == 0 { center } else { left },
+  table.header([], ..words.map(header-cell), ..context_columns.map(context-header)),
+  ..words
+    .enumerate()
+    .map(((i, word)) => (
+      header-cell(word),
+      ..counts.at(i).map(cell),
+      ..if context_counts.len() > 0 { context_counts.at(i).map(cell) } else { () },
+    ))
+    .flatten(),
+)
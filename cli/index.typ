@@ -0,0 +1,50 @@
+// Copyright (c) 2025 Ben Swift
+// Licensed under CC BY-NC-SA 4.0. See handouts/LICENSE for details.
+
+// One-page "which book do I need?" insert for a model split across several books
+#let index_path = sys.inputs.at("index_path", default: "model_index.json")
+#let index_data = json(index_path)
+#let ranges = index_data.ranges
+
+#set page(paper: sys.inputs.at("paper_size", default: "a4"), margin: 1.5cm)
+#set text(font: "Libertinus Serif", size: 11pt)
+
+#let book-label(books) = {
+  if books.len() == 1 {
+    [Book #books.at(0)]
+  } else {
+    [Books #books.map(str).join(", ", last: " and ")]
+  }
+}
+
+#let word-range(range) = {
+  if range.from == range.to {
+    strong(range.from)
+  } else {
+    [#strong(range.from) -- #strong(range.to)]
+  }
+}
+
+#text(size: 1.6em, weight: "bold")[Which book do I need?]
+
+#if index_data.title != "" [
+  #emph(index_data.title), in #index_data.books books.
+]
+Find the first word of your current prefix below: its entry is in the book
+shown. A word in more than one book has its entries split between them, so
+check the guide words at the top of each page.
+
+#v(0.5em)
+
+// Keep the index on one page: use more columns for longer indexes
+#columns(if ranges.len() > 60 { 3 } else if ranges.len() > 20 { 2 } else { 1 })[
+  #set text(size: if ranges.len() > 120 { 8pt } else { 11pt })
+  #table(
+    columns: (1fr, auto),
+    stroke: none,
+    inset: (x: 0.3em, y: 0.2em),
+    table.header([*First word*], [*Book*]),
+    table.hline(stroke: 0.5pt),
+    ..ranges.map(range => (word-range(range), book-label(range.books))).flatten(),
+  )
+]
@@ -0,0 +1,111 @@
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+
+use crate::WordFollowEntry;
+
+/// Keeps only the rows and columns of an adapter whose words are all in `vocabulary`.
+///
+/// This is the Low-Rank Adaptation handout's "same columns as the base model" rule; prefixes
+/// left with no followers are dropped.
+pub fn restrict_to_vocabulary(
+    entries: &[WordFollowEntry],
+    vocabulary: &BTreeSet<String>,
+) -> Vec<WordFollowEntry> {
+    entries
+        .iter()
+        .filter(|entry| entry.prefix.iter().all(|w| vocabulary.contains(w)))
+        .filter_map(|entry| {
+            let followers: Vec<(String, usize)> = entry
+                .followers
+                .iter()
+                .filter(|(w, _)| vocabulary.contains(w))
+                .cloned()
+                .collect();
+            (!followers.is_empty()).then(|| WordFollowEntry {
+                prefix: entry.prefix.clone(),
+                followers,
+            })
+        })
+        .collect()
+}
+
+/// Adds an adapter's counts to a base model, multiplying them by `scale` (rounded to whole counts).
+///
+/// Rows only in the base model are unchanged; rows only in the adapter are added. Followers
+/// are re-sorted by count as in [`crate::NGramCounter::get_entries`].
+pub fn apply_adapter(
+    base: &[WordFollowEntry],
+    adapter: &[WordFollowEntry],
+    scale: f64,
+) -> Vec<WordFollowEntry> {
+    let mut merged: BTreeMap<Vec<String>, HashMap<String, usize>> = BTreeMap::new();
+
+    for entry in base {
+        let followers = merged.entry(entry.prefix.clone()).or_default();
+        for (word, count) in &entry.followers {
+            *followers.entry(word.clone()).or_default() += count;
+        }
+    }
+
+    for entry in adapter {
+        let followers = merged.entry(entry.prefix.clone()).or_default();
+        for (word, count) in &entry.followers {
+            let scaled = (*count as f64 * scale).round().max(0.0) as usize;
+            if scaled > 0 {
+                *followers.entry(word.clone()).or_default() += scaled;
+            }
+        }
+    }
+
+    merged.retain(|_, followers| !followers.is_empty());
+    crate::convert_to_entries(&merged)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(prefix: &str, followers: &[(&str, usize)]) -> WordFollowEntry {
+        WordFollowEntry {
+            prefix: vec![prefix.to_string()],
+            followers: followers.iter().map(|(w, c)| (w.to_string(), *c)).collect(),
+        }
+    }
+
+    #[test]
+    fn matches_lora_handout_example() {
+        let base = vec![
+            entry("saw", &[("the", 4), ("they", 2), ("a", 2), ("red", 1)]),
+            entry("they", &[("the", 2), ("a", 1), ("saw", 1)]),
+        ];
+        let vocabulary: BTreeSet<String> = ["saw", "they", "we", "the", "a", "red"]
+            .iter()
+            .map(|w| w.to_string())
+            .collect();
+        // "i" and "cat" aren't in the base model, so only the "saw" row survives
+        let adapter = restrict_to_vocabulary(
+            &[
+                entry("i", &[("saw", 2)]),
+                entry("saw", &[("red", 2), ("the", 1), ("a", 1), ("cat", 1)]),
+            ],
+            &vocabulary,
+        );
+        assert_eq!(
+            adapter,
+            vec![entry("saw", &[("red", 2), ("the", 1), ("a", 1)])]
+        );
+
+        let merged = apply_adapter(&base, &adapter, 1.0);
+        assert_eq!(
+            merged[0],
+            entry("saw", &[("the", 5), ("a", 3), ("red", 3), ("they", 2)])
+        );
+        assert_eq!(merged[1], base[1]);
+
+        // Half strength: 2 -> 1, 1 -> 0.5 -> 1 (rounded)
+        let merged = apply_adapter(&base, &adapter, 0.5);
+        assert_eq!(
+            merged[0],
+            entry("saw", &[("the", 5), ("a", 3), ("red", 2), ("they", 2)])
+        );
+    }
+}
@@ -0,0 +1,455 @@
+use std::fmt::Write as _;
+use std::fs::File;
+use std::io::{self, Write};
+use std::path::Path;
+
+use crate::{GAP_TOKEN, Metadata, ProcessingStats, WordFollowEntry, roll_table, save_to_json};
+
+/// How [`save_booklet`] writes a book
+#[derive(Debug, Clone, PartialEq, Default)]
+pub enum BookletFormat {
+    /// The model JSON that `book.typ` typesets (see [`save_to_json`])
+    #[default]
+    Json,
+    /// A standalone web page laid out for printing (see [`save_to_html`])
+    Html(PageStyle),
+    /// A Markdown document, e.g. to paste into a word processor (see [`save_to_markdown`])
+    Markdown,
+}
+
+/// Page setup for an HTML booklet, as for `book.typ`
+#[derive(Debug, Clone, PartialEq)]
+pub struct PageStyle {
+    /// Paper size as named by typst, e.g. "a4" or "us-letter"
+    pub paper: String,
+    /// Body text size in points
+    pub font_size: f64,
+    pub columns: usize,
+}
+
+impl Default for PageStyle {
+    fn default() -> Self {
+        PageStyle {
+            paper: "a4".to_string(),
+            font_size: 8.0,
+            columns: 4,
+        }
+    }
+}
+
+impl BookletFormat {
+    /// The file extension for a book in this format
+    pub fn extension(&self) -> &'static str {
+        match self {
+            BookletFormat::Json => "json",
+            BookletFormat::Html(_) => "html",
+            BookletFormat::Markdown => "md",
+        }
+    }
+}
+
+/// Writes one book in the given format (`stats` are only kept in JSON)
+pub fn save_booklet<P: AsRef<Path>>(
+    format: &BookletFormat,
+    entries: &[WordFollowEntry],
+    path: P,
+    metadata: Option<&Metadata>,
+    stats: Option<&ProcessingStats>,
+    raw: bool,
+) -> io::Result<()> {
+    match format {
+        BookletFormat::Json => save_to_json(entries, path, metadata, stats, raw),
+        BookletFormat::Html(style) => save_to_html(entries, path, metadata, raw, style),
+        BookletFormat::Markdown => save_to_markdown(entries, path, metadata, raw),
+    }
+}
+
+/// Writes the booklet as a standalone HTML page: the same entries as `book.typ` (bold prefix,
+/// one diamond per die and each follower with the highest roll that selects it), in columns,
+/// with boxed punctuation and a heading with the guide words for each first letter.
+///
+/// When printed with a paged-media engine (e.g. WeasyPrint or Paged.js) the page headers also
+/// show the first and last prefix on each page.
+pub fn save_to_html<P: AsRef<Path>>(
+    entries: &[WordFollowEntry],
+    path: P,
+    metadata: Option<&Metadata>,
+    raw: bool,
+    style: &PageStyle,
+) -> io::Result<()> {
+    let title = metadata.map(|m| m.title.as_str()).unwrap_or_default();
+    let subtitle = booklet_subtitle(entries, metadata);
+
+    let mut html = String::new();
+    html.push_str("<!DOCTYPE html>\n<html lang=\"en\">\n<head>\n<meta charset=\"utf-8\">\n");
+    let _ = writeln!(
+        html,
+        "<title>{}</title>",
+        escape_html(&[title, subtitle.as_str()].join(" — "))
+    );
+    let _ = writeln!(html, "<style>\n{}</style>\n</head>\n<body>", css(style));
+
+    html.push_str("<section class=\"front\">\n");
+    let _ = writeln!(html, "<h1>{}</h1>", escape_html(title));
+    let _ = writeln!(html, "<p class=\"subtitle\">{}</p>", escape_html(&subtitle));
+    if let Some(author) = metadata
+        .map(|m| m.author.as_str())
+        .filter(|a| !a.is_empty())
+    {
+        let _ = writeln!(html, "<p class=\"author\">by {}</p>", escape_html(author));
+    }
+    let _ = writeln!(html, "<p>{}</p>", escape_html(&instructions(raw)));
+    html.push_str("</section>\n");
+
+    for (label, group) in letter_groups(entries) {
+        let _ = writeln!(
+            html,
+            "<section class=\"letter\">\n<h2>{} <span class=\"guide\">{}</span></h2>\n<div class=\"entries\">",
+            html_token(&label),
+            guide_words(group, html_prefix)
+        );
+        for entry in group {
+            let (total, followers) = roll_table(entry, raw);
+            let _ = write!(
+                html,
+                "<p class=\"entry\"><span class=\"prefix\">{}</span> <span class=\"dice\">{}</span>",
+                html_prefix(entry),
+                dice(total)
+            );
+            let show_counts = followers.len() > 1;
+            for (word, count) in &followers {
+                html.push_str(" <span class=\"follower\">");
+                if show_counts {
+                    let _ = write!(html, "<span class=\"count\">{}</span>|", count);
+                }
+                let _ = write!(html, "{}</span>", html_token(word));
+            }
+            html.push_str("</p>\n");
+        }
+        html.push_str("</div>\n</section>\n");
+    }
+    html.push_str("</body>\n</html>\n");
+
+    std::fs::write(path, html)
+}
+
+/// Writes the booklet as Markdown: a table of prefixes, dice and followers (with the highest
+/// roll that selects each) under a heading with the guide words for each first letter.
+/// Punctuation is set as code, which most editors show boxed.
+pub fn save_to_markdown<P: AsRef<Path>>(
+    entries: &[WordFollowEntry],
+    path: P,
+    metadata: Option<&Metadata>,
+    raw: bool,
+) -> io::Result<()> {
+    let mut writer = io::BufWriter::new(File::create(path)?);
+    let title = metadata.map(|m| m.title.as_str()).unwrap_or_default();
+    if !title.is_empty() {
+        writeln!(writer, "# {}\n", escape_markdown(title))?;
+    }
+    writeln!(
+        writer,
+        "_{}_\n",
+        escape_markdown(&booklet_subtitle(entries, metadata))
+    )?;
+    if let Some(author) = metadata
+        .map(|m| m.author.as_str())
+        .filter(|a| !a.is_empty())
+    {
+        writeln!(writer, "by {}\n", escape_markdown(author))?;
+    }
+    writeln!(writer, "{}", escape_markdown(&instructions(raw)))?;
+
+    for (label, group) in letter_groups(entries) {
+        writeln!(
+            writer,
+            "\n## {} ({})\n",
+            markdown_token(&label),
+            guide_words(group, markdown_prefix)
+        )?;
+        writeln!(writer, "| Prefix | Dice | Next word |")?;
+        writeln!(writer, "| --- | --- | --- |")?;
+        for entry in group {
+            let (total, followers) = roll_table(entry, raw);
+            let show_counts = followers.len() > 1;
+            let followers: Vec<String> = followers
+                .iter()
+                .map(|(word, count)| {
+                    if show_counts {
+                        format!("{} {}", count, markdown_token(word))
+                    } else {
+                        markdown_token(word)
+                    }
+                })
+                .collect();
+            writeln!(
+                writer,
+                "| **{}** | {} | {} |",
+                markdown_prefix(entry),
+                dice(total),
+                followers.join(" · ")
+            )?;
+        }
+    }
+    writer.flush()
+}
+
+fn booklet_subtitle(entries: &[WordFollowEntry], metadata: Option<&Metadata>) -> String {
+    match metadata
+        .map(|m| m.subtitle.as_str())
+        .filter(|s| !s.is_empty())
+    {
+        Some(subtitle) => subtitle.to_string(),
+        None => format!(
+            "A {} language model",
+            crate::model_type_str(entries.first().map_or(1, |e| e.prefix.len() + 1))
+        ),
+    }
+}
+
+fn instructions(raw: bool) -> String {
+    let roll = if raw {
+        "Pick a number from 1 to the entry's last count"
+    } else {
+        "Roll one d10 for each diamond and read the dice from left to right as a single number"
+    };
+    format!(
+        "To generate text, look up the entry for your current word(s). {}; the next word is the \
+         first one whose number is at least your roll. Write it down and repeat.",
+        roll
+    )
+}
+
+/// Entries grouped by the first character of their prefix, with the group's label
+fn letter_groups(entries: &[WordFollowEntry]) -> Vec<(String, &[WordFollowEntry])> {
+    let label = |entry: &WordFollowEntry| {
+        entry
+            .prefix
+            .first()
+            .and_then(|word| word.chars().next())
+            .map(|c| c.to_uppercase().to_string())
+            .unwrap_or_default()
+    };
+    entries
+        .chunk_by(|a, b| label(a) == label(b))
+        .map(|group| (label(&group[0]), group))
+        .collect()
+}
+
+/// The first and last prefix of a group, e.g. "a — azure"
+fn guide_words(group: &[WordFollowEntry], render: fn(&WordFollowEntry) -> String) -> String {
+    match (group.first(), group.last()) {
+        (Some(first), Some(last)) if first != last => {
+            format!("{} — {}", render(first), render(last))
+        }
+        (Some(first), _) => render(first),
+        _ => String::new(),
+    }
+}
+
+/// One diamond per d10 to roll, i.e. per digit of the total
+fn dice(total: usize) -> String {
+    "♦".repeat(total.to_string().len())
+}
+
+/// Punctuation tokens (e.g. "." or ","), which booklets set in a box
+fn is_punctuation(token: &str) -> bool {
+    token != GAP_TOKEN && !token.is_empty() && token.chars().all(|c| c.is_ascii_punctuation())
+}
+
+fn html_prefix(entry: &WordFollowEntry) -> String {
+    if entry.prefix.is_empty() {
+        return "<em>any word</em>".to_string();
+    }
+    let tokens: Vec<String> = entry.prefix.iter().map(|word| html_token(word)).collect();
+    tokens.join(" ")
+}
+
+fn html_token(token: &str) -> String {
+    if token == GAP_TOKEN {
+        "<span class=\"gap\"></span>".to_string()
+    } else if is_punctuation(token) {
+        format!("<span class=\"punct\">{}</span>", escape_html(token))
+    } else {
+        escape_html(token)
+    }
+}
+
+fn escape_html(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            _ => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+fn markdown_prefix(entry: &WordFollowEntry) -> String {
+    if entry.prefix.is_empty() {
+        return "_any word_".to_string();
+    }
+    let tokens: Vec<String> = entry
+        .prefix
+        .iter()
+        .map(|word| markdown_token(word))
+        .collect();
+    tokens.join(" ")
+}
+
+fn markdown_token(token: &str) -> String {
+    if token == GAP_TOKEN {
+        "\\_\\_\\_".to_string()
+    } else if is_punctuation(token) {
+        // Pipes are escaped even inside code in a table cell
+        let code = token.replace('|', "\\|");
+        if token.contains('`') {
+            format!("`` {} ``", code)
+        } else {
+            format!("`{}`", code)
+        }
+    } else {
+        escape_markdown(token)
+    }
+}
+
+fn escape_markdown(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        if matches!(
+            c,
+            '\\' | '`' | '*' | '_' | '|' | '[' | ']' | '<' | '>' | '#'
+        ) {
+            escaped.push('\\');
+        }
+        escaped.push(c);
+    }
+    escaped
+}
+
+/// CSS page sizes for typst's paper names
+fn css_page_size(paper: &str) -> String {
+    match paper.to_ascii_lowercase().as_str() {
+        "us-letter" => "letter".to_string(),
+        "us-legal" => "legal".to_string(),
+        other => other.to_uppercase(),
+    }
+}
+
+fn css(style: &PageStyle) -> String {
+    format!(
+        r#"@page {{
+  size: {size};
+  margin: 1.5cm;
+  @top-center {{ content: string(guide, first) " — " string(guide, last); }}
+  @bottom-center {{ content: counter(page) "/" counter(pages); }}
+}}
+body {{
+  font-family: "Libertinus Serif", "Linux Libertine", Georgia, serif;
+  font-size: {font_size}pt;
+  margin: 0 auto;
+  max-width: 60em;
+}}
+.front {{ break-after: page; font-size: 12pt; }}
+h2 {{ break-after: avoid; column-span: all; }}
+h2 .guide {{ font-weight: normal; font-size: 0.7em; }}
+.entries {{ column-count: {columns}; column-gap: 1.5em; }}
+.entry {{ margin: 0 0 0.4em; }}
+.prefix {{ font-weight: bold; font-size: 1.5em; string-set: guide content(text); }}
+.dice {{ font-size: 0.9em; }}
+.follower {{ white-space: nowrap; margin-right: 0.5em; }}
+.count {{ font-weight: 600; }}
+.punct {{
+  border: 0.25pt solid black;
+  border-radius: 1pt;
+  padding: 0 0.1em;
+  font-weight: bold;
+}}
+.gap {{ display: inline-block; width: 1.2em; border-bottom: 0.5pt solid black; }}
+@media print {{
+  body {{ max-width: none; }}
+  .letter {{ break-before: page; }}
+}}
+"#,
+        size = css_page_size(&style.paper),
+        font_size = style.font_size,
+        columns = style.columns,
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    fn entries() -> Vec<WordFollowEntry> {
+        let entry = |prefix: &str, followers: &[(&str, usize)]| WordFollowEntry {
+            prefix: vec![prefix.to_string()],
+            followers: followers.iter().map(|(w, c)| (w.to_string(), *c)).collect(),
+        };
+        vec![
+            entry(".", &[("the", 2)]),
+            entry("a", &[("cat", 3), (".", 1)]),
+            entry("and", &[("the", 1)]),
+            entry("the", &[("cat", 2), ("<b>", 1)]),
+        ]
+    }
+
+    #[test]
+    fn writes_html_booklets() -> io::Result<()> {
+        let dir = TempDir::new()?;
+        let path = dir.path().join("model.html");
+        let style = PageStyle {
+            paper: "a5".to_string(),
+            font_size: 9.0,
+            columns: 3,
+        };
+        save_to_html(&entries(), &path, None, false, &style)?;
+        let html = std::fs::read_to_string(&path)?;
+
+        assert!(html.contains("size: A5;"));
+        assert!(html.contains("column-count: 3;"));
+        assert!(html.contains("<p class=\"subtitle\">A bigram language model</p>"));
+        // Guide words for each first letter, boxed punctuation and d10-scaled rolls
+        assert!(html.contains("A <span class=\"guide\">a — and</span>"));
+        assert!(html.contains(
+            "<span class=\"prefix\"><span class=\"punct\">.</span></span> <span class=\"dice\">♦</span> <span class=\"follower\">the</span>"
+        ));
+        assert!(html.contains("<span class=\"count\">7</span>|cat"));
+        assert!(html.contains("<span class=\"count\">9</span>|<span class=\"punct\">.</span>"));
+        // Words are escaped
+        assert!(html.contains("|&lt;b&gt;</span>"));
+
+        save_to_html(&entries(), &path, None, true, &style)?;
+        let html = std::fs::read_to_string(&path)?;
+        assert!(html.contains("<span class=\"count\">3</span>|cat"));
+        assert!(html.contains("<span class=\"count\">4</span>|<span class=\"punct\">.</span>"));
+
+        Ok(())
+    }
+
+    #[test]
+    fn writes_markdown_booklets() -> io::Result<()> {
+        let dir = TempDir::new()?;
+        let path = dir.path().join("model.md");
+        save_to_markdown(&entries(), &path, None, false)?;
+        let markdown = std::fs::read_to_string(&path)?;
+
+        assert!(markdown.contains("## A (a — and)\n\n| Prefix | Dice | Next word |"));
+        assert!(markdown.contains("| **`.`** | ♦ | the |"));
+        assert!(markdown.contains("| **a** | ♦ | 7 cat · 9 `.` |"));
+        assert!(markdown.contains("| **the** | ♦ | 6 cat · 9 \\<b\\> |"));
+
+        let mut gapped = entries();
+        gapped[3].prefix = vec!["the".to_string(), GAP_TOKEN.to_string()];
+        save_to_markdown(&gapped, &path, None, true)?;
+        let markdown = std::fs::read_to_string(&path)?;
+        assert!(markdown.contains("| **the \\_\\_\\_** | ♦ | 2 cat · 3 \\<b\\> |"));
+
+        Ok(())
+    }
+}
@@ -0,0 +1,287 @@
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs::File;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use crate::manifest::{FNV_OFFSET, fnv1a};
+use crate::{SplitBoundary, TEMPLATE_FILES};
+
+/// A list of booklets to build, read from a TOML file such as:
+///
+/// ```toml
+/// data_dir = "../data"
+///
+/// [[book]]
+/// corpus = "frankenstein.txt"
+/// n = 3
+/// books = 2
+/// ```
+///
+/// Relative paths are relative to the manifest file.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct BuildManifest {
+    /// Directory holding the corpus files
+    #[serde(default = "default_dir")]
+    pub data_dir: PathBuf,
+    /// Output directory: model JSON goes in `json/`, statistics in `stats/` and PDFs in `pdf/`
+    #[serde(default = "default_out_dir")]
+    pub out_dir: PathBuf,
+    /// Typst template for the booklets (default: the bundled `book.typ`)
+    #[serde(default)]
+    pub template: Option<PathBuf>,
+    #[serde(rename = "book", default)]
+    pub targets: Vec<BuildTarget>,
+}
+
+/// One booklet (or set of split books) in a [`BuildManifest`]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct BuildTarget {
+    /// Output name (default: `<corpus stem>-<n>-<books>`, e.g. "frankenstein-3-2")
+    #[serde(default)]
+    pub name: Option<String>,
+    /// Text file with frontmatter, relative to `data_dir`
+    pub corpus: PathBuf,
+    #[serde(default = "default_n")]
+    pub n: usize,
+    #[serde(default = "default_books")]
+    pub books: usize,
+    /// Where books may be split: "entry", "letter" or "word"
+    #[serde(default)]
+    pub split_at: Option<SplitBoundary>,
+    #[serde(default)]
+    pub pages_per_book: Option<usize>,
+    #[serde(default)]
+    pub paper: Option<String>,
+    #[serde(default)]
+    pub font_size: Option<f64>,
+    #[serde(default)]
+    pub columns: Option<usize>,
+    /// Scale counts for d10 dice, or keep the raw counts
+    #[serde(default)]
+    pub dice: Dice,
+    #[serde(default)]
+    pub punctuation: Option<String>,
+    #[serde(default)]
+    pub prune_vocab: Option<usize>,
+    #[serde(default)]
+    pub min_count: Option<usize>,
+    #[serde(default)]
+    pub max_followers: Option<usize>,
+    #[serde(default)]
+    pub max_pages: Option<usize>,
+    /// Also typeset the PDFs (set to false for JSON only)
+    #[serde(default = "default_pdf")]
+    pub pdf: bool,
+}
+
+/// How a [`BuildTarget`]'s counts are written
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Dice {
+    #[default]
+    D10,
+    Raw,
+}
+
+fn default_dir() -> PathBuf {
+    PathBuf::from(".")
+}
+
+fn default_out_dir() -> PathBuf {
+    PathBuf::from("out")
+}
+
+fn default_n() -> usize {
+    2
+}
+
+fn default_books() -> usize {
+    1
+}
+
+fn default_pdf() -> bool {
+    true
+}
+
+impl BuildManifest {
+    /// Reads a manifest, resolving its paths relative to the manifest's directory
+    pub fn load<P: AsRef<Path>>(path: P) -> io::Result<Self> {
+        let path = path.as_ref();
+        let text = std::fs::read_to_string(path)?;
+        let mut manifest: BuildManifest = toml::from_str(&text).map_err(|e| {
+            io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("Invalid build manifest {}: {}", path.display(), e),
+            )
+        })?;
+
+        let base = path.parent().unwrap_or(Path::new("."));
+        manifest.data_dir = base.join(&manifest.data_dir);
+        manifest.out_dir = base.join(&manifest.out_dir);
+        manifest.template = manifest.template.map(|template| base.join(template));
+        Ok(manifest)
+    }
+
+    pub fn json_dir(&self) -> PathBuf {
+        self.out_dir.join("json")
+    }
+
+    pub fn stats_dir(&self) -> PathBuf {
+        self.out_dir.join("stats")
+    }
+
+    pub fn pdf_dir(&self) -> PathBuf {
+        self.out_dir.join("pdf")
+    }
+
+    /// Where the fingerprints of the last successful builds are kept
+    pub fn state_path(&self) -> PathBuf {
+        self.out_dir.join(".build-state.json")
+    }
+
+    pub fn corpus_path(&self, target: &BuildTarget) -> PathBuf {
+        self.data_dir.join(&target.corpus)
+    }
+
+    /// A fingerprint of everything a target's outputs depend on: its settings, the corpus,
+    /// the bundled and custom templates (if typesetting) and the version of this tool
+    pub fn fingerprint(&self, target: &BuildTarget) -> io::Result<String> {
+        let mut hash = fnv1a(FNV_OFFSET, env!("CARGO_PKG_VERSION").as_bytes());
+        hash = fnv1a(hash, &serde_json::to_vec(target)?);
+        hash = fnv1a(hash, &std::fs::read(self.corpus_path(target))?);
+        if target.pdf {
+            // The bundled templates can change without a new version during development
+            for (name, contents) in TEMPLATE_FILES {
+                hash = fnv1a(hash, name.as_bytes());
+                hash = fnv1a(hash, contents);
+            }
+            if let Some(template) = &self.template {
+                hash = fnv1a(hash, &std::fs::read(template)?);
+            }
+        }
+        Ok(format!("{:016x}", hash))
+    }
+}
+
+impl BuildTarget {
+    /// The target's output name, e.g. "frankenstein-3-2"
+    pub fn name(&self) -> String {
+        self.name.clone().unwrap_or_else(|| {
+            let stem = self
+                .corpus
+                .file_stem()
+                .and_then(|s| s.to_str())
+                .unwrap_or("model");
+            format!("{}-{}-{}", stem, self.n, self.books)
+        })
+    }
+}
+
+/// What was built for each target name, so unchanged targets can be skipped
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct BuildState {
+    pub targets: BTreeMap<String, BuildRecord>,
+}
+
+/// The fingerprint and output files of a target's last successful build
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct BuildRecord {
+    pub fingerprint: String,
+    pub outputs: Vec<PathBuf>,
+}
+
+impl BuildState {
+    /// Reads the state file, or starts afresh if there isn't one
+    pub fn load<P: AsRef<Path>>(path: P) -> io::Result<Self> {
+        match File::open(path) {
+            Ok(file) => Ok(serde_json::from_reader(file)?),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(BuildState::default()),
+            Err(e) => Err(e),
+        }
+    }
+
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> io::Result<()> {
+        let file = File::create(path)?;
+        serde_json::to_writer_pretty(file, self)?;
+        Ok(())
+    }
+
+    /// Whether `name` was last built with this fingerprint and its outputs still exist
+    pub fn is_up_to_date(&self, name: &str, fingerprint: &str) -> bool {
+        self.targets.get(name).is_some_and(|record| {
+            record.fingerprint == fingerprint && record.outputs.iter().all(|path| path.exists())
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Write;
+    use tempfile::TempDir;
+
+    #[test]
+    fn loads_manifest_and_tracks_fingerprints() -> io::Result<()> {
+        let dir = TempDir::new()?;
+        std::fs::create_dir(dir.path().join("data"))?;
+        let corpus = dir.path().join("data").join("spot.txt");
+        std::fs::write(&corpus, "---\ntitle: Spot\n---\nSee Spot run.\n")?;
+
+        let manifest_path = dir.path().join("books.toml");
+        let mut file = File::create(&manifest_path)?;
+        writeln!(file, "data_dir = \"data\"")?;
+        writeln!(
+            file,
+            "[[book]]\ncorpus = \"spot.txt\"\nn = 3\nbooks = 2\nsplit_at = \"letter\""
+        )?;
+        writeln!(
+            file,
+            "[[book]]\nname = \"tiny\"\ncorpus = \"spot.txt\"\ndice = \"raw\""
+        )?;
+
+        let manifest = BuildManifest::load(&manifest_path)?;
+        assert_eq!(manifest.out_dir, dir.path().join("out"));
+        assert_eq!(manifest.targets.len(), 2);
+        assert_eq!(manifest.targets[0].name(), "spot-3-2");
+        assert_eq!(manifest.targets[1].name(), "tiny");
+        assert_eq!(manifest.targets[0].split_at, Some(SplitBoundary::Letter));
+        assert_eq!(manifest.targets[1].n, 2);
+        assert_eq!(manifest.targets[1].split_at, None);
+        assert_eq!(manifest.targets[1].dice, Dice::Raw);
+        assert_eq!(manifest.corpus_path(&manifest.targets[0]), corpus);
+
+        // The fingerprint changes with the settings and with the corpus
+        let before = manifest.fingerprint(&manifest.targets[0])?;
+        assert_eq!(manifest.fingerprint(&manifest.targets[0])?, before);
+        assert_ne!(manifest.fingerprint(&manifest.targets[1])?, before);
+        std::fs::write(&corpus, "---\ntitle: Spot\n---\nSee Spot jump.\n")?;
+        let after = manifest.fingerprint(&manifest.targets[0])?;
+        assert_ne!(after, before);
+
+        let mut state = BuildState::load(manifest.state_path())?;
+        assert!(!state.is_up_to_date("spot-3-2", &after));
+        state.targets.insert(
+            "spot-3-2".to_string(),
+            BuildRecord {
+                fingerprint: after.clone(),
+                outputs: vec![corpus.clone()],
+            },
+        );
+        assert!(state.is_up_to_date("spot-3-2", &after));
+        assert!(!state.is_up_to_date("spot-3-2", &before));
+
+        // Unknown keys are mistakes, not silently ignored
+        std::fs::write(&manifest_path, "[[book]]\ncorpus = \"a.txt\"\nbook = 2\n")?;
+        assert!(BuildManifest::load(&manifest_path).is_err());
+        std::fs::write(
+            &manifest_path,
+            "[[book]]\ncorpus = \"a.txt\"\nsplit_at = \"page\"\n",
+        )?;
+        assert!(BuildManifest::load(&manifest_path).is_err());
+
+        Ok(())
+    }
+}
@@ -0,0 +1,134 @@
+use rand::Rng;
+use serde::Serialize;
+use std::io;
+
+use crate::generate::generate;
+use crate::model::vocabulary;
+use crate::stats::kl_divergence;
+use crate::{NGramCounter, WordFollowEntry, stats_from_entries};
+
+/// Number of generated tokens kept as a sample of each generation's text
+const SAMPLE_TOKENS: usize = 30;
+
+/// Options for [`simulate_collapse`]
+#[derive(Debug, Clone)]
+pub struct CollapseOptions {
+    /// Size of n-gram used when retraining
+    pub n: usize,
+    /// Number of generations to train after generation 0
+    pub generations: usize,
+    /// Number of tokens generated (and retrained on) per generation
+    pub tokens_per_generation: usize,
+    /// Punctuation characters kept as separate tokens when retraining
+    pub punctuation: Vec<char>,
+}
+
+/// Diversity measures for one generation of the simulation
+#[derive(Debug, Clone, Serialize)]
+pub struct GenerationStats {
+    /// 0 for the original model, then 1, 2, ... for each retrained model
+    pub generation: usize,
+    /// Number of n-gram occurrences the model was trained on
+    pub ngram_occurrences: usize,
+    /// Number of distinct tokens in the model
+    pub vocabulary_size: usize,
+    /// Number of distinct prefixes
+    pub unique_prefixes: usize,
+    /// Number of distinct n-grams (prefix + follower)
+    pub unique_ngrams: usize,
+    /// Follower entropy in bits, weighted by prefix frequency
+    pub entropy: f64,
+    /// KL divergence (bits) of generation 0 from this generation (see [`crate::kl_divergence`]);
+    /// the smoothing makes this slightly above zero even for generation 0 itself
+    pub kl_divergence: f64,
+    /// The start of the text this generation was trained on (empty for generation 0)
+    pub sample: String,
+}
+
+/// Repeatedly generates text from a model and retrains a new model on it (the Synthetic Data
+/// handout), recording how the diversity of each generation compares to the original.
+pub fn simulate_collapse<R: Rng + ?Sized>(
+    original: &[WordFollowEntry],
+    options: &CollapseOptions,
+    rng: &mut R,
+) -> io::Result<Vec<GenerationStats>> {
+    let mut results = vec![generation_stats(0, original, original, String::new())];
+    let mut model = original.to_vec();
+
+    for generation in 1..=options.generations {
+        let tokens = generate(&model, options.tokens_per_generation, rng)?;
+        let sample = tokens
+            .iter()
+            .take(SAMPLE_TOKENS)
+            .cloned()
+            .collect::<Vec<_>>()
+            .join(" ");
+
+        let mut counter = NGramCounter::new(options.n, options.punctuation.clone())?;
+        counter.process_line(&tokens.join(" "));
+        model = counter.get_entries();
+
+        results.push(generation_stats(generation, &model, original, sample));
+    }
+
+    Ok(results)
+}
+
+fn generation_stats(
+    generation: usize,
+    entries: &[WordFollowEntry],
+    original: &[WordFollowEntry],
+    sample: String,
+) -> GenerationStats {
+    let stats = stats_from_entries(entries);
+    let entropy = stats
+        .corpus
+        .as_ref()
+        .map(|corpus| corpus.entropy.weighted_mean)
+        .unwrap_or(0.0);
+
+    GenerationStats {
+        generation,
+        ngram_occurrences: stats.total_ngram_occurrences,
+        vocabulary_size: vocabulary(entries).len(),
+        unique_prefixes: stats.unique_ngrams,
+        unique_ngrams: entries.iter().map(|entry| entry.followers.len()).sum(),
+        entropy,
+        kl_divergence: kl_divergence(original, entries),
+        sample,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::SeedableRng;
+    use rand::rngs::StdRng;
+
+    #[test]
+    fn vocabulary_never_grows_across_generations() {
+        let mut counter = NGramCounter::new(2, vec![',', '.']).unwrap();
+        counter.process_line(
+            "the cat sat on the mat. the dog sat on the log. a cat and a dog ran to the park.",
+        );
+        let options = CollapseOptions {
+            n: 2,
+            generations: 5,
+            tokens_per_generation: 40,
+            punctuation: vec![',', '.'],
+        };
+
+        let mut rng = StdRng::seed_from_u64(3);
+        let results = simulate_collapse(&counter.get_entries(), &options, &mut rng).unwrap();
+
+        assert_eq!(results.len(), 6);
+        assert_eq!(results[0].vocabulary_size, 13);
+        assert!(results[0].sample.is_empty());
+        assert_eq!(results[3].sample.split(' ').count(), 30);
+        // Retrained models can only use words the previous generation produced
+        for pair in results.windows(2) {
+            assert!(pair[1].vocabulary_size <= pair[0].vocabulary_size);
+        }
+        assert!(results[5].kl_divergence > results[0].kl_divergence);
+    }
+}
@@ -0,0 +1,257 @@
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::fs;
+use std::io;
+use std::path::Path;
+
+use crate::WordFollowEntry;
+
+const BUILTIN_VERBS: &[&str] = &[
+    "am", "are", "ate", "be", "came", "come", "did", "do", "does", "eat", "get", "go", "goes",
+    "got", "had", "has", "have", "is", "jump", "jumped", "jumps", "know", "like", "look", "looked",
+    "made", "make", "play", "played", "ran", "run", "runs", "said", "sat", "saw", "say", "see",
+    "sees", "sit", "take", "think", "took", "want", "was", "went", "were",
+];
+
+const BUILTIN_PRONOUNS: &[&str] = &[
+    "he", "her", "him", "i", "it", "me", "she", "them", "they", "us", "we", "you",
+];
+
+const BUILTIN_PREPOSITIONS: &[&str] = &[
+    "about", "after", "at", "before", "by", "down", "for", "from", "in", "into", "near", "of",
+    "off", "on", "over", "through", "to", "under", "up", "with",
+];
+
+/// A named list of words; the words that follow them are counted in an "after ..." column
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct WordClass {
+    pub name: String,
+    pub words: BTreeSet<String>,
+}
+
+/// The word classes used for context columns, in column order
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct WordClasses {
+    pub classes: Vec<WordClass>,
+}
+
+impl WordClasses {
+    /// A small built-in lexicon with the handout's verb, pronoun and preposition columns
+    pub fn builtin() -> Self {
+        let class = |name: &str, words: &[&str]| WordClass {
+            name: name.to_string(),
+            words: words.iter().map(|w| w.to_string()).collect(),
+        };
+        WordClasses {
+            classes: vec![
+                class("verb", BUILTIN_VERBS),
+                class("pronoun", BUILTIN_PRONOUNS),
+                class("preposition", BUILTIN_PREPOSITIONS),
+            ],
+        }
+    }
+
+    /// Reads word classes from a YAML file mapping each class name to a list of words, e.g.
+    ///
+    /// ```yaml
+    /// pronoun: [i, you, they]
+    /// colour: [red, green, blue]
+    /// ```
+    pub fn from_yaml_file<P: AsRef<Path>>(path: P) -> io::Result<Self> {
+        let path = path.as_ref();
+        let contents = fs::read_to_string(path)?;
+        let mapping: serde_yaml::Mapping = serde_yaml::from_str(&contents).map_err(|e| {
+            io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("{} is not a valid word class file: {e}", path.display()),
+            )
+        })?;
+
+        let mut classes = Vec::new();
+        for (name, words) in mapping {
+            let (Some(name), Some(words)) = (name.as_str(), words.as_sequence()) else {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    format!(
+                        "{}: each entry should map a class name to a list of words",
+                        path.display()
+                    ),
+                ));
+            };
+            classes.push(WordClass {
+                name: name.to_string(),
+                words: words
+                    .iter()
+                    .filter_map(|w| w.as_str())
+                    .map(str::to_lowercase)
+                    .collect(),
+            });
+        }
+
+        Ok(WordClasses { classes })
+    }
+
+    /// Adds the classes from `other`, replacing any existing class with the same name
+    pub fn merge(&mut self, other: WordClasses) {
+        for class in other.classes {
+            match self.classes.iter_mut().find(|c| c.name == class.name) {
+                Some(existing) => *existing = class,
+                None => self.classes.push(class),
+            }
+        }
+    }
+
+    /// Names of the classes containing `word` (case-insensitive)
+    pub fn classes_of<'a>(&'a self, word: &str) -> impl Iterator<Item = &'a str> {
+        let word = word.to_lowercase();
+        self.classes
+            .iter()
+            .filter(move |class| class.words.contains(&word))
+            .map(|class| class.name.as_str())
+    }
+}
+
+/// Context column counts, as described in the "Context Columns" handout.
+///
+/// Each time a word from a class is followed by another word, the follower's count in
+/// that class's "after ..." column goes up by one.
+#[derive(Debug, Clone, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub struct ContextColumns {
+    /// Word lists for each column, in column order
+    pub classes: Vec<WordClass>,
+    /// `counts[class][follower]` is the number of times `follower` came after a word in `class`
+    pub counts: BTreeMap<String, BTreeMap<String, usize>>,
+}
+
+impl ContextColumns {
+    pub fn new(classes: WordClasses) -> Self {
+        ContextColumns {
+            classes: classes.classes,
+            counts: BTreeMap::new(),
+        }
+    }
+
+    /// Records that `follower` came after `previous`
+    pub fn record(&mut self, previous: &str, follower: &str) {
+        let previous = previous.to_lowercase();
+        for class in &self.classes {
+            if class.words.contains(&previous) {
+                *self
+                    .counts
+                    .entry(class.name.clone())
+                    .or_default()
+                    .entry(follower.to_string())
+                    .or_default() += 1;
+            }
+        }
+    }
+
+    /// The context counts for `follower`, one per class in column order
+    pub fn row(&self, follower: &str) -> Vec<usize> {
+        self.classes
+            .iter()
+            .map(|class| {
+                self.counts
+                    .get(&class.name)
+                    .and_then(|column| column.get(follower))
+                    .copied()
+                    .unwrap_or(0)
+            })
+            .collect()
+    }
+}
+
+/// Combines the context columns into the follower counts, as in the handout's generation step.
+///
+/// For each prefix whose last word belongs to a class, every count in that class's column is
+/// added to the prefix's followers (adding new followers where needed).
+///
+/// This follows the handout's rules rather than its worked example. The example only tallies
+/// some followers in the context columns and only adds to words already in the row, so for
+/// "I run fast. You run to me." it gives `run` -> fast 1, to 2 where this gives fast 2, to 2.
+pub fn combine_context(
+    entries: &[WordFollowEntry],
+    context: &ContextColumns,
+) -> Vec<WordFollowEntry> {
+    entries
+        .iter()
+        .map(|entry| {
+            let Some(last) = entry.prefix.last() else {
+                return entry.clone();
+            };
+            let last = last.to_lowercase();
+
+            let mut combined: HashMap<String, usize> = entry.followers.iter().cloned().collect();
+            for class in context.classes.iter().filter(|c| c.words.contains(&last)) {
+                for (follower, count) in context.counts.get(&class.name).into_iter().flatten() {
+                    *combined.entry(follower.clone()).or_default() += count;
+                }
+            }
+
+            let mut followers: Vec<(String, usize)> = combined.into_iter().collect();
+            followers.sort_by(|a, b| {
+                b.1.cmp(&a.1)
+                    .then_with(|| a.0.to_lowercase().cmp(&b.0.to_lowercase()))
+            });
+
+            WordFollowEntry {
+                prefix: entry.prefix.clone(),
+                followers,
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::NGramCounter;
+
+    #[test]
+    fn counts_and_combines_context_columns() {
+        // The training text from the handout. Its worked example misses the "after verb" count
+        // for "fast" and adds nothing to "me", so the combined counts below differ from it
+        let mut counter = NGramCounter::new(2, vec![',', '.']).unwrap();
+        counter.enable_context(WordClasses::builtin());
+        counter.process_line("I run fast. You run to me.");
+
+        let context = counter.get_context().unwrap();
+        // run (verb) -> fast, to; i/you (pronouns) -> run; to (preposition) -> me
+        assert_eq!(context.row("fast"), vec![1, 0, 0]);
+        assert_eq!(context.row("to"), vec![1, 0, 0]);
+        assert_eq!(context.row("run"), vec![0, 2, 0]);
+        assert_eq!(context.row("me"), vec![0, 0, 1]);
+
+        let combined = combine_context(&counter.get_entries(), context);
+        let run = combined.iter().find(|e| e.prefix == ["run"]).unwrap();
+        assert_eq!(
+            run.followers,
+            vec![("fast".to_string(), 2), ("to".to_string(), 2)]
+        );
+        // "me" is a pronoun, so it picks up the "after pronoun" column (run 2, "." 1)
+        let me = combined.iter().find(|e| e.prefix == ["me"]).unwrap();
+        assert_eq!(
+            me.followers,
+            vec![(".".to_string(), 2), ("run".to_string(), 2)]
+        );
+    }
+
+    #[test]
+    fn file_classes_replace_builtin_ones() -> io::Result<()> {
+        let file = tempfile::NamedTempFile::new()?;
+        fs::write(file.path(), "pronoun: [We, us]\ncolour: [red]\n")?;
+
+        let mut classes = WordClasses::builtin();
+        classes.merge(WordClasses::from_yaml_file(file.path())?);
+
+        let names: Vec<&str> = classes.classes.iter().map(|c| c.name.as_str()).collect();
+        assert_eq!(names, vec!["verb", "pronoun", "preposition", "colour"]);
+        assert_eq!(
+            classes.classes_of("WE").collect::<Vec<_>>(),
+            vec!["pronoun"]
+        );
+        assert_eq!(classes.classes_of("you").count(), 0);
+
+        Ok(())
+    }
+}
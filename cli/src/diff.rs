@@ -0,0 +1,266 @@
+use serde::Serialize;
+use std::collections::{BTreeMap, BTreeSet};
+use std::io::{self, Write};
+
+use crate::WordFollowEntry;
+use crate::model::vocabulary;
+use crate::stats::{js_bits, js_divergence, kl_divergence, smoothed_kl_bits};
+
+/// Change in the probability of one follower of a shared prefix
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ProbabilityShift {
+    pub follower: String,
+    /// Probability of the follower after this prefix in model A
+    pub a: f64,
+    /// Probability of the follower after this prefix in model B
+    pub b: f64,
+}
+
+/// Comparison of one prefix found in both models
+#[derive(Debug, Clone, Serialize)]
+pub struct PrefixDiff {
+    pub prefix: Vec<String>,
+    /// Followers seen after this prefix only in model A
+    pub only_in_a: Vec<String>,
+    /// Followers seen after this prefix only in model B
+    pub only_in_b: Vec<String>,
+    /// The followers whose probability changed most, largest change first
+    pub largest_shifts: Vec<ProbabilityShift>,
+    /// KL divergence D(A || B) of the follower distributions in bits (B add-one smoothed)
+    pub kl_divergence: f64,
+    /// Jensen-Shannon divergence of the follower distributions in bits
+    pub js_divergence: f64,
+}
+
+/// Differences between two models (A and B)
+#[derive(Debug, Clone, Serialize)]
+pub struct ModelDiff {
+    pub vocabulary_only_in_a: Vec<String>,
+    pub vocabulary_only_in_b: Vec<String>,
+    pub prefixes_only_in_a: Vec<Vec<String>>,
+    pub prefixes_only_in_b: Vec<Vec<String>>,
+    /// Prefixes found in both models, most changed (by JS divergence) first
+    pub shared_prefixes: Vec<PrefixDiff>,
+    /// KL divergence D(A || B) of the n-gram distributions (see [`crate::kl_divergence`])
+    pub kl_divergence: f64,
+    /// Jensen-Shannon divergence of the n-gram distributions
+    pub js_divergence: f64,
+}
+
+/// Compares two models, keeping the `top_shifts` largest probability shifts for each shared prefix
+pub fn diff_models(a: &[WordFollowEntry], b: &[WordFollowEntry], top_shifts: usize) -> ModelDiff {
+    let vocabulary_a = vocabulary(a);
+    let vocabulary_b = vocabulary(b);
+
+    let followers =
+        |entries: &[WordFollowEntry]| -> BTreeMap<Vec<String>, BTreeMap<String, usize>> {
+            entries
+                .iter()
+                .map(|entry| {
+                    (
+                        entry.prefix.clone(),
+                        entry.followers.iter().cloned().collect(),
+                    )
+                })
+                .collect()
+        };
+    let rows_a = followers(a);
+    let rows_b = followers(b);
+
+    let mut shared_prefixes: Vec<PrefixDiff> = rows_a
+        .iter()
+        .filter_map(|(prefix, row_a)| {
+            let row_b = rows_b.get(prefix)?;
+            Some(prefix_diff(prefix, row_a, row_b, top_shifts))
+        })
+        .collect();
+    shared_prefixes.sort_by(|x, y| {
+        y.js_divergence
+            .total_cmp(&x.js_divergence)
+            .then_with(|| x.prefix.cmp(&y.prefix))
+    });
+
+    ModelDiff {
+        vocabulary_only_in_a: vocabulary_a.difference(&vocabulary_b).cloned().collect(),
+        vocabulary_only_in_b: vocabulary_b.difference(&vocabulary_a).cloned().collect(),
+        prefixes_only_in_a: only_in(&rows_a, &rows_b),
+        prefixes_only_in_b: only_in(&rows_b, &rows_a),
+        shared_prefixes,
+        kl_divergence: kl_divergence(a, b),
+        js_divergence: js_divergence(a, b),
+    }
+}
+
+fn only_in<V>(x: &BTreeMap<Vec<String>, V>, y: &BTreeMap<Vec<String>, V>) -> Vec<Vec<String>> {
+    x.keys().filter(|k| !y.contains_key(*k)).cloned().collect()
+}
+
+fn prefix_diff(
+    prefix: &[String],
+    row_a: &BTreeMap<String, usize>,
+    row_b: &BTreeMap<String, usize>,
+    top_shifts: usize,
+) -> PrefixDiff {
+    let total_a = row_a.values().sum::<usize>().max(1) as f64;
+    let total_b = row_b.values().sum::<usize>().max(1) as f64;
+
+    let support: BTreeSet<&String> = row_a.keys().chain(row_b.keys()).collect();
+    let mut shifts: Vec<ProbabilityShift> = support
+        .into_iter()
+        .map(|follower| ProbabilityShift {
+            follower: follower.clone(),
+            a: row_a.get(follower).copied().unwrap_or(0) as f64 / total_a,
+            b: row_b.get(follower).copied().unwrap_or(0) as f64 / total_b,
+        })
+        .collect();
+    shifts.sort_by(|x, y| (y.b - y.a).abs().total_cmp(&(x.b - x.a).abs()));
+    shifts.retain(|shift| shift.a != shift.b);
+    shifts.truncate(top_shifts);
+
+    PrefixDiff {
+        prefix: prefix.to_vec(),
+        only_in_a: row_a
+            .keys()
+            .filter(|w| !row_b.contains_key(*w))
+            .cloned()
+            .collect(),
+        only_in_b: row_b
+            .keys()
+            .filter(|w| !row_a.contains_key(*w))
+            .cloned()
+            .collect(),
+        largest_shifts: shifts,
+        kl_divergence: smoothed_kl_bits(row_a, row_b),
+        js_divergence: js_bits(row_a, row_b),
+    }
+}
+
+impl ModelDiff {
+    /// Writes a plain-text summary for printing, listing at most `limit` items in each section
+    pub fn write_summary<W: Write>(&self, writer: &mut W, limit: usize) -> io::Result<()> {
+        writeln!(writer, "Overall divergence")?;
+        writeln!(writer, "------------------")?;
+        writeln!(writer, "KL(A || B): {:.3} bits", self.kl_divergence)?;
+        writeln!(writer, "JS(A, B):   {:.3} bits", self.js_divergence)?;
+
+        let list = |items: &[String]| -> String {
+            if items.is_empty() {
+                return "none".to_string();
+            }
+            let mut shown: Vec<&str> = items.iter().take(limit).map(String::as_str).collect();
+            if items.len() > limit {
+                shown.push("...");
+            }
+            shown.join(", ")
+        };
+        let prefixes = |items: &[Vec<String>]| -> Vec<String> {
+            items.iter().map(|prefix| prefix.join(" ")).collect()
+        };
+
+        writeln!(writer, "\nVocabulary")?;
+        writeln!(writer, "----------")?;
+        writeln!(
+            writer,
+            "Only in A ({}): {}",
+            self.vocabulary_only_in_a.len(),
+            list(&self.vocabulary_only_in_a)
+        )?;
+        writeln!(
+            writer,
+            "Only in B ({}): {}",
+            self.vocabulary_only_in_b.len(),
+            list(&self.vocabulary_only_in_b)
+        )?;
+
+        writeln!(writer, "\nPrefixes")?;
+        writeln!(writer, "--------")?;
+        writeln!(writer, "Shared: {}", self.shared_prefixes.len())?;
+        writeln!(
+            writer,
+            "Only in A ({}): {}",
+            self.prefixes_only_in_a.len(),
+            list(&prefixes(&self.prefixes_only_in_a))
+        )?;
+        writeln!(
+            writer,
+            "Only in B ({}): {}",
+            self.prefixes_only_in_b.len(),
+            list(&prefixes(&self.prefixes_only_in_b))
+        )?;
+
+        writeln!(writer, "\nMost changed shared prefixes")?;
+        writeln!(writer, "----------------------------")?;
+        for prefix in self
+            .shared_prefixes
+            .iter()
+            .take(limit)
+            .filter(|p| p.js_divergence > 0.0)
+        {
+            let shifts: Vec<String> = prefix
+                .largest_shifts
+                .iter()
+                .map(|s| format!("{} {:.2} -> {:.2}", s.follower, s.a, s.b))
+                .collect();
+            writeln!(
+                writer,
+                "'{}' (JS {:.3}): {}",
+                prefix.prefix.join(" "),
+                prefix.js_divergence,
+                shifts.join(", ")
+            )?;
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::NGramCounter;
+
+    fn entries(text: &str) -> Vec<WordFollowEntry> {
+        let mut counter = NGramCounter::new(2, vec![',', '.']).unwrap();
+        counter.process_line(text);
+        counter.get_entries()
+    }
+
+    #[test]
+    fn compares_original_and_synthetic_models() {
+        // The Synthetic Data handout example
+        let a = entries("See Spot run. See Spot jump.");
+        let b = entries("See run. Run spot. Spot run run.");
+        let diff = diff_models(&a, &b, 2);
+
+        assert_eq!(diff.vocabulary_only_in_a, vec!["jump"]);
+        assert!(diff.vocabulary_only_in_b.is_empty());
+        assert_eq!(diff.prefixes_only_in_a, vec![vec!["jump".to_string()]]);
+
+        let spot = diff
+            .shared_prefixes
+            .iter()
+            .find(|p| p.prefix == ["spot"])
+            .unwrap();
+        // "spot" is followed by run/jump in A, but by run/"." in B
+        assert_eq!(spot.only_in_a, vec!["jump"]);
+        assert_eq!(spot.only_in_b, vec!["."]);
+        assert_eq!(
+            spot.largest_shifts[0],
+            ProbabilityShift {
+                follower: ".".to_string(),
+                a: 0.0,
+                b: 0.5
+            }
+        );
+        assert!((spot.js_divergence - 0.5).abs() < 1e-9);
+
+        assert!(diff.js_divergence > 0.0 && diff.js_divergence <= 1.0);
+        assert_eq!(diff_models(&a, &a, 2).js_divergence, 0.0);
+
+        let mut summary = Vec::new();
+        diff.write_summary(&mut summary, 5).unwrap();
+        let summary = String::from_utf8(summary).unwrap();
+        assert!(summary.contains("Only in A (1): jump"));
+        assert!(summary.contains("'spot' (JS 0.500): . 0.00 -> 0.50, jump 0.50 -> 0.00"));
+    }
+}
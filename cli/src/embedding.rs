@@ -0,0 +1,283 @@
+use serde::Serialize;
+use std::collections::{BTreeSet, HashMap};
+use std::io::{self, Write};
+
+use crate::WordFollowEntry;
+use crate::context::ContextColumns;
+use crate::export::write_record;
+
+/// What goes in each cell of a row embedding
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+#[cfg_attr(feature = "clap", derive(clap::ValueEnum))]
+pub enum EmbeddingValues {
+    /// The counts from the model grid
+    Raw,
+    /// Counts divided by the row total (i.e. follower probabilities)
+    Normalised,
+    /// Counts scaled so each row totals 10^k - 1, as printed in the booklets
+    Scaled,
+}
+
+/// How to measure the distance between two embeddings
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+#[cfg_attr(feature = "clap", derive(clap::ValueEnum))]
+pub enum DistanceMetric {
+    /// Sum of absolute differences (as in the Word Embeddings handout)
+    L1,
+    /// Euclidean distance
+    L2,
+    /// 1 - cosine similarity (0 for identical directions, 1 if no followers are shared)
+    Cosine,
+}
+
+/// Row embeddings: each prefix's row of the model grid as a vector
+#[derive(Debug, Clone, PartialEq)]
+pub struct Embeddings {
+    /// Row labels (prefixes joined with spaces)
+    pub words: Vec<String>,
+    /// Column labels (followers, then any context columns)
+    pub columns: Vec<String>,
+    /// One vector per row
+    pub vectors: Vec<Vec<f64>>,
+}
+
+/// Pairwise distances between all rows of an [`Embeddings`]
+#[derive(Debug, Clone, Serialize)]
+pub struct DistanceMatrix {
+    pub metric: DistanceMetric,
+    pub words: Vec<String>,
+    /// `distances[i][j]` is the distance between `words[i]` and `words[j]`
+    pub distances: Vec<Vec<f64>>,
+}
+
+impl Embeddings {
+    /// Builds one embedding per entry, with a column for each follower in the model
+    pub fn from_entries(entries: &[WordFollowEntry], values: EmbeddingValues) -> Self {
+        let columns: Vec<String> = entries
+            .iter()
+            .flat_map(|entry| entry.followers.iter().map(|(w, _)| w.clone()))
+            .collect::<BTreeSet<_>>()
+            .into_iter()
+            .collect();
+        let position: HashMap<&String, usize> =
+            columns.iter().enumerate().map(|(i, w)| (w, i)).collect();
+
+        let vectors = entries
+            .iter()
+            .map(|entry| {
+                let mut vector = vec![0.0; columns.len()];
+                for (word, count) in &entry.followers {
+                    vector[position[word]] = *count as f64;
+                }
+                scale_row(&mut vector, values);
+                vector
+            })
+            .collect();
+
+        Embeddings {
+            words: entries.iter().map(|e| e.prefix.join(" ")).collect(),
+            columns,
+            vectors,
+        }
+    }
+
+    /// Appends the context columns (e.g. "after verb") for the last word of each row
+    pub fn with_context(mut self, context: &ContextColumns) -> Self {
+        for (word, vector) in self.words.iter().zip(&mut self.vectors) {
+            let last = word.rsplit(' ').next().unwrap_or(word);
+            vector.extend(context.row(last).into_iter().map(|c| c as f64));
+        }
+        self.columns.extend(
+            context
+                .classes
+                .iter()
+                .map(|class| format!("after {}", class.name)),
+        );
+        self
+    }
+
+    /// The embedding for a row label, if there is one
+    pub fn get(&self, word: &str) -> Option<&[f64]> {
+        let index = self.words.iter().position(|w| w == word)?;
+        Some(&self.vectors[index])
+    }
+
+    /// Distances between every pair of rows
+    pub fn distance_matrix(&self, metric: DistanceMetric) -> DistanceMatrix {
+        let distances = self
+            .vectors
+            .iter()
+            .map(|a| {
+                self.vectors
+                    .iter()
+                    .map(|b| distance(a, b, metric))
+                    .collect()
+            })
+            .collect();
+
+        DistanceMatrix {
+            metric,
+            words: self.words.clone(),
+            distances,
+        }
+    }
+
+    /// The `k` rows closest to `word` (excluding itself), nearest first
+    pub fn nearest(&self, word: &str, k: usize, metric: DistanceMetric) -> Vec<(String, f64)> {
+        let Some(target) = self.get(word) else {
+            return Vec::new();
+        };
+
+        let mut neighbours: Vec<(String, f64)> = self
+            .words
+            .iter()
+            .zip(&self.vectors)
+            .filter(|(w, _)| w.as_str() != word)
+            .map(|(w, v)| (w.clone(), distance(target, v, metric)))
+            .collect();
+        neighbours.sort_by(|a, b| a.1.total_cmp(&b.1).then_with(|| a.0.cmp(&b.0)));
+        neighbours.truncate(k);
+        neighbours
+    }
+}
+
+fn scale_row(vector: &mut [f64], values: EmbeddingValues) {
+    let total: f64 = vector.iter().sum();
+    if total == 0.0 {
+        return;
+    }
+
+    let factor = match values {
+        EmbeddingValues::Raw => return,
+        EmbeddingValues::Normalised => 1.0 / total,
+        EmbeddingValues::Scaled => {
+            let k_digits = (total as usize).to_string().len() as i32;
+            (10f64.powi(k_digits) - 1.0) / total
+        }
+    };
+
+    for value in vector.iter_mut() {
+        *value *= factor;
+        if values == EmbeddingValues::Scaled {
+            *value = value.round();
+        }
+    }
+}
+
+/// Distance between two equal-length vectors
+pub fn distance(a: &[f64], b: &[f64], metric: DistanceMetric) -> f64 {
+    match metric {
+        DistanceMetric::L1 => a.iter().zip(b).map(|(x, y)| (x - y).abs()).sum(),
+        DistanceMetric::L2 => a
+            .iter()
+            .zip(b)
+            .map(|(x, y)| (x - y).powi(2))
+            .sum::<f64>()
+            .sqrt(),
+        DistanceMetric::Cosine => {
+            let dot: f64 = a.iter().zip(b).map(|(x, y)| x * y).sum();
+            let norm_a = a.iter().map(|x| x * x).sum::<f64>().sqrt();
+            let norm_b = b.iter().map(|y| y * y).sum::<f64>().sqrt();
+            if norm_a == 0.0 || norm_b == 0.0 {
+                // A row with no followers shares no direction with anything (except another empty row)
+                return if norm_a == norm_b { 0.0 } else { 1.0 };
+            }
+            1.0 - dot / (norm_a * norm_b)
+        }
+    }
+}
+
+impl DistanceMatrix {
+    /// Writes the matrix as TSV with the words as row and column headers
+    pub fn write_tsv<W: Write>(&self, writer: &mut W) -> io::Result<()> {
+        let mut header = vec![String::new()];
+        header.extend(self.words.iter().cloned());
+        write_record(writer, &header, '\t')?;
+
+        for (word, row) in self.words.iter().zip(&self.distances) {
+            let mut record = vec![word.clone()];
+            record.extend(row.iter().map(|d| format_distance(*d)));
+            write_record(writer, &record, '\t')?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Whole numbers are written without a decimal point (L1 distances on raw counts are integers)
+fn format_distance(distance: f64) -> String {
+    if distance.fract() == 0.0 {
+        format!("{}", distance as i64)
+    } else {
+        format!("{:.4}", distance)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::NGramCounter;
+
+    #[test]
+    fn computes_l1_distances_for_handout_text() {
+        let mut counter = NGramCounter::new(2, vec![',', '.']).unwrap();
+        counter.process_line("See Spot. Spot runs.");
+        let embeddings = Embeddings::from_entries(&counter.get_entries(), EmbeddingValues::Raw);
+
+        assert_eq!(embeddings.words, vec![".", "runs", "see", "spot"]);
+        let matrix = embeddings.distance_matrix(DistanceMetric::L1);
+        let d = |a: &str, b: &str| {
+            let i = matrix.words.iter().position(|w| w == a).unwrap();
+            let j = matrix.words.iter().position(|w| w == b).unwrap();
+            matrix.distances[i][j]
+        };
+        assert_eq!(d("see", "spot"), 3.0);
+        assert_eq!(d("see", "runs"), 2.0);
+        assert_eq!(d("spot", "runs"), 1.0);
+        assert_eq!(d("see", "."), 0.0);
+
+        assert_eq!(
+            embeddings.nearest("see", 2, DistanceMetric::L1),
+            vec![(".".to_string(), 0.0), ("runs".to_string(), 2.0)]
+        );
+
+        let mut tsv = Vec::new();
+        matrix.write_tsv(&mut tsv).unwrap();
+        assert_eq!(
+            String::from_utf8(tsv).unwrap().lines().nth(3),
+            Some("see\t0\t2\t0\t3")
+        );
+    }
+
+    #[test]
+    fn normalised_rows_ignore_frequency() {
+        let entries = vec![
+            WordFollowEntry {
+                prefix: vec!["a".to_string()],
+                followers: vec![("x".to_string(), 1), ("y".to_string(), 1)],
+            },
+            WordFollowEntry {
+                prefix: vec!["b".to_string()],
+                followers: vec![("x".to_string(), 5), ("y".to_string(), 5)],
+            },
+        ];
+
+        let raw = Embeddings::from_entries(&entries, EmbeddingValues::Raw);
+        let normalised = Embeddings::from_entries(&entries, EmbeddingValues::Normalised);
+        let scaled = Embeddings::from_entries(&entries, EmbeddingValues::Scaled);
+
+        assert_eq!(
+            raw.distance_matrix(DistanceMetric::L2).distances[0][1],
+            32f64.sqrt()
+        );
+        assert_eq!(
+            normalised.distance_matrix(DistanceMetric::L1).distances[0][1],
+            0.0
+        );
+        assert_eq!(scaled.get("a"), Some(&[5.0, 5.0][..]));
+        assert_eq!(scaled.get("b"), Some(&[50.0, 50.0][..]));
+        assert!(raw.distance_matrix(DistanceMetric::Cosine).distances[0][1].abs() < 1e-12);
+    }
+}
@@ -0,0 +1,255 @@
+use std::collections::{HashMap, HashSet};
+use std::io::{self, Write};
+
+use crate::{GAP_TOKEN, WordFollowEntry};
+
+/// Which counts go in the cells of an exported matrix
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "clap", derive(clap::ValueEnum))]
+pub enum MatrixCounts {
+    /// The number of times the column word follows the row prefix
+    Raw,
+    /// Running totals along each row (as used for dice lookup on worksheets)
+    Cumulative,
+}
+
+/// Ordering of the matrix rows and columns
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "clap", derive(clap::ValueEnum))]
+pub enum MatrixOrder {
+    Alphabetical,
+    /// Most frequent words first
+    Frequency,
+    /// Order of first appearance in the text (as in the hand-training algorithm)
+    Appearance,
+}
+
+/// Options for [`write_matrix`]
+#[derive(Debug, Clone)]
+pub struct MatrixOptions {
+    /// Cell separator, e.g. '\t' for TSV or ',' for CSV
+    pub delimiter: char,
+    pub counts: MatrixCounts,
+    pub order: MatrixOrder,
+    /// Keep only this many of the most frequent words
+    pub max_vocabulary: Option<usize>,
+    /// Write "0" instead of leaving cells with a zero count blank
+    pub zero_cells: bool,
+}
+
+impl Default for MatrixOptions {
+    fn default() -> Self {
+        MatrixOptions {
+            delimiter: '\t',
+            counts: MatrixCounts::Cumulative,
+            order: MatrixOrder::Alphabetical,
+            max_vocabulary: None,
+            zero_cells: false,
+        }
+    }
+}
+
+/// Chooses the words used for the matrix columns (and rows, for bigrams).
+///
+/// `vocabulary` lists each token with its frequency, in order of first appearance
+/// (see [`crate::NGramCounter::get_vocabulary`]).
+pub fn matrix_vocabulary(vocabulary: &[(String, usize)], options: &MatrixOptions) -> Vec<String> {
+    let mut words: Vec<(usize, &String, usize)> = vocabulary
+        .iter()
+        .enumerate()
+        .map(|(position, (word, count))| (position, word, *count))
+        .collect();
+
+    if let Some(max) = options.max_vocabulary {
+        words.sort_by(|a, b| b.2.cmp(&a.2).then_with(|| a.0.cmp(&b.0)));
+        words.truncate(max);
+    }
+
+    match options.order {
+        MatrixOrder::Alphabetical => words.sort_by(|a, b| {
+            a.1.to_lowercase()
+                .cmp(&b.1.to_lowercase())
+                .then_with(|| a.1.cmp(b.1))
+        }),
+        MatrixOrder::Frequency => words.sort_by(|a, b| b.2.cmp(&a.2).then_with(|| a.1.cmp(b.1))),
+        MatrixOrder::Appearance => words.sort_by_key(|w| w.0),
+    }
+
+    words.into_iter().map(|(_, word, _)| word.clone()).collect()
+}
+
+/// Writes the n-gram counts as a delimited prefix-by-follower matrix.
+///
+/// For bigrams the rows and columns both use the (ordered, capped) vocabulary; for
+/// longer prefixes there is one row per prefix whose words are all in the vocabulary. The
+/// skipped positions of a skip-gram model's prefixes stay as [`GAP_TOKEN`] in the row labels.
+pub fn write_matrix<W: Write>(
+    entries: &[WordFollowEntry],
+    vocabulary: &[(String, usize)],
+    options: &MatrixOptions,
+    writer: &mut W,
+) -> io::Result<()> {
+    let columns = matrix_vocabulary(vocabulary, options);
+    let column_set: HashSet<&String> = columns.iter().collect();
+
+    let lookup: HashMap<&[String], &WordFollowEntry> = entries
+        .iter()
+        .map(|entry| (entry.prefix.as_slice(), entry))
+        .collect();
+
+    let bigram = entries.iter().all(|entry| entry.prefix.len() == 1);
+    let rows: Vec<Vec<String>> = if bigram {
+        columns.iter().map(|word| vec![word.clone()]).collect()
+    } else {
+        let position: HashMap<&String, usize> =
+            columns.iter().enumerate().map(|(i, w)| (w, i)).collect();
+        let mut prefixes: Vec<Vec<String>> = entries
+            .iter()
+            .filter(|entry| {
+                entry
+                    .prefix
+                    .iter()
+                    .all(|w| w == GAP_TOKEN || column_set.contains(w))
+            })
+            .map(|entry| entry.prefix.clone())
+            .collect();
+        prefixes.sort_by_key(|prefix| {
+            prefix
+                .iter()
+                .filter_map(|w| position.get(w).copied())
+                .collect::<Vec<_>>()
+        });
+        prefixes
+    };
+
+    let mut header = vec![String::new()];
+    header.extend(columns.iter().cloned());
+    write_record(writer, &header, options.delimiter)?;
+
+    for prefix in rows {
+        let followers: HashMap<&String, usize> = lookup
+            .get(prefix.as_slice())
+            .map(|entry| entry.followers.iter().map(|(w, c)| (w, *c)).collect())
+            .unwrap_or_default();
+
+        let mut record = vec![prefix.join(" ")];
+        let mut running = 0;
+        for column in &columns {
+            let count = followers.get(column).copied().unwrap_or(0);
+            running += count;
+            record.push(if count == 0 {
+                if options.zero_cells {
+                    "0".to_string()
+                } else {
+                    String::new()
+                }
+            } else {
+                match options.counts {
+                    MatrixCounts::Raw => count.to_string(),
+                    MatrixCounts::Cumulative => running.to_string(),
+                }
+            });
+        }
+        write_record(writer, &record, options.delimiter)?;
+    }
+
+    Ok(())
+}
+
+pub(crate) fn write_record<W: Write>(
+    writer: &mut W,
+    fields: &[String],
+    delimiter: char,
+) -> io::Result<()> {
+    let escaped: Vec<String> = fields
+        .iter()
+        .map(|field| {
+            if field.contains(delimiter) || field.contains('"') || field.contains('\n') {
+                format!("\"{}\"", field.replace('"', "\"\""))
+            } else {
+                field.clone()
+            }
+        })
+        .collect();
+    writeln!(writer, "{}", escaped.join(&delimiter.to_string()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::NGramCounter;
+
+    fn matrix(text: &str, options: &MatrixOptions) -> String {
+        let mut counter = NGramCounter::new(2, vec![',', '.']).unwrap();
+        counter.process_line(text);
+        let mut out = Vec::new();
+        write_matrix(
+            &counter.get_entries(),
+            &counter.get_vocabulary(),
+            options,
+            &mut out,
+        )
+        .unwrap();
+        String::from_utf8(out).unwrap()
+    }
+
+    #[test]
+    fn writes_cumulative_tsv_matrix() {
+        let out = matrix("see spot run. see spot jump.", &MatrixOptions::default());
+        assert_eq!(
+            out,
+            "\t.\tjump\trun\tsee\tspot\n\
+             .\t\t\t\t1\t\n\
+             jump\t1\t\t\t\t\n\
+             run\t1\t\t\t\t\n\
+             see\t\t\t\t\t2\n\
+             spot\t\t1\t2\t\t\n"
+        );
+    }
+
+    #[test]
+    fn writes_raw_csv_in_appearance_order_with_zeros() {
+        let options = MatrixOptions {
+            delimiter: ',',
+            counts: MatrixCounts::Raw,
+            order: MatrixOrder::Appearance,
+            max_vocabulary: Some(3),
+            zero_cells: true,
+        };
+        // see(2) spot(3) run(1) ,(1) jump(1): the cap keeps see, spot and the first of the ties
+        let out = matrix("see spot run, spot. see spot jump", &options);
+        assert_eq!(
+            out,
+            ",see,spot,run\n\
+             see,0,2,0\n\
+             spot,0,0,1\n\
+             run,0,0,0\n"
+        );
+    }
+
+    #[test]
+    fn keeps_skip_gram_rows_with_their_gaps() {
+        let mut counter = NGramCounter::with_positions(&[2], vec![',', '.']).unwrap();
+        counter.process_line("see spot run. see spot jump.");
+        let options = MatrixOptions {
+            counts: MatrixCounts::Raw,
+            ..MatrixOptions::default()
+        };
+        let mut out = Vec::new();
+        write_matrix(
+            &counter.get_entries(),
+            &counter.get_vocabulary(),
+            &options,
+            &mut out,
+        )
+        .unwrap();
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            "\t.\tjump\trun\tsee\tspot\n\
+             . _\t\t\t\t\t1\n\
+             run _\t\t\t\t1\t\n\
+             see _\t\t1\t1\t\t\n\
+             spot _\t2\t\t\t\t\n"
+        );
+    }
+}
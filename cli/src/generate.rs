@@ -0,0 +1,200 @@
+use rand::Rng;
+use std::collections::HashMap;
+use std::io;
+
+use crate::{GAP_TOKEN, WordFollowEntry};
+
+/// Generates `length` tokens from a model, rolling for each follower as in Basic Generation.
+///
+/// Generation starts from a random prefix (weighted by how often it occurs). When it reaches a
+/// prefix with no followers it starts again from a new random prefix.
+///
+/// Skip-gram models are refused: their prefixes have no words for the skipped positions, so
+/// the text can't be started from one.
+pub fn generate<R: Rng + ?Sized>(
+    entries: &[WordFollowEntry],
+    length: usize,
+    rng: &mut R,
+) -> io::Result<Vec<String>> {
+    if entries
+        .iter()
+        .any(|entry| entry.prefix.iter().any(|word| word == GAP_TOKEN))
+    {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            "Can't generate text from a skip-gram model (its prefixes have skipped positions)",
+        ));
+    }
+
+    let lookup: HashMap<&[String], &WordFollowEntry> = entries
+        .iter()
+        .filter(|entry| entry_total(entry) > 0)
+        .map(|entry| (entry.prefix.as_slice(), entry))
+        .collect();
+
+    let starts: Vec<(&WordFollowEntry, usize)> = entries
+        .iter()
+        .map(|entry| (entry, entry_total(entry)))
+        .filter(|(_, total)| *total > 0)
+        .collect();
+    if starts.is_empty() {
+        return Ok(Vec::new());
+    }
+
+    let mut tokens: Vec<String> = Vec::with_capacity(length);
+    let mut prefix: Vec<String> = Vec::new();
+
+    while tokens.len() < length {
+        let Some(entry) = lookup.get(prefix.as_slice()) else {
+            let start = choose_weighted(&starts, rng);
+            prefix = start.prefix.clone();
+            tokens.extend(prefix.iter().cloned());
+            continue;
+        };
+
+        let follower = choose_weighted(&entry.followers, rng).clone();
+        // Unigram models have an empty prefix, which never changes
+        if !prefix.is_empty() {
+            prefix.remove(0);
+            prefix.push(follower.clone());
+        }
+        tokens.push(follower);
+    }
+
+    tokens.truncate(length);
+    Ok(tokens)
+}
+
+/// A random bigram model over the given words (the Synthetic Data handout's "Joker mode").
+///
+/// Each cell gets between 1 and 5 tally marks with probability one half, and every row gets
+/// at least one follower.
+pub fn joker_model<R: Rng + ?Sized>(words: &[String], rng: &mut R) -> Vec<WordFollowEntry> {
+    let mut entries: Vec<WordFollowEntry> = words
+        .iter()
+        .map(|word| {
+            let mut followers = Vec::new();
+            for follower in words {
+                if rng.random_bool(0.5) {
+                    followers.push((follower.clone(), rng.random_range(1..=5)));
+                }
+            }
+            if followers.is_empty() {
+                let follower = &words[rng.random_range(0..words.len())];
+                followers.push((follower.clone(), rng.random_range(1..=5)));
+            }
+            followers.sort_by(|a, b| {
+                b.1.cmp(&a.1)
+                    .then_with(|| a.0.to_lowercase().cmp(&b.0.to_lowercase()))
+            });
+            WordFollowEntry {
+                prefix: vec![word.clone()],
+                followers,
+            }
+        })
+        .collect();
+    entries.sort_by(|a, b| a.prefix.cmp(&b.prefix));
+    entries
+}
+
+fn choose_weighted<'a, T, R: Rng + ?Sized>(items: &'a [(T, usize)], rng: &mut R) -> &'a T {
+    let total: usize = items.iter().map(|(_, weight)| weight).sum();
+    let mut roll = rng.random_range(0..total);
+    for (item, weight) in items {
+        if roll < *weight {
+            return item;
+        }
+        roll -= weight;
+    }
+    unreachable!("roll is less than the total weight")
+}
+
+fn entry_total(entry: &WordFollowEntry) -> usize {
+    entry.followers.iter().map(|(_, count)| *count).sum()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::NGramCounter;
+    use rand::SeedableRng;
+    use rand::rngs::StdRng;
+
+    #[test]
+    fn generates_only_seen_transitions() {
+        let mut counter = NGramCounter::new(3, vec![',', '.']).unwrap();
+        counter.process_line("see spot run. see spot jump. run spot run.");
+        let entries = counter.get_entries();
+
+        let mut rng = StdRng::seed_from_u64(7);
+        let tokens = generate(&entries, 50, &mut rng).unwrap();
+        assert_eq!(tokens.len(), 50);
+
+        // Every trigram in the output was in the text, except where generation restarted
+        let known: Vec<Vec<String>> = entries
+            .iter()
+            .flat_map(|e| {
+                e.followers.iter().map(|(w, _)| {
+                    let mut ngram = e.prefix.clone();
+                    ngram.push(w.clone());
+                    ngram
+                })
+            })
+            .collect();
+        let seen = tokens
+            .windows(3)
+            .filter(|window| known.contains(&window.to_vec()))
+            .count();
+        assert!(seen > 40);
+
+        // The same seed gives the same text
+        let mut rng = StdRng::seed_from_u64(7);
+        assert_eq!(generate(&entries, 50, &mut rng).unwrap(), tokens);
+    }
+
+    #[test]
+    fn generates_from_a_unigram_model() {
+        let mut counter = NGramCounter::new(1, vec![',', '.']).unwrap();
+        counter.process_line("see spot run.");
+        let entries = counter.get_entries();
+
+        let mut rng = StdRng::seed_from_u64(7);
+        let tokens = generate(&entries, 20, &mut rng).unwrap();
+        assert_eq!(tokens.len(), 20);
+        assert!(
+            tokens
+                .iter()
+                .all(|t| ["see", "spot", "run", "."].contains(&t.as_str()))
+        );
+    }
+
+    #[test]
+    fn refuses_skip_gram_models() {
+        let mut counter = NGramCounter::with_positions(&[2], vec![',', '.']).unwrap();
+        counter.process_line("see spot run. see spot jump.");
+
+        let mut rng = StdRng::seed_from_u64(7);
+        let err = generate(&counter.get_entries(), 20, &mut rng).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidInput);
+    }
+
+    #[test]
+    fn joker_model_has_a_row_per_word() {
+        let words: Vec<String> = ["pizza", "robot", "moon", "dance"]
+            .iter()
+            .map(|w| w.to_string())
+            .collect();
+        let mut rng = StdRng::seed_from_u64(1);
+        let model = joker_model(&words, &mut rng);
+
+        assert_eq!(model.len(), 4);
+        assert_eq!(model[0].prefix, vec!["dance"]);
+        assert!(model.iter().all(|entry| {
+            !entry.followers.is_empty()
+                && entry
+                    .followers
+                    .iter()
+                    .all(|(w, c)| words.contains(w) && (1..=5).contains(c))
+        }));
+    }
+}
@@ -0,0 +1,111 @@
+use serde::Serialize;
+use std::collections::HashMap;
+
+use crate::context::{ContextColumns, WordClasses};
+use crate::text::{Normalizer, NormalizerConfig};
+
+/// A bigram training grid, laid out the way it is filled in by hand
+#[derive(Debug, Clone, Serialize)]
+pub struct TrainingGrid {
+    /// The original text
+    pub text: String,
+    /// The preprocessed text, one token per element
+    pub tokens: Vec<String>,
+    /// Row and column headers, in order of first appearance
+    pub words: Vec<String>,
+    /// `counts[row][column]` is the number of times `words[column]` follows `words[row]`
+    pub counts: Vec<Vec<usize>>,
+    /// Names of the context columns (e.g. "verb" for an "after verb" column), if any
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub context_columns: Vec<String>,
+    /// `context_counts[row][column]` is the number of times `words[row]` came after a word in
+    /// `context_columns[column]`
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub context_counts: Vec<Vec<usize>>,
+}
+
+impl TrainingGrid {
+    /// Adds context columns (as in the Context Columns handout) for the given word classes
+    pub fn with_context(mut self, classes: WordClasses) -> Self {
+        let mut context = ContextColumns::new(classes);
+        for pair in self.tokens.windows(2) {
+            context.record(&pair[0], &pair[1]);
+        }
+
+        self.context_columns = context.classes.iter().map(|c| c.name.clone()).collect();
+        self.context_counts = self.words.iter().map(|word| context.row(word)).collect();
+        self
+    }
+}
+
+/// Splits text into tokens using the same normalizer as [`crate::NGramCounter`]
+pub fn tokenize(text: &str, punctuation: Vec<char>) -> Vec<String> {
+    let normalizer = Normalizer::new(NormalizerConfig::new(punctuation));
+    text.lines()
+        .flat_map(|line| normalizer.normalize_line(line))
+        .collect()
+}
+
+/// Builds the bigram grid for a text.
+///
+/// Words get a new row and column when they are first seen, as in the Basic Training handout.
+pub fn training_grid(text: &str, punctuation: Vec<char>) -> TrainingGrid {
+    let tokens = tokenize(text, punctuation);
+
+    let mut words: Vec<String> = Vec::new();
+    let mut index: HashMap<&str, usize> = HashMap::new();
+    for token in &tokens {
+        if !index.contains_key(token.as_str()) {
+            index.insert(token, words.len());
+            words.push(token.clone());
+        }
+    }
+
+    let mut counts = vec![vec![0; words.len()]; words.len()];
+    for pair in tokens.windows(2) {
+        counts[index[pair[0].as_str()]][index[pair[1].as_str()]] += 1;
+    }
+
+    TrainingGrid {
+        text: text.to_string(),
+        tokens,
+        words,
+        counts,
+        context_columns: Vec::new(),
+        context_counts: Vec::new(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn matches_basic_training_example() {
+        let grid = training_grid(
+            "See Spot run. See Spot jump. Run, Spot, run. Jump, Spot, jump.",
+            vec![',', '.'],
+        );
+
+        assert_eq!(grid.tokens.len(), 20);
+        assert_eq!(grid.words, vec!["see", "spot", "run", ".", "jump", ","]);
+        // see -> spot (2)
+        assert_eq!(grid.counts[0], vec![0, 2, 0, 0, 0, 0]);
+        // spot -> run, jump, ",", ","
+        assert_eq!(grid.counts[1], vec![0, 0, 1, 0, 1, 2]);
+        // "," -> spot, run, spot, jump
+        assert_eq!(grid.counts[5], vec![0, 2, 1, 0, 1, 0]);
+    }
+
+    #[test]
+    fn adds_context_columns() {
+        let grid = training_grid("I run fast. You run to me.", vec![',', '.'])
+            .with_context(WordClasses::builtin());
+
+        assert_eq!(grid.context_columns, vec!["verb", "pronoun", "preposition"]);
+        assert_eq!(grid.words[1], "run");
+        assert_eq!(grid.context_counts[1], vec![0, 2, 0]);
+        assert_eq!(grid.words[6], "me");
+        assert_eq!(grid.context_counts[6], vec![0, 0, 1]);
+    }
+}
@@ -0,0 +1,154 @@
+use std::io;
+
+use crate::WordFollowEntry;
+
+/// Approximate layout of a `book.typ` booklet, for estimating page counts without running typst.
+///
+/// Lengths are measured in lines and average characters of body text; the defaults match the
+/// template's defaults (A4 paper, 8pt Libertinus Serif, 4 columns).
+#[derive(Debug, Clone, PartialEq)]
+pub struct BookLayout {
+    /// Columns per page
+    pub columns: usize,
+    /// Body text characters that fit on one line of a column
+    pub chars_per_line: f64,
+    /// Body text lines that fit in one column
+    pub lines_per_column: f64,
+    /// Size of the bold prefix relative to the body text
+    pub prefix_scale: f64,
+    /// Extra lines per entry (the taller first line and the gap between entries)
+    pub entry_spacing_lines: f64,
+    /// Pages before the entries: title, copyright, instructions and a blank page
+    pub front_matter_pages: usize,
+}
+
+impl Default for BookLayout {
+    fn default() -> Self {
+        BookLayout {
+            columns: 4,
+            chars_per_line: 29.0,
+            lines_per_column: 67.0,
+            prefix_scale: 1.5,
+            entry_spacing_lines: 1.2,
+            front_matter_pages: 4,
+        }
+    }
+}
+
+/// Text area (width, height in mm) inside `book.typ`'s margins for each supported paper size
+const PAPER_TEXT_AREAS: [(&str, f64, f64); 4] = [
+    ("a3", 258.0, 370.0),
+    ("a4", 171.0, 247.0),
+    ("a5", 109.0, 160.0),
+    ("us-letter", 176.9, 229.4),
+];
+
+impl BookLayout {
+    /// The layout for a paper size (as named by typst, e.g. "a4"), body text size in points and
+    /// number of columns, scaled from the A4, 8pt, 4-column defaults
+    pub fn for_page(paper: &str, font_size: f64, columns: usize) -> io::Result<Self> {
+        let defaults = BookLayout::default();
+        let (_, default_width, default_height) = PAPER_TEXT_AREAS[1];
+        let Some(&(_, width, height)) = PAPER_TEXT_AREAS
+            .iter()
+            .find(|(name, _, _)| name.eq_ignore_ascii_case(paper))
+        else {
+            let names: Vec<&str> = PAPER_TEXT_AREAS.iter().map(|(name, _, _)| *name).collect();
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!(
+                    "Unknown paper size '{}' (expected one of {})",
+                    paper,
+                    names.join(", ")
+                ),
+            ));
+        };
+        if columns == 0 || !(font_size.is_finite() && font_size > 0.0) {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "The booklet needs at least one column and a positive font size",
+            ));
+        }
+
+        let font_scale = 8.0 / font_size;
+        Ok(BookLayout {
+            columns,
+            chars_per_line: defaults.chars_per_line
+                * (width / default_width)
+                * (defaults.columns as f64 / columns as f64)
+                * font_scale,
+            lines_per_column: defaults.lines_per_column * (height / default_height) * font_scale,
+            ..defaults
+        })
+    }
+
+    /// Estimated lines taken by one entry, including the space after it
+    pub fn entry_lines(&self, entry: &WordFollowEntry) -> f64 {
+        let total: usize = entry.followers.iter().map(|(_, count)| count).sum();
+        // Scaled and cumulative counts have at most as many digits as the row total
+        let digits = total.max(1).to_string().len();
+
+        let prefix_chars: usize = entry.prefix.iter().map(|w| w.chars().count() + 1).sum();
+        let mut chars = prefix_chars as f64 * self.prefix_scale + digits as f64 + 1.0;
+        for (word, _) in &entry.followers {
+            chars += word.chars().count() as f64 + 1.0;
+            if entry.followers.len() > 1 {
+                // "count|" before each follower
+                chars += digits as f64 + 1.0;
+            }
+        }
+
+        (chars / self.chars_per_line).ceil() + self.entry_spacing_lines
+    }
+
+    /// Body text lines that fit on one page
+    pub fn lines_per_page(&self) -> f64 {
+        self.lines_per_column * self.columns as f64
+    }
+
+    /// Estimated page count for a booklet of these entries, including the front matter
+    pub fn estimate_pages(&self, entries: &[WordFollowEntry]) -> usize {
+        let lines: f64 = entries.iter().map(|entry| self.entry_lines(entry)).sum();
+        self.front_matter_pages + (lines / self.lines_per_page()).ceil() as usize
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn estimates_grow_with_entries() {
+        let layout = BookLayout::default();
+        let entry = WordFollowEntry {
+            prefix: vec!["the".to_string()],
+            followers: vec![("cat".to_string(), 60), ("dog".to_string(), 39)],
+        };
+        // (3 + 1) * 1.5 + 2 + 1 = 9 chars of prefix and dice, then 2 * (3 + 1 + 3) = 14
+        assert_eq!(layout.entry_lines(&entry), 1.0 + 1.2);
+
+        assert_eq!(layout.estimate_pages(&[]), 4);
+        assert_eq!(layout.estimate_pages(std::slice::from_ref(&entry)), 5);
+        let many = vec![entry; 1000];
+        // 2200 lines at 268 lines per page
+        assert_eq!(layout.estimate_pages(&many), 4 + 9);
+    }
+
+    #[test]
+    fn scales_with_paper_font_and_columns() -> io::Result<()> {
+        assert_eq!(BookLayout::for_page("A4", 8.0, 4)?, BookLayout::default());
+
+        let a5 = BookLayout::for_page("a5", 8.0, 2)?;
+        assert_eq!(a5.columns, 2);
+        // Narrower paper but half the columns: wider columns, fewer lines
+        assert!(a5.chars_per_line > 29.0);
+        assert!(a5.lines_per_page() < BookLayout::default().lines_per_page() / 2.0);
+
+        let large = BookLayout::for_page("a4", 16.0, 4)?;
+        assert_eq!(large.lines_per_column, 33.5);
+
+        assert!(BookLayout::for_page("b5", 8.0, 4).is_err());
+        assert!(BookLayout::for_page("a4", 8.0, 0).is_err());
+        Ok(())
+    }
+}
@@ -1,12 +1,59 @@
-use serde::Serialize;
+use serde::{Deserialize, Serialize};
 use std::collections::{BTreeMap, HashMap, VecDeque};
 use std::fs::File;
 use std::io;
 use std::path::Path;
 
+mod adapter;
+mod booklet;
+mod build;
+mod collapse;
+mod context;
+mod diff;
+mod embedding;
+mod export;
+mod generate;
+mod grid;
+mod layout;
+mod manifest;
+mod merge;
+mod model;
+mod orders;
+mod prune;
+mod stats;
+mod summary;
+mod templates;
 mod text;
-
+mod trace;
+#[cfg(feature = "embed-typst")]
+mod typeset;
+
+pub use adapter::{apply_adapter, restrict_to_vocabulary};
+pub use booklet::{BookletFormat, PageStyle, save_booklet, save_to_html, save_to_markdown};
+pub use build::{BuildManifest, BuildRecord, BuildState, BuildTarget, Dice};
+pub use collapse::{CollapseOptions, GenerationStats, simulate_collapse};
+pub use context::{ContextColumns, WordClass, WordClasses, combine_context};
+pub use diff::{ModelDiff, PrefixDiff, ProbabilityShift, diff_models};
+pub use embedding::{DistanceMatrix, DistanceMetric, EmbeddingValues, Embeddings, distance};
+pub use export::{MatrixCounts, MatrixOptions, MatrixOrder, matrix_vocabulary, write_matrix};
+pub use generate::{generate, joker_model};
+pub use grid::{TrainingGrid, tokenize, training_grid};
+pub use layout::BookLayout;
+pub use manifest::{BookIndex, BookManifest, IndexRange, ManifestBook, book_index, counts_hash};
+pub use merge::{ModelSource, merge_models};
+pub use model::{Model, Scaling, load_model, vocabulary};
+pub use orders::MultiOrderCounter;
+pub use prune::{PruneOptions, PruningStats, UNKNOWN_TOKEN};
+pub use stats::{
+    BranchingStats, CorpusStats, DEFAULT_TOP_N, EntropyStats, corpus_stats, entropy_bits,
+    js_divergence, kl_divergence,
+};
+pub use summary::{ModelComparison, ModelKey, Summary, perplexity, summarize};
+pub use templates::{TEMPLATE_FILES, export_templates, template_file};
 use text::{Normalizer, NormalizerConfig};
+pub use trace::{TraceStep, write_trace_jsonl, write_trace_table};
+#[cfg(feature = "embed-typst")]
+pub use typeset::{DiagnosticSeverity, Typeset, TypesetError, TypstDiagnostic, typeset_pdf};
 
 /// Helper function to get model type string (e.g., "bigram", "trigram")
 pub fn model_type_str(n: usize) -> String {
@@ -18,28 +65,73 @@ pub fn model_type_str(n: usize) -> String {
     }
 }
 
+/// The booklet subtitle for a model, e.g. "A bigram language model", or "A skip-gram language
+/// model (context positions -3, -1)" for a model with [`Metadata::context_positions`]
+pub fn model_subtitle(n: usize, context_positions: &[isize]) -> String {
+    if context_positions.is_empty() {
+        return format!("A {} language model", model_type_str(n));
+    }
+    format!(
+        "A skip-gram language model (context positions {})",
+        context_positions
+            .iter()
+            .map(|p| p.to_string())
+            .collect::<Vec<_>>()
+            .join(", ")
+    )
+}
+
 /// Contains metadata from the frontmatter of the processed file
-#[derive(Debug, Clone, Serialize)]
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
 pub struct Metadata {
     /// Title of the document
+    #[serde(default)]
     pub title: String,
     /// Author of the document
+    #[serde(default)]
     pub author: String,
     /// URL related to the document
+    #[serde(default)]
     pub url: String,
     /// Size of n-gram used for processing
     pub n: usize,
     /// Subtitle for the booklet (e.g., "A bigram language model" or "A trigram language model: A-K (Book 1 of 3)")
+    #[serde(default)]
     pub subtitle: String,
     /// CLI version used to generate this model
+    #[serde(default)]
     pub version: String,
     /// Summary statistics for the processed text
-    #[serde(skip_serializing_if = "Option::is_none")]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
     pub stats: Option<ProcessingStats>,
+    /// Context column counts, if the model was built with context columns
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub context: Option<ContextColumns>,
+    /// Tokenizer settings the counts were made with (absent in older files)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tokenization: Option<Tokenization>,
+    /// The models combined into this one, if it was made by [`merge_models`]
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub sources: Vec<ModelSource>,
+    /// Positions of the prefix words relative to the predicted word (e.g. `[-3, -1]`) for
+    /// skip-gram models, whose prefixes mark skipped positions with [`GAP_TOKEN`]; empty for
+    /// ordinary n-gram models
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub context_positions: Vec<isize>,
+}
+
+/// Prefix entry for a skipped position in a skip-gram model's prefix
+pub const GAP_TOKEN: &str = "_";
+
+/// Tokenizer settings for a model; only models with the same settings can be merged
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Tokenization {
+    /// Punctuation characters kept as separate tokens, in sorted order
+    pub punctuation: String,
 }
 
 /// Contains summary statistics for processed text
-#[derive(Debug, Clone, Serialize)]
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
 pub struct ProcessingStats {
     /// Total number of tokens in the text
     pub total_tokens: usize,
@@ -48,11 +140,46 @@ pub struct ProcessingStats {
     /// Total number of n-gram occurrences
     pub total_ngram_occurrences: usize,
     /// Most common n-gram prefix and its most common follower
-    #[serde(skip_serializing_if = "Option::is_none")]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
     pub most_common_ngram: Option<(Vec<String>, String, usize)>,
     /// Prefix with the most cumulative followers
-    #[serde(skip_serializing_if = "Option::is_none")]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub most_popular_prefix: Option<(Vec<String>, usize)>,
+    /// Vocabulary, entropy and frequency distribution statistics
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub corpus: Option<CorpusStats>,
+    /// What pruning removed, if the model was pruned
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub pruning: Option<PruningStats>,
+}
+
+/// One row of the model summary table (as read by `summary.typ`)
+#[derive(Debug, Clone, Serialize)]
+pub struct StatsSummary {
+    pub title: String,
+    pub n: usize,
+    pub total_tokens: usize,
+    pub unique_prefixes: usize,
+    pub total_ngram_occurrences: usize,
+    pub most_common_ngram: Option<(Vec<String>, String, usize)>,
     pub most_popular_prefix: Option<(Vec<String>, usize)>,
+    #[serde(flatten)]
+    pub corpus: Option<CorpusStats>,
+}
+
+impl StatsSummary {
+    pub fn new(metadata: Option<&Metadata>, n: usize, stats: &ProcessingStats) -> Self {
+        StatsSummary {
+            title: metadata.map(|m| m.title.clone()).unwrap_or_default(),
+            n,
+            total_tokens: stats.total_tokens,
+            unique_prefixes: stats.unique_ngrams,
+            total_ngram_occurrences: stats.total_ngram_occurrences,
+            most_common_ngram: stats.most_common_ngram.clone(),
+            most_popular_prefix: stats.most_popular_prefix.clone(),
+            corpus: stats.corpus.clone(),
+        }
+    }
 }
 
 /// Represents an N-gram prefix and its following words with their counts
@@ -65,13 +192,21 @@ pub struct WordFollowEntry {
     pub followers: Vec<(String, usize)>,
 }
 
-    /// A counter for tracking n-gram occurrences in text
-    #[derive(Debug)]
-    pub struct NGramCounter {
-        /// Mapping of n-gram prefixes to their following words and counts
-        prefix_map: BTreeMap<Vec<String>, HashMap<String, usize>>,
+/// A counter for tracking n-gram occurrences in text
+#[derive(Debug)]
+pub struct NGramCounter {
+    /// Mapping of n-gram prefixes to their following words and counts
+    prefix_map: BTreeMap<Vec<String>, HashMap<String, usize>>,
+    /// Frequency of each individual token
+    token_counts: HashMap<String, usize>,
+    /// Distinct tokens in order of first appearance
+    token_order: Vec<String>,
     /// Size of n-gram (e.g., 2 for bigrams, 3 for trigrams)
     n: usize,
+    /// How many words back each prefix word is, furthest first (e.g. [2, 1] for trigrams)
+    positions: Vec<usize>,
+    /// Whether some positions are skipped (a skip-gram model)
+    gapped: bool,
     /// Statistics gathered during processing
     stats: ProcessingStats,
     /// Sliding window for processing text
@@ -80,112 +215,178 @@ pub struct WordFollowEntry {
     metadata: Option<Metadata>,
     /// Unified tokenizer/normalizer
     normalizer: Normalizer,
+    /// Ordered record of count updates, if tracing is enabled
+    trace: Option<Vec<TraceStep>>,
+    /// Maximum number of trace steps to keep
+    trace_limit: Option<usize>,
+    /// "After verb" (etc.) counts, if context columns are enabled
+    context: Option<ContextColumns>,
 }
 
 impl NGramCounter {
-    /// Creates a new NGramCounter with the specified n-gram size and punctuation chars
-    pub fn new(n: usize, punctuation: Vec<char>) -> Self {
-        if n < 2 {
-            eprintln!("Warning: N must be 2 or greater for N-gram analysis. Defaulting to 2.");
-            return Self::new(2, punctuation);
+    /// Creates a new NGramCounter with the specified n-gram size and punctuation chars.
+    ///
+    /// With `n = 1` (a unigram model) every count has the empty prefix, giving a single
+    /// distribution over all the tokens.
+    pub fn new(n: usize, punctuation: Vec<char>) -> io::Result<Self> {
+        if n == 0 {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "N-gram size must be 1 or greater",
+            ));
         }
 
-        let prefix_size = n - 1;
+        let positions: Vec<usize> = (1..n).collect();
+        Self::with_positions(&positions, punctuation)
+    }
+
+    /// Creates a counter whose prefixes are the words at the given positions before the
+    /// predicted word (1 = the previous word, 2 = the word two back, and so on).
+    ///
+    /// `[1, 2]` is an ordinary trigram model. Positions with gaps give a skip-gram model, e.g.
+    /// `[2]` predicts each word from the word two back. Skipped positions appear in the
+    /// prefixes as [`GAP_TOKEN`] so the shape of the context is visible in the booklet.
+    pub fn with_positions(positions: &[usize], punctuation: Vec<char>) -> io::Result<Self> {
+        if positions.contains(&0) {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "Context positions must be before the predicted word (1 or more words back)",
+            ));
+        }
+        let mut positions = positions.to_vec();
+        positions.sort_unstable_by(|a, b| b.cmp(a));
+        positions.dedup();
+        let span = positions.first().copied().unwrap_or(0);
+        let gapped = positions.len() != span;
 
-        NGramCounter {
+        Ok(NGramCounter {
             prefix_map: BTreeMap::new(),
-            n,
-            stats: ProcessingStats {
-                total_tokens: 0,
-                unique_ngrams: 0,
-                total_ngram_occurrences: 0,
-                most_common_ngram: None,
-                most_popular_prefix: None,
-            },
-            window: VecDeque::with_capacity(prefix_size),
+            token_counts: HashMap::new(),
+            token_order: Vec::new(),
+            n: positions.len() + 1,
+            positions,
+            gapped,
+            stats: ProcessingStats::default(),
+            window: VecDeque::with_capacity(span),
             metadata: None,
             normalizer: Normalizer::new(NormalizerConfig::new(punctuation)),
-        }
+            trace: None,
+            trace_limit: None,
+            context: None,
+        })
+    }
+
+    /// Also counts context columns for the given word classes (see [`ContextColumns`])
+    pub fn enable_context(&mut self, classes: WordClasses) {
+        self.context = Some(ContextColumns::new(classes));
+    }
+
+    /// Records each count update (see [`NGramCounter::get_trace`]), keeping at most `limit` steps
+    pub fn enable_trace(&mut self, limit: Option<usize>) {
+        self.trace = Some(Vec::new());
+        self.trace_limit = limit;
     }
 
     /// Process a single line of text
     pub fn process_line(&mut self, line: &str) {
         let words = self.normalizer.normalize_line(line);
-        let prefix_size = self.n - 1;
+        self.process_tokens(words);
+    }
+
+    /// Count a line of already-normalized tokens
+    fn process_tokens(&mut self, words: Vec<String>) {
+        let span = self.positions.first().copied().unwrap_or(0);
 
         // Add to token count
         self.stats.total_tokens += words.len();
 
         // Process each word
-        for word in words {
-            // If the window is full (contains n-1 words), we have a complete N-gram prefix
-            if self.window.len() == prefix_size {
-                let prefix = self.window.iter().cloned().collect::<Vec<String>>();
-                let follower = word.clone();
+        let first_index = self.stats.total_tokens - words.len();
+        for (offset, word) in words.into_iter().enumerate() {
+            // Keys are looked up by reference so that words are only copied the first time
+            match self.token_counts.get_mut(&word) {
+                Some(count) => *count += 1,
+                None => {
+                    self.token_counts.insert(word.clone(), 1);
+                    self.token_order.push(word.clone());
+                }
+            }
+
+            // If the window is full (reaches back to the furthest position), we have a complete prefix
+            if self.window.len() == span {
+                let prefix = self.window_prefix();
 
                 // Update the frequency map
-                self.prefix_map
-                    .entry(prefix)
-                    .or_insert_with(HashMap::new)
-                    .entry(follower)
-                    .and_modify(|count| {
+                if !self.prefix_map.contains_key(&prefix) {
+                    self.prefix_map.insert(prefix.clone(), HashMap::new());
+                }
+                let followers = self
+                    .prefix_map
+                    .get_mut(&prefix)
+                    .expect("the prefix was just inserted");
+                let count = match followers.get_mut(&word) {
+                    Some(count) => {
                         *count += 1;
-                        self.stats.total_ngram_occurrences += 1;
-                    })
-                    .or_insert_with(|| {
-                        self.stats.total_ngram_occurrences += 1;
+                        *count
+                    }
+                    None => {
+                        followers.insert(word.clone(), 1);
                         1
-                    });
+                    }
+                };
+                self.stats.total_ngram_occurrences += 1;
+
+                if let Some(context) = &mut self.context
+                    && let Some(previous) = prefix.iter().rev().find(|w| *w != GAP_TOKEN)
+                {
+                    context.record(previous, &word);
+                }
 
-                // Slide the window: remove the oldest word
-                self.window.pop_front();
+                if let Some(trace) = &mut self.trace
+                    && self.trace_limit.is_none_or(|limit| trace.len() < limit)
+                {
+                    trace.push(TraceStep {
+                        step: trace.len() + 1,
+                        token_index: first_index + offset + 1,
+                        prefix,
+                        follower: word.clone(),
+                        count,
+                    });
+                }
             }
-            // Add the current word to the window
+            // Slide the window: add the current word and drop the oldest once it's over size
             self.window.push_back(word);
+            if self.window.len() > span {
+                self.window.pop_front();
+            }
+        }
+    }
+
+    /// The prefix for a full window: the words at the context positions, with gaps marked
+    fn window_prefix(&self) -> Vec<String> {
+        if !self.gapped {
+            return self.window.iter().cloned().collect();
         }
+        let span = self.window.len();
+        (1..=span)
+            .rev()
+            .map(|back| {
+                if self.positions.contains(&back) {
+                    self.window[span - back].clone()
+                } else {
+                    GAP_TOKEN.to_string()
+                }
+            })
+            .collect()
     }
 
     /// Process a file containing text with frontmatter
     pub fn process_file<P: AsRef<Path>>(&mut self, path: P) -> io::Result<()> {
         use std::io::{BufRead, BufReader};
 
-        let file = File::open(&path)?;
-        let mut reader = BufReader::new(file);
-
-        let mut line = String::new();
-        if reader.read_line(&mut line)? == 0 {
-            return Err(io::Error::new(
-                io::ErrorKind::InvalidData,
-                "Input file is empty; expected YAML frontmatter.",
-            ));
-        }
-
-        if line.trim() != "---" {
-            return Err(io::Error::new(
-                io::ErrorKind::InvalidData,
-                "Input must start with '---' followed by YAML frontmatter.",
-            ));
-        }
-
-        let mut frontmatter_raw = String::new();
-        loop {
-            line.clear();
-            let bytes = reader.read_line(&mut line)?;
-            if bytes == 0 {
-                return Err(io::Error::new(
-                    io::ErrorKind::InvalidData,
-                    "Reached end of file before closing frontmatter delimiter '---'.",
-                ));
-            }
-
-            if line.trim() == "---" {
-                break;
-            }
-
-            frontmatter_raw.push_str(&line);
-        }
-
-        self.metadata = Some(parse_frontmatter(&frontmatter_raw, self.n)?);
+        let mut reader = BufReader::new(File::open(&path)?);
+        let frontmatter_raw = read_frontmatter(&mut reader)?;
+        self.set_frontmatter(&frontmatter_raw)?;
 
         for line in reader.lines() {
             self.process_line(&line?);
@@ -197,47 +398,78 @@ impl NGramCounter {
         Ok(())
     }
 
-    /// Calculate statistics after processing
-    fn calculate_statistics(&mut self) {
-        // Find the most common n-gram
-        let mut most_common_count = 0;
-        let mut most_common_prefix = None;
-        let mut most_common_follower = None;
-
-        // Find the prefix with the most cumulative followers
-        let mut most_popular_prefix = None;
-        let mut most_popular_prefix_count = 0;
-
-        for (prefix, followers) in &self.prefix_map {
-            // Calculate the cumulative count for this prefix
-            let total_followers: usize = followers.values().sum();
-
-            // Check if this is the prefix with the most followers
-            if total_followers > most_popular_prefix_count {
-                most_popular_prefix_count = total_followers;
-                most_popular_prefix = Some(prefix.clone());
-            }
+    /// Prunes the counted n-grams (see [`PruneOptions`]) and recalculates the statistics,
+    /// recording what was removed in [`ProcessingStats::pruning`].
+    ///
+    /// Traces and context columns still describe the unpruned text.
+    pub fn prune(&mut self, options: &PruneOptions) -> io::Result<()> {
+        let mut frequency_order = self.token_order.clone();
+        // Stable sort, so equally frequent tokens stay in order of first appearance
+        frequency_order.sort_by(|a, b| self.token_counts[b].cmp(&self.token_counts[a]));
 
-            // Continue with existing logic for finding the most common specific n-gram
-            for (follower, count) in followers {
-                if *count > most_common_count {
-                    most_common_count = *count;
-                    most_common_prefix = Some(prefix.clone());
-                    most_common_follower = Some(follower.clone());
-                }
+        let pruning =
+            prune::prune_counts(&mut self.prefix_map, &frequency_order, options, self.gapped)?;
+
+        if let Some(size) = options.vocabulary
+            && size < frequency_order.len()
+        {
+            for token in &frequency_order[size..] {
+                let count = self.token_counts.remove(token).unwrap_or(0);
+                *self
+                    .token_counts
+                    .entry(UNKNOWN_TOKEN.to_string())
+                    .or_default() += count;
             }
+            let mut seen_unknown = false;
+            self.token_order = std::mem::take(&mut self.token_order)
+                .into_iter()
+                .filter_map(|token| {
+                    if self.token_counts.contains_key(&token) && token != UNKNOWN_TOKEN {
+                        Some(token)
+                    } else if !seen_unknown {
+                        seen_unknown = true;
+                        Some(UNKNOWN_TOKEN.to_string())
+                    } else {
+                        None
+                    }
+                })
+                .collect();
         }
 
-        if let (Some(prefix), Some(follower)) = (most_common_prefix, most_common_follower) {
-            self.stats.most_common_ngram = Some((prefix, follower, most_common_count));
-        }
+        self.stats.total_ngram_occurrences = self
+            .prefix_map
+            .values()
+            .flat_map(|followers| followers.values())
+            .sum();
+        self.stats.most_common_ngram = None;
+        self.stats.most_popular_prefix = None;
+        self.calculate_statistics();
+        self.stats.pruning = Some(pruning);
+        Ok(())
+    }
 
-        if let Some(prefix) = most_popular_prefix {
-            self.stats.most_popular_prefix = Some((prefix, most_popular_prefix_count));
+    /// Parse the metadata for this counter's model from raw YAML frontmatter
+    fn set_frontmatter(&mut self, frontmatter_raw: &str) -> io::Result<()> {
+        let mut metadata = parse_frontmatter(frontmatter_raw, self.n)?;
+        metadata.tokenization = Some(self.tokenization());
+        if self.gapped {
+            metadata.context_positions = self.context_positions();
+            metadata.subtitle = model_subtitle(metadata.n, &metadata.context_positions);
         }
+        self.metadata = Some(metadata);
+        Ok(())
+    }
+
+    /// Calculate statistics after processing
+    fn calculate_statistics(&mut self) {
+        let entries = self.get_entries();
+        fill_ngram_stats(&mut self.stats, &entries);
+        self.stats.corpus = Some(corpus_stats(&self.token_counts, &entries, DEFAULT_TOP_N));
+    }
 
-        // Set the count of unique n-grams
-        self.stats.unique_ngrams = self.prefix_map.len();
+    /// Calculate vocabulary, entropy and frequency statistics, keeping `top_n` items in each top list
+    pub fn corpus_stats(&self, top_n: usize) -> CorpusStats {
+        corpus_stats(&self.token_counts, &self.get_entries(), top_n)
     }
 
     /// Get the results as a sorted list of WordFollowEntry
@@ -245,6 +477,24 @@ impl NGramCounter {
         convert_to_entries(&self.prefix_map)
     }
 
+    /// Get the distinct tokens (with their frequencies) in order of first appearance
+    pub fn get_vocabulary(&self) -> Vec<(String, usize)> {
+        self.token_order
+            .iter()
+            .map(|token| (token.clone(), self.token_counts[token]))
+            .collect()
+    }
+
+    /// Get the recorded count updates (empty unless [`NGramCounter::enable_trace`] was called)
+    pub fn get_trace(&self) -> &[TraceStep] {
+        self.trace.as_deref().unwrap_or_default()
+    }
+
+    /// Get the context column counts (if [`NGramCounter::enable_context`] was called)
+    pub fn get_context(&self) -> Option<&ContextColumns> {
+        self.context.as_ref()
+    }
+
     /// Get the statistics collected during processing
     pub fn get_stats(&self) -> &ProcessingStats {
         &self.stats
@@ -254,6 +504,30 @@ impl NGramCounter {
     pub fn get_metadata(&self) -> Option<&Metadata> {
         self.metadata.as_ref()
     }
+
+    /// Positions of the prefix words relative to the predicted word, furthest first
+    /// (e.g. `[-3, -1]`)
+    pub fn context_positions(&self) -> Vec<isize> {
+        self.positions
+            .iter()
+            .map(|&back| -(back as isize))
+            .collect()
+    }
+
+    /// The tokenizer settings this counter was created with
+    pub fn tokenization(&self) -> Tokenization {
+        let mut punctuation: Vec<char> = self
+            .normalizer
+            .config()
+            .punctuation
+            .iter()
+            .copied()
+            .collect();
+        punctuation.sort_unstable();
+        Tokenization {
+            punctuation: punctuation.into_iter().collect(),
+        }
+    }
 }
 
 /// Processes a text file and returns N-gram following statistics along with summary statistics and metadata
@@ -262,7 +536,7 @@ pub fn process_file<P: AsRef<Path>>(
     n: usize,
 ) -> io::Result<(Vec<WordFollowEntry>, ProcessingStats, Option<Metadata>)> {
     let punctuation = vec![',', '.'];
-    let mut counter = NGramCounter::new(n, punctuation);
+    let mut counter = NGramCounter::new(n, punctuation)?;
     counter.process_file(path)?;
 
     let entries = counter.get_entries();
@@ -272,6 +546,45 @@ pub fn process_file<P: AsRef<Path>>(
     Ok((entries, stats, metadata))
 }
 
+/// Reads the YAML frontmatter between the opening and closing `---` lines, leaving the
+/// reader at the start of the text
+fn read_frontmatter<R: io::BufRead>(reader: &mut R) -> io::Result<String> {
+    let mut line = String::new();
+    if reader.read_line(&mut line)? == 0 {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            "Input file is empty; expected YAML frontmatter.",
+        ));
+    }
+
+    if line.trim() != "---" {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            "Input must start with '---' followed by YAML frontmatter.",
+        ));
+    }
+
+    let mut frontmatter_raw = String::new();
+    loop {
+        line.clear();
+        let bytes = reader.read_line(&mut line)?;
+        if bytes == 0 {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "Reached end of file before closing frontmatter delimiter '---'.",
+            ));
+        }
+
+        if line.trim() == "---" {
+            break;
+        }
+
+        frontmatter_raw.push_str(&line);
+    }
+
+    Ok(frontmatter_raw)
+}
+
 fn parse_frontmatter(frontmatter_raw: &str, n: usize) -> io::Result<Metadata> {
     use serde_yaml::Value;
 
@@ -306,12 +619,72 @@ fn parse_frontmatter(frontmatter_raw: &str, n: usize) -> io::Result<Metadata> {
         author: author.to_string(),
         url: url.to_string(),
         n,
-        subtitle: format!("A {} language model", model_type_str(n)),
+        subtitle: model_subtitle(n, &[]),
         version: env!("CARGO_PKG_VERSION").to_string(),
         stats: None,
+        context: None,
+        tokenization: None,
+        sources: Vec::new(),
+        context_positions: Vec::new(),
     })
 }
 
+/// Statistics for entries that weren't counted directly from a text (e.g. adapted or merged models).
+///
+/// Token frequencies are estimated from the follower counts, so `total_tokens` is the
+/// number of n-gram occurrences.
+pub fn stats_from_entries(entries: &[WordFollowEntry]) -> ProcessingStats {
+    let mut token_counts: HashMap<String, usize> = HashMap::new();
+    for entry in entries {
+        for (follower, count) in &entry.followers {
+            *token_counts.entry(follower.clone()).or_default() += count;
+        }
+    }
+    let total: usize = token_counts.values().sum();
+
+    let mut stats = ProcessingStats {
+        total_tokens: total,
+        unique_ngrams: 0,
+        total_ngram_occurrences: total,
+        most_common_ngram: None,
+        most_popular_prefix: None,
+        corpus: None,
+        pruning: None,
+    };
+    fill_ngram_stats(&mut stats, entries);
+    stats.corpus = Some(corpus_stats(&token_counts, entries, DEFAULT_TOP_N));
+    stats
+}
+
+/// Fills in the most common n-gram, the most popular prefix and the unique prefix count
+fn fill_ngram_stats(stats: &mut ProcessingStats, entries: &[WordFollowEntry]) {
+    // Find the most common n-gram
+    let mut most_common_count = 0;
+    // Find the prefix with the most cumulative followers
+    let mut most_popular_prefix_count = 0;
+
+    for entry in entries {
+        // Calculate the cumulative count for this prefix
+        let total_followers: usize = entry.followers.iter().map(|(_, count)| count).sum();
+
+        // Check if this is the prefix with the most followers
+        if total_followers > most_popular_prefix_count {
+            most_popular_prefix_count = total_followers;
+            stats.most_popular_prefix = Some((entry.prefix.clone(), total_followers));
+        }
+
+        for (follower, count) in &entry.followers {
+            if *count > most_common_count {
+                most_common_count = *count;
+                stats.most_common_ngram = Some((entry.prefix.clone(), follower.clone(), *count));
+            }
+        }
+    }
+
+    // Set the count of unique n-grams
+    stats.unique_ngrams = entries.len();
+}
+
 /// Converts the internal N-gram HashMap representation to the required output format
 fn convert_to_entries(
     follow_map: &BTreeMap<Vec<String>, HashMap<String, usize>>,
@@ -374,6 +747,220 @@ pub fn split_entries_into_books(
     books
 }
 
+/// Where [`split_books`] may cut between one book and the next
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+#[cfg_attr(feature = "clap", derive(clap::ValueEnum))]
+pub enum SplitBoundary {
+    /// Between any two entries, at even weight cutoffs; a letter may span two books
+    #[default]
+    Entry,
+    /// Only between first letters, so each letter is in exactly one book (e.g. "A-F", "G-M")
+    Letter,
+    /// Only between first words, so e.g. all the "the ..." trigram entries are in one book
+    Word,
+}
+
+/// Splits entries into up to `num_books` books, cutting only at the given boundaries.
+///
+/// For [`SplitBoundary::Letter`] and [`SplitBoundary::Word`] the entries are grouped by first
+/// letter (or first word) and the groups are partitioned to make the heaviest book as light as
+/// possible, so the book labels never overlap. There are fewer books than `num_books` if there
+/// are fewer groups than that.
+pub fn split_books(
+    entries: &[WordFollowEntry],
+    num_books: usize,
+    boundary: SplitBoundary,
+) -> Vec<(String, Vec<WordFollowEntry>)> {
+    if boundary == SplitBoundary::Entry || num_books <= 1 || entries.is_empty() {
+        return split_entries_into_books(entries, num_books);
+    }
+    split_weighted(entries, num_books, boundary, entry_weight)
+}
+
+/// Splits entries into up to `num_books` books of about equal printed thickness, as estimated
+/// by `layout` (rather than by total counts, as [`split_books`] does)
+pub fn split_books_by_pages(
+    entries: &[WordFollowEntry],
+    num_books: usize,
+    boundary: SplitBoundary,
+    layout: &BookLayout,
+) -> Vec<(String, Vec<WordFollowEntry>)> {
+    if num_books <= 1 || entries.is_empty() {
+        return split_entries_into_books(entries, num_books);
+    }
+    split_weighted(entries, num_books, boundary, |entry| {
+        layout_weight(layout, entry)
+    })
+}
+
+/// Splits entries into as few books as possible that are each estimated to fit in
+/// `max_pages` pages (including the front matter), then evens out their thickness.
+///
+/// Errors if the pages leave no room after the front matter, or if a group that can't be
+/// split at `boundary` (e.g. one letter) needs more pages than that on its own.
+pub fn split_books_to_page_limit(
+    entries: &[WordFollowEntry],
+    max_pages: usize,
+    boundary: SplitBoundary,
+    layout: &BookLayout,
+) -> io::Result<Vec<(String, Vec<WordFollowEntry>)>> {
+    if max_pages <= layout.front_matter_pages {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!(
+                "The front matter takes {} pages, so books need more than {} pages",
+                layout.front_matter_pages, max_pages
+            ),
+        ));
+    }
+    if entries.is_empty() {
+        return Ok(split_entries_into_books(entries, 1));
+    }
+
+    let capacity =
+        ((max_pages - layout.front_matter_pages) as f64 * layout.lines_per_page() * LINE_UNITS)
+            .floor() as usize;
+    let groups = group_entries(entries, boundary, |entry| layout_weight(layout, entry));
+    if let Some((start, weight)) = groups.iter().find(|(_, weight)| *weight > capacity) {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!(
+                "The entries for '{}' alone need about {} pages, more than the {} allowed",
+                book_label(&entries[*start], boundary),
+                layout.front_matter_pages
+                    + (*weight as f64 / LINE_UNITS / layout.lines_per_page()).ceil() as usize,
+                max_pages
+            ),
+        ));
+    }
+
+    let weights: Vec<usize> = groups.iter().map(|(_, weight)| *weight).collect();
+    let num_books = greedy_runs(&weights, capacity).len();
+    if num_books == 1 {
+        return Ok(split_entries_into_books(entries, 1));
+    }
+    Ok(split_weighted(entries, num_books, boundary, |entry| {
+        layout_weight(layout, entry)
+    }))
+}
+
+/// Hundredths of a line, so layout estimates can be partitioned as whole numbers
+const LINE_UNITS: f64 = 100.0;
+
+fn layout_weight(layout: &BookLayout, entry: &WordFollowEntry) -> usize {
+    (layout.entry_lines(entry) * LINE_UNITS).round() as usize
+}
+
+/// Runs of entries that can't be split across books: (start index, total weight)
+fn group_entries(
+    entries: &[WordFollowEntry],
+    boundary: SplitBoundary,
+    weight: impl Fn(&WordFollowEntry) -> usize,
+) -> Vec<(usize, usize)> {
+    let mut groups: Vec<(usize, usize)> = Vec::new();
+    for (idx, entry) in entries.iter().enumerate() {
+        let same_group = idx > 0
+            && match boundary {
+                SplitBoundary::Entry => false,
+                SplitBoundary::Letter => prefix_label(entry) == prefix_label(&entries[idx - 1]),
+                SplitBoundary::Word => entry.prefix.first() == entries[idx - 1].prefix.first(),
+            };
+        if !same_group {
+            groups.push((idx, 0));
+        }
+        if let Some(group) = groups.last_mut() {
+            group.1 += weight(entry);
+        }
+    }
+    groups
+}
+
+fn split_weighted(
+    entries: &[WordFollowEntry],
+    num_books: usize,
+    boundary: SplitBoundary,
+    weight: impl Fn(&WordFollowEntry) -> usize,
+) -> Vec<(String, Vec<WordFollowEntry>)> {
+    let groups = group_entries(entries, boundary, weight);
+    let weights: Vec<usize> = groups.iter().map(|(_, weight)| *weight).collect();
+
+    let mut books = Vec::new();
+    let mut start_group = 0;
+    for end_group in partition_groups(&weights, num_books) {
+        let start_idx = groups[start_group].0;
+        let end_idx = groups.get(end_group).map_or(entries.len(), |(idx, _)| *idx);
+        let book_entries = entries[start_idx..end_idx].to_vec();
+        let first = book_label(&book_entries[0], boundary);
+        let last = book_label(&book_entries[book_entries.len() - 1], boundary);
+        let label = if first == last {
+            first
+        } else {
+            format!("{}-{}", first, last)
+        };
+        books.push((label, book_entries));
+        start_group = end_group;
+    }
+
+    books
+}
+
+fn book_label(entry: &WordFollowEntry, boundary: SplitBoundary) -> String {
+    match boundary {
+        SplitBoundary::Word => entry.prefix.first().cloned().unwrap_or_default(),
+        _ => prefix_label(entry),
+    }
+}
+
+/// Splits `weights` into at most `parts` contiguous runs, minimising the heaviest run's total
+/// (the linear partition problem); returns the end index of each run
+fn partition_groups(weights: &[usize], parts: usize) -> Vec<usize> {
+    let parts = parts.min(weights.len()).max(1);
+
+    // The lightest heaviest run that greedy filling can fit into `parts` runs is optimal
+    let mut low = weights.iter().copied().max().unwrap_or(0);
+    let mut high = weights.iter().sum::<usize>();
+    while low < high {
+        let mid = low + (high - low) / 2;
+        if greedy_runs(weights, mid).len() <= parts {
+            high = mid;
+        } else {
+            low = mid + 1;
+        }
+    }
+
+    // Greedy filling may need fewer runs; splitting a run never makes it heavier
+    let mut ends = greedy_runs(weights, low);
+    while ends.len() < parts {
+        let mut start = 0;
+        for i in 0..ends.len() {
+            if ends[i] - start >= 2 {
+                ends.insert(i, ends[i] - 1);
+                break;
+            }
+            start = ends[i];
+        }
+    }
+    ends
+}
+
+/// End indices of the runs made by filling each run up to `capacity` before starting the next
+fn greedy_runs(weights: &[usize], capacity: usize) -> Vec<usize> {
+    let mut ends = Vec::new();
+    let mut running = 0;
+    for (i, weight) in weights.iter().enumerate() {
+        if running > 0 && running + weight > capacity {
+            ends.push(i);
+            running = 0;
+        }
+        running += weight;
+    }
+    if !weights.is_empty() {
+        ends.push(weights.len());
+    }
+    ends
+}
+
 fn entry_weight(entry: &WordFollowEntry) -> usize {
     let weight: usize = entry.followers.iter().map(|(_, count)| *count).sum();
     weight.max(1)
@@ -407,6 +994,57 @@ fn prefix_label(entry: &WordFollowEntry) -> String {
         .unwrap_or_else(|| "?".to_string())
 }
 
+/// Saves the summary statistics (see [`StatsSummary`]) to a JSON file
+pub fn save_stats_json<P: AsRef<Path>>(
+    path: P,
+    metadata: Option<&Metadata>,
+    n: usize,
+    stats: &ProcessingStats,
+) -> io::Result<()> {
+    let file = File::create(path)?;
+    serde_json::to_writer_pretty(file, &StatsSummary::new(metadata, n, stats))?;
+    Ok(())
+}
+
+/// An entry's roll table as printed in a booklet: the total to roll up to, and each follower
+/// with its cumulative count (the highest roll that selects it), scaled for d10 dice unless `raw`.
+///
+/// With d10 scaling the total is 10^k - 1 for a total count of k digits (e.g. 99 for 75), so
+/// each die gives one digit of the roll.
+pub(crate) fn roll_table(entry: &WordFollowEntry, raw: bool) -> (usize, Vec<(String, usize)>) {
+    let total_original_count: usize = entry.followers.iter().map(|(_, count)| count).sum();
+    if total_original_count == 0 {
+        // If there are no follower occurrences, total is 0, no follower data.
+        return (0, Vec::new());
+    }
+
+    // Followers are already sorted by count (largest to smallest) from convert_to_entries
+    let mut running_sum = 0;
+    let original_cumulative_counts = entry.followers.iter().map(|(follower, count)| {
+        running_sum += count;
+        (follower.clone(), running_sum)
+    });
+
+    if raw {
+        // Raw output mode - no scaling
+        return (total_original_count, original_cumulative_counts.collect());
+    }
+
+    // Always use 10^k-1 scaling for d10 (0-9 range on each die)
+    // k is the number of digits in total_original_count
+    let k_digits = total_original_count.to_string().len() as u32;
+    // max_val is 10^k_digits - 1 (e.g., if count is 75, k=2, max_val=99)
+    let max_val_for_scaling = 10_usize.pow(k_digits).saturating_sub(1);
+    let scaling_factor = max_val_for_scaling as f64 / total_original_count as f64;
+    let followers = original_cumulative_counts
+        .map(|(follower_word, original_cumul)| {
+            let scaled_cumul = (original_cumul as f64 * scaling_factor).round() as usize;
+            (follower_word, scaled_cumul)
+        })
+        .collect();
+    (max_val_for_scaling, followers)
+}
+
 /// Saves the N-gram follow entries to a JSON file
 pub fn save_to_json<P: AsRef<Path>>(
     entries: &[WordFollowEntry],
@@ -416,72 +1054,24 @@ pub fn save_to_json<P: AsRef<Path>>(
     raw: bool,
 ) -> io::Result<()> {
     // Convert entries to the required format: ["joined prefix", total_count, ["follower", cumulative_count], ...]
-    let formatted_entries: Vec<Vec<serde_json::Value>> = entries
-        .iter()
-        .map(|entry| {
-            let mut formatted_entry_json = Vec::new();
-            // First element is the joined prefix string
-            let prefix_str = entry.prefix.join(" ");
-            formatted_entry_json.push(serde_json::Value::String(prefix_str.clone()));
-
-            // Calculate the total sum of occurrences for all followers
-            let total_original_count: usize = entry.followers.iter().map(|(_, count)| count).sum();
-
-            // Followers are already sorted by count (largest to smallest) from convert_to_entries
-            // Get number of unique followers (no need to sort as we only need the count)
-            let _num_unique_followers = entry.followers.len();
-
-            // Calculate original cumulative counts
-            let mut original_cumulative_counts = Vec::new();
-            let mut running_sum = 0;
-
-            for (follower, count) in &entry.followers {
-                running_sum += count;
-                original_cumulative_counts.push((follower.clone(), running_sum));
-            }
-
-            // Determine scaling strategy and apply it
-            let (json_total_for_prefix, scaled_follower_values_json) = if total_original_count == 0
-            {
-                // If there are no follower occurrences, total is 0, no follower data.
-                (serde_json::json!(0), Vec::new())
-            } else if raw {
-                // Raw output mode - no scaling
-                let actual_json_total = serde_json::json!(total_original_count);
-                let followers_json_list: Vec<serde_json::Value> = original_cumulative_counts
-                    .iter()
-                    .map(|(follower_word, original_cumul)| {
-                        serde_json::json!([follower_word, original_cumul])
-                    })
-                    .collect();
-                (actual_json_total, followers_json_list)
-            } else {
-                // Always use 10^k-1 scaling for d10 (0-9 range on each die)
-                // k is the number of digits in total_original_count
-                let k_digits = total_original_count.to_string().len() as u32;
-                // max_val is 10^k_digits - 1 (e.g., if count is 75, k=2, max_val=99)
-                let max_val_for_scaling = 10_u32.pow(k_digits).saturating_sub(1);
-
-                let actual_json_total = serde_json::json!(max_val_for_scaling);
-                let scaling_factor = max_val_for_scaling as f64 / total_original_count as f64;
-
-                let followers_json_list: Vec<serde_json::Value> = original_cumulative_counts
-                    .iter()
-                    .map(|(follower_word, original_cumul)| {
-                        let scaled_cumul =
-                            (*original_cumul as f64 * scaling_factor).round() as usize;
-                        serde_json::json!([follower_word, scaled_cumul])
-                    })
-                    .collect();
-                (actual_json_total, followers_json_list)
-            };
-
-            formatted_entry_json.push(json_total_for_prefix);
-            formatted_entry_json.extend(scaled_follower_values_json);
-
-            formatted_entry_json
-        })
-        .collect();
+    let formatted_entries: Vec<Vec<serde_json::Value>> =
+        entries
+            .iter()
+            .map(|entry| {
+                let mut formatted_entry_json = Vec::new();
+                // First element is the joined prefix string
+                let prefix_str = entry.prefix.join(" ");
+                formatted_entry_json.push(serde_json::Value::String(prefix_str.clone()));
+
+                let (total, followers) = roll_table(entry, raw);
+                formatted_entry_json.push(serde_json::json!(total));
+                formatted_entry_json.extend(followers.iter().map(|(follower_word, cumulative)| {
+                    serde_json::json!([follower_word, cumulative])
+                }));
+
+                formatted_entry_json
+            })
+            .collect();
 
     // Build the full output object with metadata and data
     let mut output = serde_json::Map::new();
@@ -505,6 +1095,9 @@ pub fn save_to_json<P: AsRef<Path>>(
         output.insert("metadata".to_string(), serde_json::Value::Object(meta_map));
     }
 
+    let scaling = if raw { Scaling::Raw } else { Scaling::D10 };
+    output.insert("scaling".to_string(), serde_json::to_value(scaling)?);
+
     // Add data
     output.insert("data".to_string(), serde_json::to_value(formatted_entries)?);
 
@@ -524,7 +1117,7 @@ mod tests {
     #[test]
     fn test_follower_sort_order() {
         // Test the sorting of followers by count (largest to smallest)
-        let mut counter = NGramCounter::new(2, vec![',', '.']);
+        let mut counter = NGramCounter::new(2, vec![',', '.']).unwrap();
         counter.process_line("the cat sat on the mat and the cat ate");
 
         // Get entries and check sorting
@@ -540,7 +1133,7 @@ mod tests {
         assert_eq!(the_entry.followers[1].1, 1);
 
         // Test equal counts with alphabetical tiebreaker
-        let mut counter2 = NGramCounter::new(2, vec![',', '.']);
+        let mut counter2 = NGramCounter::new(2, vec![',', '.']).unwrap();
         counter2.process_line("he no test he yes test");
 
         let entries2 = counter2.get_entries();
@@ -553,6 +1146,80 @@ mod tests {
         assert_eq!(he_entry.followers[1].1, 1);
     }
 
+    #[test]
+    fn test_unigram_counts() -> io::Result<()> {
+        let mut counter = NGramCounter::new(1, vec![',', '.'])?;
+        counter.process_line("the cat sat on the mat.");
+        counter.process_line("the end.");
+
+        // A single row with an empty prefix, counting every token
+        let entries = counter.get_entries();
+        assert_eq!(entries.len(), 1);
+        assert!(entries[0].prefix.is_empty());
+        assert_eq!(entries[0].followers[0], ("the".to_string(), 3));
+        assert_eq!(entries[0].followers[1], (".".to_string(), 2));
+        assert_eq!(counter.get_stats().total_ngram_occurrences, 10);
+
+        // The empty prefix survives a round trip through the JSON file
+        let file = NamedTempFile::new()?;
+        save_to_json(&entries, file.path(), None, None, false)?;
+        let model = load_model(file.path())?;
+        assert!(model.entries[0].prefix.is_empty());
+        assert_eq!(model.entries[0].followers.len(), 7);
+
+        let err = NGramCounter::new(0, vec![]).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidInput);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_skip_gram_positions() -> io::Result<()> {
+        let line = "the cat sat on the mat.";
+
+        // {-2}: each word from the word two back, with the skipped word as a gap
+        let mut counter = NGramCounter::with_positions(&[2], vec![',', '.'])?;
+        counter.process_line(line);
+        let entries = counter.get_entries();
+        let the = entries.iter().find(|e| e.prefix == ["the", "_"]).unwrap();
+        assert_eq!(
+            the.followers,
+            vec![(".".to_string(), 1), ("sat".to_string(), 1)]
+        );
+        assert_eq!(counter.get_stats().total_ngram_occurrences, 5);
+        assert_eq!(counter.context_positions(), vec![-2]);
+
+        // {-1, -3}, given in any order
+        let mut counter = NGramCounter::with_positions(&[1, 3], vec![',', '.'])?;
+        counter.process_line(line);
+        let prefixes: Vec<Vec<String>> = counter
+            .get_entries()
+            .into_iter()
+            .map(|e| e.prefix)
+            .collect();
+        assert_eq!(
+            prefixes,
+            vec![
+                vec!["cat", "_", "on"],
+                vec!["on", "_", "mat"],
+                vec!["sat", "_", "the"],
+                vec!["the", "_", "sat"],
+            ]
+        );
+        assert_eq!(counter.context_positions(), vec![-3, -1]);
+
+        // Contiguous positions are an ordinary n-gram model
+        let mut contiguous = NGramCounter::with_positions(&[2, 1], vec![',', '.'])?;
+        let mut trigram = NGramCounter::new(3, vec![',', '.'])?;
+        contiguous.process_line(line);
+        trigram.process_line(line);
+        assert_eq!(contiguous.get_entries(), trigram.get_entries());
+
+        assert!(NGramCounter::with_positions(&[0, 1], vec![]).is_err());
+
+        Ok(())
+    }
+
     // Tokenization-specific tests live alongside the normalizer in text.rs
 
     #[test]
@@ -766,6 +1433,7 @@ mod tests {
             subtitle: "A bigram language model".to_string(),
             version: "test".to_string(),
             stats: None,
+            ..Default::default()
         };
 
         // Test with default 10^k-1 scaling
@@ -831,6 +1499,7 @@ mod tests {
             subtitle: "A trigram language model".to_string(),
             version: "test".to_string(),
             stats: None,
+            ..Default::default()
         };
 
         // Test with default 10^k-1 scaling
@@ -895,6 +1564,7 @@ mod tests {
             subtitle: "A bigram language model".to_string(),
             version: "test".to_string(),
             stats: None,
+            ..Default::default()
         };
 
         // Test with default 10^k-1 scaling
@@ -940,6 +1610,7 @@ mod tests {
     }
 
     #[test]
+    #[allow(clippy::needless_borrows_for_generic_args)]
     fn test_save_to_json_raw_output() -> Result<(), Box<dyn std::error::Error>> {
         use serde_json::Value;
         use std::fs;
@@ -969,6 +1640,7 @@ mod tests {
             subtitle: "A bigram language model".to_string(),
             version: "test".to_string(),
             stats: None,
+            ..Default::default()
         };
 
         // Test with raw=true (no scaling)
@@ -1027,6 +1699,7 @@ mod tests {
             subtitle: "A bigram language model".to_string(),
             version: "test".to_string(),
             stats: None,
+            ..Default::default()
         };
 
         // Test raw output
@@ -1129,6 +1802,97 @@ mod tests {
     }
 
     #[test]
+    fn test_split_books_at_letter_boundaries() {
+        let entry = |prefix: &[&str], count: usize| WordFollowEntry {
+            prefix: prefix.iter().map(|w| w.to_string()).collect(),
+            followers: vec![("x".to_string(), count)],
+        };
+        let entries = vec![
+            entry(&["ant", "hill"], 5),
+            entry(&["apple", "pie"], 5),
+            entry(&["bat", "man"], 50),
+            entry(&["bee", "hive"], 20),
+            entry(&["cat", "nap"], 20),
+            entry(&["dog", "day"], 10),
+            entry(&["dog", "star"], 10),
+        ];
+
+        // Weight cutoffs put B in both books; letter boundaries keep it together
+        let books = split_entries_into_books(&entries, 2);
+        assert_eq!(books[0].0, "A-B");
+        assert_eq!(books[1].0, "B-D");
+
+        let books = split_books(&entries, 2, SplitBoundary::Letter);
+        let labels: Vec<&str> = books.iter().map(|(label, _)| label.as_str()).collect();
+        assert_eq!(labels, vec!["A-B", "C-D"]);
+        assert_eq!(books[0].1.len(), 4);
+
+        // Three books: B alone weighs 70, so A | B | C-D beats A-B | C | D
+        let books = split_books(&entries, 3, SplitBoundary::Letter);
+        let labels: Vec<&str> = books.iter().map(|(label, _)| label.as_str()).collect();
+        assert_eq!(labels, vec!["A", "B", "C-D"]);
+
+        // Word boundaries keep both "dog" entries together
+        let books = split_books(&entries, 6, SplitBoundary::Word);
+        assert_eq!(books.len(), 6);
+        assert_eq!(books[5].0, "dog");
+        assert_eq!(books[5].1.len(), 2);
+
+        // More books than letters: one book per letter
+        assert_eq!(split_books(&entries, 10, SplitBoundary::Letter).len(), 4);
+        assert_eq!(partition_groups(&[1, 2, 3, 4, 5], 2), vec![3, 5]);
+    }
+
+    #[test]
+    fn test_split_books_by_pages() -> io::Result<()> {
+        let layout = BookLayout::default();
+        // Long rows print much bigger than their counts suggest
+        let long_row = |word: &str| WordFollowEntry {
+            prefix: vec![word.to_string()],
+            followers: (0..40).map(|i| (format!("w{}", i), 1)).collect(),
+        };
+        let short_row = |word: &str| WordFollowEntry {
+            prefix: vec![word.to_string()],
+            followers: vec![("x".to_string(), 40)],
+        };
+        let mut entries: Vec<WordFollowEntry> = ["aa", "ab", "ac", "ad"]
+            .iter()
+            .map(|w| long_row(w))
+            .collect();
+        entries.extend(["ba", "bb", "bc", "bd"].iter().map(|w| short_row(w)));
+
+        // Equal counts, so count balancing cuts in the middle...
+        let by_count = split_books(&entries, 2, SplitBoundary::Entry);
+        assert_eq!(by_count[0].1.len(), 4);
+        // ...but page balancing gives the long rows a book of their own
+        let by_pages = split_books_by_pages(&entries, 2, SplitBoundary::Entry, &layout);
+        assert_eq!(by_pages[0].1.len(), 2);
+        let pages: Vec<usize> = by_pages
+            .iter()
+            .map(|(_, book)| layout.estimate_pages(book))
+            .collect();
+        assert_eq!(pages, vec![5, 5]);
+
+        // A page limit picks the number of books
+        let many: Vec<WordFollowEntry> = (0..600)
+            .map(|i| long_row(&format!("{}{}", (b'a' + (i / 26 % 26) as u8) as char, i)))
+            .collect();
+        let books = split_books_to_page_limit(&many, 12, SplitBoundary::Entry, &layout)?;
+        assert!(books.len() > 1);
+        assert!(
+            books
+                .iter()
+                .all(|(_, book)| layout.estimate_pages(book) <= 12)
+        );
+        assert!(split_books_to_page_limit(&many, 4, SplitBoundary::Entry, &layout).is_err());
+        // All the "a..." rows together need more than 5 pages
+        assert!(split_books_to_page_limit(&many, 5, SplitBoundary::Letter, &layout).is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    #[allow(clippy::len_zero)]
     fn test_split_entries_balanced() {
         // Create entries with uneven distribution of followers
         let entries = vec![
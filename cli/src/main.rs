@@ -1,48 +1,653 @@
-use clap::Parser;
+use clap::{Parser, Subcommand, ValueEnum};
 use llms_unplugged::{
-    Metadata, NGramCounter, ProcessingStats, WordFollowEntry, save_to_json,
-    split_entries_into_books,
+    BookIndex, BookLayout, BookManifest, BookletFormat, BuildManifest, BuildRecord, BuildState,
+    BuildTarget, CollapseOptions, Dice, DistanceMetric, EmbeddingValues, Embeddings, MatrixCounts,
+    MatrixOptions, MatrixOrder, Metadata, Model, MultiOrderCounter, NGramCounter, PageStyle,
+    ProcessingStats, PruneOptions, Scaling, SplitBoundary, TraceStep, WordClasses, WordFollowEntry,
+    apply_adapter, book_index, combine_context, diff_models, export_templates, joker_model,
+    load_model, merge_models, restrict_to_vocabulary, save_booklet, save_stats_json, save_to_json,
+    simulate_collapse, split_books, split_books_by_pages, split_books_to_page_limit,
+    stats_from_entries, summarize, training_grid, write_matrix, write_trace_jsonl,
+    write_trace_table,
 };
+use rand::SeedableRng;
+use rand::rngs::StdRng;
 use std::io;
+use std::num::NonZeroUsize;
 use std::path::{Path, PathBuf};
-use std::process::Command;
+use std::sync::Mutex;
+use std::sync::atomic::{AtomicUsize, Ordering};
 
 /// A simple language model builder that processes text files and outputs word following statistics
 #[derive(Parser, Debug)]
-#[command(author, version, about, long_about = None)]
+#[command(
+    author,
+    version,
+    about,
+    long_about = None,
+    args_conflicts_with_subcommands = true,
+    subcommand_negates_reqs = true
+)]
 struct Args {
+    #[command(subcommand)]
+    command: Option<Commands>,
+
     /// Input text file to process
-    #[arg(index = 1)]
-    input: PathBuf,
+    #[arg(index = 1, required = true)]
+    input: Option<PathBuf>,
 
-    /// Output JSON file for results (defaults to "model.json")
-    #[arg(short, long, default_value = "model.json")]
-    output: PathBuf,
+    /// Output file for results (defaults to "model.json", or "model" with the extension for
+    /// --format); the extension is only added if the path has none
+    #[arg(short, long)]
+    output: Option<PathBuf>,
 
-    /// The size of the N-gram (e.g., 2 for bigrams, 3 for trigrams).
-    #[arg(short, long, default_value_t = 2)]
-    n: usize,
+    /// The size of the N-gram (e.g., 1 for unigrams, 2 for bigrams, 3 for trigrams), or a range
+    /// such as 2..4 to count every size in one pass and write one model per size
+    #[arg(short, long, default_value = "2", value_parser = parse_orders)]
+    n: Orders,
+
+    /// Predict each word from the words at these positions before it, e.g. -2 for a skip-gram
+    /// model using the word two back, or -3,-1 (replaces -n)
+    #[arg(
+        long,
+        value_delimiter = ',',
+        allow_hyphen_values = true,
+        conflicts_with = "n",
+        value_parser = parse_position
+    )]
+    positions: Option<Vec<usize>>,
+
+    #[command(flatten)]
+    books: BookArgs,
+
+    /// Run typst compile on the generated JSON files to create PDFs
+    #[arg(long = "typst")]
+    run_typst: bool,
+
+    /// Output raw counts without scaling
+    #[arg(long = "raw")]
+    raw: bool,
+
+    /// Punctuation characters to preserve as separate tokens (default: ",.")
+    #[arg(short = 'p', long = "punctuation", default_value = ",.")]
+    punctuation: String,
+
+    /// Also write the corpus statistics (vocabulary, entropy, Zipf fit, etc.) to this JSON file
+    #[arg(long = "stats-json")]
+    stats_json: Option<PathBuf>,
+
+    /// Output format: a booklet JSON model (for typst), a booklet as a standalone HTML or Markdown
+    /// file, or a prefix-by-follower matrix for worksheets
+    #[arg(long = "format", value_enum, default_value_t = OutputFormat::Json)]
+    format: OutputFormat,
+
+    /// Matrix cells: raw counts or running totals along each row
+    #[arg(long = "matrix-counts", value_enum, default_value_t = MatrixCounts::Cumulative)]
+    matrix_counts: MatrixCounts,
+
+    /// Ordering of the matrix rows and columns
+    #[arg(long = "matrix-order", value_enum, default_value_t = MatrixOrder::Alphabetical)]
+    matrix_order: MatrixOrder,
+
+    /// Keep only the N most frequent words in the matrix
+    #[arg(long = "max-vocab")]
+    max_vocab: Option<usize>,
+
+    /// Write 0 in matrix cells with no count (instead of leaving them blank)
+    #[arg(long = "zero-cells")]
+    zero_cells: bool,
+
+    /// Write a step-by-step training trace (each count update, in order) to this file
+    #[arg(long = "trace")]
+    trace: Option<PathBuf>,
+
+    /// Trace format: one JSON object per line, or a printable table
+    #[arg(long = "trace-format", value_enum, default_value_t = TraceFormat::Jsonl)]
+    trace_format: TraceFormat,
+
+    /// Keep only the first N steps of the trace
+    #[arg(long = "trace-limit")]
+    trace_limit: Option<usize>,
+
+    #[command(flatten)]
+    prune: PruneArgs,
 
+    #[command(flatten)]
+    context: ContextArgs,
+
+    /// Add the context columns into the booklet's follower counts (as in the handout's generation
+    /// step). Every count in a matching column is added, so the numbers can be higher than the
+    /// handout's worked example, which leaves some followers out
+    #[arg(long = "combine-context", requires = "context_columns")]
+    combine_context: bool,
+}
+
+/// The n-gram sizes to build: a single size, or an inclusive range
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+struct Orders {
+    min: usize,
+    max: usize,
+}
+
+/// Parses "3" or "2..4" (also "2..=4")
+fn parse_orders(value: &str) -> Result<Orders, String> {
+    let parse = |s: &str| {
+        s.trim()
+            .parse::<usize>()
+            .map_err(|_| format!("'{}' is not an n-gram size", s.trim()))
+    };
+    let (min, max) = match value.split_once("..") {
+        Some((min, max)) => (parse(min)?, parse(max.trim_start_matches('='))?),
+        None => {
+            let n = parse(value)?;
+            (n, n)
+        }
+    };
+    if min > max {
+        return Err(format!("the range {}..{} is empty", min, max));
+    }
+    Ok(Orders { min, max })
+}
+
+/// Parses a context position such as "-2" into how many words back it is (2)
+fn parse_position(value: &str) -> Result<usize, String> {
+    match value.trim().parse::<isize>() {
+        Ok(0) => Err("position 0 is the predicted word itself".to_string()),
+        Ok(position) if position < 0 => Ok(position.unsigned_abs()),
+        Ok(position) => Err(format!(
+            "position {} is after the predicted word (use -{} for the word {} back)",
+            value.trim(),
+            position,
+            position
+        )),
+        Err(_) => Err(format!("'{}' is not a position", value.trim())),
+    }
+}
+
+/// Options for shrinking a model to a classroom-sized booklet
+#[derive(clap::Args, Debug)]
+struct PruneArgs {
+    /// Keep only the V most frequent tokens, replacing the rest with <unk>
+    #[arg(long = "prune-vocab")]
+    prune_vocab: Option<usize>,
+
+    /// Drop followers counted fewer than this many times
+    #[arg(long = "min-count")]
+    min_count: Option<usize>,
+
+    /// Keep at most K followers per prefix (the most frequent)
+    #[arg(long = "max-followers")]
+    max_followers: Option<usize>,
+
+    /// Drop the least frequent prefixes until the booklet is estimated to fit in this many pages
+    #[arg(long = "max-pages")]
+    max_pages: Option<usize>,
+}
+
+impl PruneArgs {
+    fn options(&self, layout: BookLayout) -> PruneOptions {
+        PruneOptions {
+            vocabulary: self.prune_vocab,
+            min_count: self.min_count,
+            max_followers: self.max_followers,
+            max_pages: self.max_pages,
+            layout,
+        }
+    }
+}
+
+/// Options for splitting a model into books and estimating their printed size
+#[derive(clap::Args, Debug)]
+struct BookArgs {
     /// Number of books to split the output into (default 1 = no splitting)
     #[arg(short = 'b', long = "books", default_value_t = 1)]
     num_books: usize,
 
-    /// Run typst compile on the generated JSON files to create PDFs
+    /// Where books may be split: between any entries, or only between first letters or first
+    /// words (so book ranges never overlap)
+    #[arg(long = "split-at", value_enum, default_value_t = SplitBoundary::Entry)]
+    split_at: SplitBoundary,
+
+    /// Balance the books by estimated printed pages rather than by total counts
+    #[arg(long = "balance-pages")]
+    balance_pages: bool,
+
+    /// Split into as many books as needed for each to fit in this many (estimated) pages
+    #[arg(long = "pages-per-book", conflicts_with = "num_books")]
+    pages_per_book: Option<usize>,
+
+    /// Paper size of the booklet (a3, a4, a5 or us-letter)
+    #[arg(long = "paper", default_value = "a4")]
+    paper: String,
+
+    /// Body text size of the booklet in points (e.g. 9 or 9pt)
+    #[arg(long = "font-size", default_value = "8", value_parser = parse_font_size)]
+    font_size: f64,
+
+    /// Columns per page of the booklet
+    #[arg(long = "columns", default_value_t = 4)]
+    columns: usize,
+
+    /// Typst template for the booklets (with --typst; default: the bundled book.typ). An index.typ
+    /// next to it is used for the book index
+    #[arg(long = "template")]
+    template: Option<PathBuf>,
+
+    /// Maximum number of typst runs at once (with --typst; default: one per CPU)
+    #[arg(long = "jobs")]
+    jobs: Option<NonZeroUsize>,
+
+    /// Directory for the PDFs (with --typst; default: next to the JSON files)
+    #[arg(long = "pdf-dir")]
+    pdf_dir: Option<PathBuf>,
+}
+
+/// Parses a font size in points, with or without the "pt" unit
+fn parse_font_size(value: &str) -> Result<f64, String> {
+    let points = value.trim().trim_end_matches("pt").trim();
+    match points.parse::<f64>() {
+        Ok(size) if size.is_finite() && size > 0.0 => Ok(size),
+        _ => Err(format!("'{}' is not a font size in points", value)),
+    }
+}
+
+impl BookArgs {
+    fn layout(&self) -> io::Result<BookLayout> {
+        BookLayout::for_page(&self.paper, self.font_size, self.columns)
+    }
+
+    fn page_style(&self) -> PageStyle {
+        PageStyle {
+            paper: self.paper.to_lowercase(),
+            font_size: self.font_size,
+            columns: self.columns,
+        }
+    }
+
+    /// Splits the entries into books and prints the estimated pages of each
+    fn split(
+        &self,
+        entries: &[WordFollowEntry],
+    ) -> io::Result<Vec<(String, Vec<WordFollowEntry>)>> {
+        let layout = self.layout()?;
+        let boundary = self.split_at;
+        let books = if let Some(max_pages) = self.pages_per_book {
+            split_books_to_page_limit(entries, max_pages, boundary, &layout)?
+        } else if self.balance_pages {
+            split_books_by_pages(entries, self.num_books, boundary, &layout)
+        } else {
+            split_books(entries, self.num_books, boundary)
+        };
+
+        let estimates: Vec<String> = books
+            .iter()
+            .map(|(range, book)| {
+                let pages = layout.estimate_pages(book);
+                if range.is_empty() {
+                    pages.to_string()
+                } else {
+                    format!("{} ({})", pages, range.replace('-', "–"))
+                }
+            })
+            .collect();
+        println!(
+            "Estimated pages ({} {}pt, {} columns): {}",
+            self.paper,
+            self.font_size,
+            self.columns,
+            estimates.join(", ")
+        );
+
+        Ok(books)
+    }
+}
+
+/// Options for "after verb"/"after pronoun"/"after preposition" context columns
+#[derive(clap::Args, Debug)]
+struct ContextArgs {
+    /// Count context columns (see the Context Columns handout) using the built-in word lists
+    #[arg(long = "context-columns")]
+    context_columns: bool,
+
+    /// YAML file mapping class names to word lists, replacing/extending the built-in lists
+    #[arg(long = "word-classes", requires = "context_columns")]
+    word_classes: Option<PathBuf>,
+}
+
+impl ContextArgs {
+    /// The word classes to use, or None if context columns are off
+    fn word_classes(&self) -> io::Result<Option<WordClasses>> {
+        if !self.context_columns {
+            return Ok(None);
+        }
+        let mut classes = WordClasses::builtin();
+        if let Some(path) = &self.word_classes {
+            classes.merge(WordClasses::from_yaml_file(path)?);
+        }
+        Ok(Some(classes))
+    }
+}
+
+#[derive(ValueEnum, Clone, Copy, Debug)]
+enum TraceFormat {
+    Jsonl,
+    Table,
+}
+
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+enum OutputFormat {
+    Json,
+    Html,
+    Markdown,
+    TsvMatrix,
+    CsvMatrix,
+}
+
+impl OutputFormat {
+    fn extension(self) -> &'static str {
+        match self {
+            OutputFormat::Json => "json",
+            OutputFormat::Html => "html",
+            OutputFormat::Markdown => "md",
+            OutputFormat::TsvMatrix => "tsv",
+            OutputFormat::CsvMatrix => "csv",
+        }
+    }
+}
+
+#[derive(Subcommand, Debug)]
+enum Commands {
+    /// Summarise model JSON files (e.g. for summary.typ), deduplicating split books
+    Summarize(SummarizeArgs),
+    /// Build the hand-training bigram grid for a short text (blank worksheet and answer key)
+    Grid(GridArgs),
+    /// Compute the embedding distance matrix (see the Word Embeddings handout) for a model JSON file
+    Embed(EmbedArgs),
+    /// Train a LoRA-style adapter on a second text and add it to a base model
+    Adapt(AdaptArgs),
+    /// Simulate model collapse: repeatedly generate text and retrain on it
+    Collapse(CollapseArgs),
+    /// Compare two models (model JSON files or text files): vocabulary, transitions and divergence
+    Diff(DiffArgs),
+    /// Merge model JSON files (e.g. one per book) by adding their counts, optionally weighted
+    Merge(MergeArgs),
+    /// Build every booklet listed in a TOML build manifest, skipping those that are up to date
+    Build(BuildArgs),
+    /// Work with the typst templates built into this tool
+    Templates(TemplatesArgs),
+}
+
+#[derive(clap::Args, Debug)]
+struct TemplatesArgs {
+    #[command(subcommand)]
+    command: TemplatesCommand,
+}
+
+#[derive(Subcommand, Debug)]
+enum TemplatesCommand {
+    /// Write copies of the bundled templates to customise (use them with --template)
+    Export(ExportTemplatesArgs),
+}
+
+#[derive(clap::Args, Debug)]
+struct ExportTemplatesArgs {
+    /// Directory to write the templates to
+    #[arg(default_value = "templates")]
+    dir: PathBuf,
+
+    /// Replace existing files
+    #[arg(long = "force")]
+    force: bool,
+}
+
+#[derive(clap::Args, Debug)]
+struct BuildArgs {
+    /// TOML file listing the booklets to build
+    manifest: PathBuf,
+
+    /// Rebuild every booklet, even those that are up to date
+    #[arg(long = "force")]
+    force: bool,
+
+    /// Maximum number of typst runs at once (default: one per CPU)
+    #[arg(long = "jobs")]
+    jobs: Option<NonZeroUsize>,
+}
+
+#[derive(clap::Args, Debug)]
+struct MergeArgs {
+    /// Model JSON files to merge (built with --raw, so the counts can be added)
+    #[arg(required = true)]
+    models: Vec<PathBuf>,
+
+    /// Comma-separated weight for each model's counts, in order (default: 1 for every model)
+    #[arg(short = 'w', long = "weights", value_delimiter = ',')]
+    weights: Vec<f64>,
+
+    /// Output JSON file for the merged model
+    #[arg(short, long, default_value = "merged.json")]
+    output: PathBuf,
+
+    /// Title for the merged model (default: the source titles joined with " + ")
+    #[arg(long = "title")]
+    title: Option<String>,
+
+    #[command(flatten)]
+    books: BookArgs,
+
+    /// Output raw counts without scaling
+    #[arg(long = "raw")]
+    raw: bool,
+
+    /// Run typst compile on the merged model to create PDFs
     #[arg(long = "typst")]
     run_typst: bool,
+}
+
+#[derive(clap::Args, Debug)]
+struct DiffArgs {
+    /// First model (A): a model JSON file (built with --raw), or a text file with frontmatter
+    a: PathBuf,
+
+    /// Second model (B): a model JSON file (built with --raw), or a text file with frontmatter
+    b: PathBuf,
+
+    /// N-gram size for text files (defaults to the n of the other model, or 2)
+    #[arg(short, long)]
+    n: Option<usize>,
+
+    /// Punctuation characters to preserve as separate tokens in text files (default: ",.")
+    #[arg(short = 'p', long = "punctuation", default_value = ",.")]
+    punctuation: String,
+
+    /// Also write the full comparison to this JSON file
+    #[arg(short, long)]
+    output: Option<PathBuf>,
+
+    /// Number of items to print in each section of the summary
+    #[arg(long = "top", default_value_t = 10)]
+    top: usize,
+
+    /// Number of probability shifts to keep per shared prefix
+    #[arg(long = "shifts", default_value_t = 3)]
+    shifts: usize,
+}
+
+#[derive(clap::Args, Debug)]
+#[command(group = clap::ArgGroup::new("source").required(true))]
+struct CollapseArgs {
+    /// Text file (with frontmatter) to train generation 0 on
+    #[arg(group = "source")]
+    input: Option<PathBuf>,
+
+    /// Joker mode: start from a random bigram grid over these words, e.g. "pizza robot moon dance"
+    #[arg(long = "joker", group = "source")]
+    joker: Option<String>,
+
+    /// The size of the N-gram (e.g., 2 for bigrams, 3 for trigrams).
+    #[arg(short, long, default_value_t = 2)]
+    n: usize,
+
+    /// Number of generations to retrain after generation 0
+    #[arg(short = 'g', long = "generations", default_value_t = 5)]
+    generations: usize,
+
+    /// Number of tokens to generate per generation
+    #[arg(short = 't', long = "tokens", default_value_t = 100)]
+    tokens: usize,
+
+    /// Random seed, for reproducible runs (a random one is chosen and reported otherwise)
+    #[arg(long = "seed")]
+    seed: Option<u64>,
+
+    /// Punctuation characters to preserve as separate tokens (default: ",.")
+    #[arg(short = 'p', long = "punctuation", default_value = ",.")]
+    punctuation: String,
+
+    /// Output JSON file for the per-generation results
+    #[arg(short, long, default_value = "collapse.json")]
+    output: PathBuf,
+}
+
+#[derive(clap::Args, Debug)]
+struct AdaptArgs {
+    /// Base model JSON file (build it with --raw so the counts can be added exactly)
+    base: PathBuf,
+
+    /// Text file (with frontmatter) to train the adapter on
+    input: PathBuf,
+
+    /// Output JSON file for the adapted model
+    #[arg(short, long, default_value = "adapted.json")]
+    output: PathBuf,
+
+    /// Output JSON file for the adapter on its own (defaults to <output>_adapter.json)
+    #[arg(long = "adapter-output")]
+    adapter_output: Option<PathBuf>,
+
+    /// Multiply the adapter counts by this before adding them (adaptation strength)
+    #[arg(long = "scale", default_value_t = 1.0)]
+    scale: f64,
+
+    /// Keep adapter rows and columns for words that aren't in the base model
+    #[arg(long = "unrestricted")]
+    unrestricted: bool,
+
+    /// Punctuation characters to preserve as separate tokens (default: the base model's, or ",."
+    /// if it has none recorded)
+    #[arg(short = 'p', long = "punctuation")]
+    punctuation: Option<String>,
+
+    #[command(flatten)]
+    books: BookArgs,
 
     /// Output raw counts without scaling
     #[arg(long = "raw")]
     raw: bool,
 
+    /// Run typst compile on the adapted model to create PDFs
+    #[arg(long = "typst")]
+    run_typst: bool,
+}
+
+#[derive(clap::Args, Debug)]
+struct EmbedArgs {
+    /// Model JSON file (use --raw when building it to get raw counts)
+    model: PathBuf,
+
+    /// Output file for the distance matrix
+    #[arg(short, long, default_value = "distances.tsv")]
+    output: PathBuf,
+
+    /// Output format for the distance matrix
+    #[arg(long = "format", value_enum, default_value_t = DistanceFormat::Tsv)]
+    format: DistanceFormat,
+
+    /// Values in each row embedding
+    #[arg(long = "values", value_enum, default_value_t = EmbeddingValues::Raw)]
+    values: EmbeddingValues,
+
+    /// Distance between embeddings
+    #[arg(long = "metric", value_enum, default_value_t = DistanceMetric::L1)]
+    metric: DistanceMetric,
+
+    /// Print the nearest neighbours of this word
+    #[arg(long = "nearest")]
+    nearest: Option<String>,
+
+    /// Number of nearest neighbours to print
+    #[arg(short = 'k', long = "neighbours", default_value_t = 5)]
+    neighbours: usize,
+}
+
+#[derive(ValueEnum, Clone, Copy, Debug)]
+enum DistanceFormat {
+    Tsv,
+    Json,
+}
+
+#[derive(clap::Args, Debug)]
+struct SummarizeArgs {
+    /// Model JSON files to summarise
+    #[arg(required = true)]
+    models: Vec<PathBuf>,
+
+    /// Output JSON file for the summary
+    #[arg(short, long, default_value = "summary.json")]
+    output: PathBuf,
+}
+
+#[derive(clap::Args, Debug)]
+#[command(group = clap::ArgGroup::new("source").required(true))]
+struct GridArgs {
+    /// Plain text file to build the grid from
+    #[arg(group = "source")]
+    input: Option<PathBuf>,
+
+    /// Text to build the grid from, e.g. "See Spot run. See Spot jump."
+    #[arg(short, long, group = "source")]
+    text: Option<String>,
+
+    /// Output JSON file for the grid data
+    #[arg(short, long, default_value = "grid.json")]
+    output: PathBuf,
+
     /// Punctuation characters to preserve as separate tokens (default: ",.")
     #[arg(short = 'p', long = "punctuation", default_value = ",.")]
     punctuation: String,
+
+    /// Run typst compile on grid.typ to create the blank worksheet and answer key PDFs
+    #[arg(long = "typst")]
+    run_typst: bool,
+
+    /// Typst template for the worksheets (with --typst; default: the bundled grid.typ)
+    #[arg(long = "template")]
+    template: Option<PathBuf>,
+
+    /// Print the words as row/column headers on the blank worksheet
+    #[arg(long = "labels")]
+    labels: bool,
+
+    #[command(flatten)]
+    context: ContextArgs,
 }
 
 fn main() {
     let args = Args::parse();
-    match run(&args) {
+    let result = match &args.command {
+        Some(Commands::Summarize(summarize_args)) => run_summarize(summarize_args),
+        Some(Commands::Grid(grid_args)) => run_grid(grid_args),
+        Some(Commands::Embed(embed_args)) => run_embed(embed_args),
+        Some(Commands::Adapt(adapt_args)) => run_adapt(adapt_args),
+        Some(Commands::Collapse(collapse_args)) => run_collapse(collapse_args),
+        Some(Commands::Diff(diff_args)) => run_diff(diff_args),
+        Some(Commands::Merge(merge_args)) => run_merge(merge_args),
+        Some(Commands::Build(build_args)) => run_build(build_args),
+        Some(Commands::Templates(templates_args)) => run_templates(templates_args),
+        None => run(&args),
+    };
+
+    match result {
         Ok(_) => {}
         Err(CliError::Processing(err)) => {
             if err.kind() == io::ErrorKind::InvalidData {
@@ -65,6 +670,10 @@ fn main() {
             eprintln!("{err}");
             std::process::exit(1);
         }
+        Err(CliError::Model(err)) => {
+            eprintln!("Error: {err}");
+            std::process::exit(1);
+        }
     }
 }
 
@@ -72,27 +681,696 @@ fn main() {
 enum CliError {
     Processing(io::Error),
     Typst(String),
+    /// Errors reading or writing model JSON files
+    Model(io::Error),
 }
 
 fn run(args: &Args) -> Result<(), CliError> {
+    let input = args
+        .input
+        .as_deref()
+        .expect("clap requires an input file unless a subcommand is given");
+    // Checked before counting, so a bad combination doesn't leave a trace or stats file behind
+    if args.run_typst && args.format != OutputFormat::Json {
+        return Err(CliError::Processing(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            "--typst typesets JSON booklets: use it without --format",
+        )));
+    }
     let punctuation: Vec<char> = args.punctuation.chars().collect();
-    let mut counter = NGramCounter::new(args.n, punctuation);
-    counter
-        .process_file(&args.input)
+    let classes = args.context.word_classes().map_err(CliError::Processing)?;
+
+    if let Some(positions) = &args.positions {
+        let mut counter =
+            NGramCounter::with_positions(positions, punctuation).map_err(CliError::Processing)?;
+        if args.trace.is_some() {
+            counter.enable_trace(args.trace_limit);
+        }
+        if let Some(classes) = classes {
+            counter.enable_context(classes);
+        }
+        counter.process_file(input).map_err(CliError::Processing)?;
+        let n = counter.context_positions().len() + 1;
+        return build_model(args, &mut counter, n, Path::to_path_buf);
+    }
+
+    let mut counters = MultiOrderCounter::new(args.n.min..=args.n.max, punctuation)
         .map_err(CliError::Processing)?;
+    for counter in counters.counters_mut() {
+        if args.trace.is_some() {
+            counter.enable_trace(args.trace_limit);
+        }
+        if let Some(classes) = &classes {
+            counter.enable_context(classes.clone());
+        }
+    }
+    counters.process_file(input).map_err(CliError::Processing)?;
+
+    // With a range of sizes, each model's files get the size as a suffix (model-2.json, ...)
+    let multiple = args.n.min != args.n.max;
+    for (n, mut counter) in (args.n.min..=args.n.max).zip(counters.into_counters()) {
+        let path = |path: &Path| {
+            if multiple {
+                order_path(path, n)
+            } else {
+                path.to_path_buf()
+            }
+        };
+        build_model(args, &mut counter, n, path)?;
+    }
+
+    Ok(())
+}
+
+/// The output file given with -o, or "model" if there was none, with `extension` added if
+/// the path has no extension
+fn output_path(output: Option<&Path>, extension: &str) -> PathBuf {
+    let output = output.unwrap_or(Path::new("model"));
+    if output.extension().is_some() {
+        output.to_path_buf()
+    } else {
+        output.with_extension(extension)
+    }
+}
 
-    let entries = counter.get_entries();
+/// Adds the n-gram size to a file name, e.g. `model.json` -> `model-3.json`
+fn order_path(path: &Path, n: usize) -> PathBuf {
+    let stem = path.file_stem().and_then(|s| s.to_str()).unwrap_or("model");
+    let file_name = match path.extension().and_then(|e| e.to_str()) {
+        Some(extension) => format!("{}-{}.{}", stem, n, extension),
+        None => format!("{}-{}", stem, n),
+    };
+    path.with_file_name(file_name)
+}
+
+/// Prunes, writes and reports on one counted model; `path` maps each output path from the
+/// arguments to the path for this model
+fn build_model(
+    args: &Args,
+    counter: &mut NGramCounter,
+    n: usize,
+    path: impl Fn(&Path) -> PathBuf,
+) -> Result<(), CliError> {
+    let layout = args.books.layout().map_err(CliError::Processing)?;
+    let prune_options = args.prune.options(layout);
+    if prune_options.is_active() {
+        counter
+            .prune(&prune_options)
+            .map_err(CliError::Processing)?;
+    }
+
+    if let Some(trace_path) = &args.trace {
+        let trace_path = path(trace_path);
+        write_trace(&trace_path, args.trace_format, counter.get_trace())
+            .map_err(CliError::Processing)?;
+        println!(
+            "Successfully wrote {} training steps to '{}'",
+            counter.get_trace().len(),
+            trace_path.display()
+        );
+    }
+
+    let mut entries = counter.get_entries();
     let stats = counter.get_stats().clone();
-    let metadata = counter.get_metadata().cloned();
-    let books = split_entries_into_books(&entries, args.num_books);
+    let mut metadata = counter.get_metadata().cloned();
 
-    let written = write_books(&books, &args.output, metadata.as_ref(), &stats, args.raw)
+    if let Some(context) = counter.get_context() {
+        if let Some(metadata) = metadata.as_mut() {
+            metadata.context = Some(context.clone());
+        }
+        if args.combine_context {
+            entries = combine_context(&entries, context);
+            if let Some(metadata) = metadata.as_mut() {
+                metadata.subtitle.push_str(" with context columns");
+            }
+        }
+    }
+
+    let output = path(&output_path(
+        args.output.as_deref(),
+        args.format.extension(),
+    ));
+    let format = match args.format {
+        OutputFormat::TsvMatrix | OutputFormat::CsvMatrix => {
+            return write_matrix_output(args, counter, &output);
+        }
+        OutputFormat::Json => BookletFormat::Json,
+        OutputFormat::Html => BookletFormat::Html(args.books.page_style()),
+        OutputFormat::Markdown => BookletFormat::Markdown,
+    };
+    let books = args.books.split(&entries).map_err(CliError::Processing)?;
+
+    let written = write_books(
+        &books,
+        &output,
+        metadata.as_ref(),
+        &stats,
+        args.raw,
+        &format,
+    )
+    .map_err(CliError::Processing)?;
+
+    if let Some(stats_path) = &args.stats_json {
+        let stats_path = path(stats_path);
+        save_stats_json(&stats_path, metadata.as_ref(), n, &stats).map_err(CliError::Processing)?;
+        println!(
+            "Successfully wrote statistics to '{}'",
+            stats_path.display()
+        );
+    }
+
+    print_summary(&stats, metadata.as_ref(), n, args.raw);
+
+    if args.run_typst {
+        run_typst(&written, &output, &args.books).map_err(CliError::Typst)?;
+    }
+
+    Ok(())
+}
+
+fn write_trace(path: &Path, format: TraceFormat, steps: &[TraceStep]) -> io::Result<()> {
+    let mut writer = io::BufWriter::new(std::fs::File::create(path)?);
+    match format {
+        TraceFormat::Jsonl => write_trace_jsonl(steps, &mut writer),
+        TraceFormat::Table => write_trace_table(steps, &mut writer),
+    }
+}
+
+fn write_matrix_output(args: &Args, counter: &NGramCounter, output: &Path) -> Result<(), CliError> {
+    let delimiter = match args.format {
+        OutputFormat::CsvMatrix => ',',
+        _ => '\t',
+    };
+    let options = MatrixOptions {
+        delimiter,
+        counts: args.matrix_counts,
+        order: args.matrix_order,
+        max_vocabulary: args.max_vocab,
+        zero_cells: args.zero_cells,
+    };
+
+    let mut writer =
+        io::BufWriter::new(std::fs::File::create(output).map_err(CliError::Processing)?);
+    write_matrix(
+        &counter.get_entries(),
+        &counter.get_vocabulary(),
+        &options,
+        &mut writer,
+    )
+    .map_err(CliError::Processing)?;
+
+    println!(
+        "Successfully wrote {} matrix to '{}'",
+        args.format.extension().to_uppercase(),
+        output.display()
+    );
+
+    Ok(())
+}
+
+fn run_summarize(args: &SummarizeArgs) -> Result<(), CliError> {
+    let mut models = Vec::new();
+    for path in &args.models {
+        match load_model(path) {
+            Ok(model) => models.push(model),
+            // Split books are written alongside a manifest and index, so `json/*.json` finds them
+            Err(_) if is_book_listing(path) => {
+                println!("Skipping book manifest/index '{}'", path.display())
+            }
+            Err(e) => return Err(CliError::Model(e)),
+        }
+    }
+
+    let summary = summarize(&models).map_err(CliError::Model)?;
+
+    let file = std::fs::File::create(&args.output).map_err(CliError::Model)?;
+    serde_json::to_writer_pretty(file, &summary).map_err(|e| CliError::Model(e.into()))?;
+
+    println!(
+        "Summarised {} models from {} files to '{}'",
+        summary.models.len(),
+        models.len(),
+        args.output.display()
+    );
+
+    Ok(())
+}
+
+/// Whether a file is the book manifest or index written for a split model
+fn is_book_listing(path: &Path) -> bool {
+    let Ok(json) = std::fs::read_to_string(path) else {
+        return false;
+    };
+    serde_json::from_str::<BookManifest>(&json).is_ok()
+        || serde_json::from_str::<BookIndex>(&json).is_ok()
+}
+
+fn run_embed(args: &EmbedArgs) -> Result<(), CliError> {
+    let model = load_model(&args.model).map_err(CliError::Model)?;
+    if args.values == EmbeddingValues::Raw && model.scaling != Scaling::Raw {
+        return Err(CliError::Model(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!(
+                "{} has d10-scaled counts: build it with --raw for raw embeddings, or use --values normalised or scaled",
+                args.model.display()
+            ),
+        )));
+    }
+
+    let mut embeddings = Embeddings::from_entries(&model.entries, args.values);
+    if let Some(context) = &model.metadata.context {
+        embeddings = embeddings.with_context(context);
+    }
+    let matrix = embeddings.distance_matrix(args.metric);
+
+    let mut writer =
+        io::BufWriter::new(std::fs::File::create(&args.output).map_err(CliError::Model)?);
+    match args.format {
+        DistanceFormat::Tsv => matrix.write_tsv(&mut writer).map_err(CliError::Model)?,
+        DistanceFormat::Json => serde_json::to_writer_pretty(&mut writer, &matrix)
+            .map_err(|e| CliError::Model(e.into()))?,
+    }
+    println!(
+        "Successfully wrote {}x{} distance matrix to '{}'",
+        matrix.words.len(),
+        matrix.words.len(),
+        args.output.display()
+    );
+
+    if let Some(word) = &args.nearest {
+        let neighbours = embeddings.nearest(word, args.neighbours, args.metric);
+        if neighbours.is_empty() {
+            return Err(CliError::Model(io::Error::new(
+                io::ErrorKind::NotFound,
+                format!("'{}' has no row in {}", word, args.model.display()),
+            )));
+        }
+        println!("\nNearest neighbours of '{}':", word);
+        for (neighbour, distance) in neighbours {
+            println!("  {:<20} {:.3}", neighbour, distance);
+        }
+    }
+
+    Ok(())
+}
+
+fn run_adapt(args: &AdaptArgs) -> Result<(), CliError> {
+    if !(args.scale.is_finite() && args.scale >= 0.0) {
+        return Err(CliError::Model(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!("--scale must be zero or more (got {})", args.scale),
+        )));
+    }
+
+    let base = load_model(&args.base).map_err(CliError::Model)?;
+    // Raw adapter counts added to scaled base counts would weight the adapter arbitrarily
+    if base.scaling != Scaling::Raw {
+        return Err(CliError::Model(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!(
+                "{} has d10-scaled counts; build the base model with --raw to adapt it",
+                args.base.display()
+            ),
+        )));
+    }
+    let recorded = base
+        .metadata
+        .tokenization
+        .as_ref()
+        .map(|t| t.punctuation.as_str());
+    let punctuation = match (&args.punctuation, recorded) {
+        (Some(given), Some(recorded)) if given != recorded => {
+            return Err(CliError::Model(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!(
+                    "{} was tokenized with punctuation {:?}, not {:?}",
+                    args.base.display(),
+                    recorded,
+                    given
+                ),
+            )));
+        }
+        (Some(given), _) => given.as_str(),
+        (None, recorded) => recorded.unwrap_or(",."),
+    };
+
+    // The adapter counts the same (possibly skip-gram) contexts as the base
+    let n = base.metadata.n;
+    let punctuation = punctuation.chars().collect();
+    let mut counter = if base.metadata.context_positions.is_empty() {
+        NGramCounter::new(n, punctuation)
+    } else {
+        let positions: Vec<usize> = base
+            .metadata
+            .context_positions
+            .iter()
+            .map(|p| p.unsigned_abs())
+            .collect();
+        NGramCounter::with_positions(&positions, punctuation)
+    }
+    .map_err(CliError::Processing)?;
+    counter
+        .process_file(&args.input)
         .map_err(CliError::Processing)?;
 
-    print_summary(&stats, metadata.as_ref(), args.n, args.raw);
+    let mut adapter = counter.get_entries();
+    if !args.unrestricted {
+        adapter = restrict_to_vocabulary(&adapter, &base.vocabulary());
+    }
+    let merged = apply_adapter(&base.entries, &adapter, args.scale);
+
+    let model_type = llms_unplugged::model_type_str(n);
+    let adapter_metadata = counter.get_metadata().cloned().map(|mut metadata| {
+        metadata.subtitle = format!("A {} adapter for {}", model_type, base.metadata.title);
+        metadata
+    });
+    let adapter_output = args.adapter_output.clone().unwrap_or_else(|| {
+        let stem = args
+            .output
+            .file_stem()
+            .and_then(|s| s.to_str())
+            .unwrap_or("adapted");
+        args.output.with_file_name(format!("{}_adapter.json", stem))
+    });
+    save_to_json(
+        &adapter,
+        &adapter_output,
+        adapter_metadata.as_ref(),
+        Some(&stats_from_entries(&adapter)),
+        true,
+    )
+    .map_err(CliError::Processing)?;
+    println!(
+        "Successfully wrote adapter ({} rows) to '{}'",
+        adapter.len(),
+        adapter_output.display()
+    );
+
+    let mut metadata = base.metadata.clone();
+    metadata.subtitle = format!(
+        "{} adapted to {}",
+        llms_unplugged::model_subtitle(n, &base.metadata.context_positions),
+        adapter_metadata
+            .as_ref()
+            .map(|m| m.title.as_str())
+            .unwrap_or("new text")
+    );
+    let stats = stats_from_entries(&merged);
+    let books = args.books.split(&merged).map_err(CliError::Processing)?;
+    let written = write_books(
+        &books,
+        &args.output,
+        Some(&metadata),
+        &stats,
+        args.raw,
+        &BookletFormat::Json,
+    )
+    .map_err(CliError::Processing)?;
+
+    if args.run_typst {
+        run_typst(&written, &args.output, &args.books).map_err(CliError::Typst)?;
+    }
+
+    Ok(())
+}
+
+fn run_merge(args: &MergeArgs) -> Result<(), CliError> {
+    let weights = match args.weights.len() {
+        0 => vec![1.0; args.models.len()],
+        len if len == args.models.len() => args.weights.clone(),
+        len => {
+            return Err(CliError::Model(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!(
+                    "Got {} weights for {} models; give one weight per model",
+                    len,
+                    args.models.len()
+                ),
+            )));
+        }
+    };
+
+    let models = args
+        .models
+        .iter()
+        .map(load_model)
+        .collect::<io::Result<Vec<_>>>()
+        .map_err(CliError::Model)?;
+
+    let weighted: Vec<_> = models.iter().zip(weights).collect();
+    let (merged, mut metadata) = merge_models(&weighted).map_err(CliError::Model)?;
+    if let Some(title) = &args.title {
+        metadata.title = title.clone();
+    }
+    println!("Merged {} models into {} rows", models.len(), merged.len());
+
+    let stats = stats_from_entries(&merged);
+    let books = args.books.split(&merged).map_err(CliError::Processing)?;
+    let written = write_books(
+        &books,
+        &args.output,
+        Some(&metadata),
+        &stats,
+        args.raw,
+        &BookletFormat::Json,
+    )
+    .map_err(CliError::Processing)?;
+
+    if args.run_typst {
+        run_typst(&written, &args.output, &args.books).map_err(CliError::Typst)?;
+    }
+
+    Ok(())
+}
+
+fn run_collapse(args: &CollapseArgs) -> Result<(), CliError> {
+    let seed = args.seed.unwrap_or_else(rand::random);
+    let mut rng = StdRng::seed_from_u64(seed);
+    let punctuation: Vec<char> = args.punctuation.chars().collect();
+
+    let original = match (&args.joker, &args.input) {
+        (Some(words), _) => {
+            if args.n != 2 {
+                return Err(CliError::Model(io::Error::new(
+                    io::ErrorKind::InvalidInput,
+                    "Joker mode starts from a bigram grid; use -n 2",
+                )));
+            }
+            let words: Vec<String> = words
+                .split(|c: char| c.is_whitespace() || c == ',')
+                .filter(|w| !w.is_empty())
+                .map(str::to_lowercase)
+                .collect();
+            if words.is_empty() {
+                return Err(CliError::Model(io::Error::new(
+                    io::ErrorKind::InvalidInput,
+                    "--joker needs at least one word",
+                )));
+            }
+            joker_model(&words, &mut rng)
+        }
+        (None, Some(path)) => {
+            let mut counter =
+                NGramCounter::new(args.n, punctuation.clone()).map_err(CliError::Processing)?;
+            counter.process_file(path).map_err(CliError::Processing)?;
+            counter.get_entries()
+        }
+        (None, None) => unreachable!("clap requires either an input file or --joker"),
+    };
+
+    let options = CollapseOptions {
+        n: args.n,
+        generations: args.generations,
+        tokens_per_generation: args.tokens,
+        punctuation,
+    };
+    let results = simulate_collapse(&original, &options, &mut rng).map_err(CliError::Processing)?;
+
+    let report = serde_json::json!({
+        "seed": seed,
+        "n": args.n,
+        "joker": args.joker.is_some(),
+        "tokens_per_generation": args.tokens,
+        "generations": results,
+    });
+    let file = std::fs::File::create(&args.output).map_err(CliError::Model)?;
+    serde_json::to_writer_pretty(file, &report).map_err(|e| CliError::Model(e.into()))?;
+
+    println!(
+        "{:>10}  {:>10}  {:>8}  {:>7}  {:>12}  {:>7}",
+        "generation", "vocabulary", "prefixes", "n-grams", "entropy/bits", "KL/bits"
+    );
+    for result in &results {
+        println!(
+            "{:>10}  {:>10}  {:>8}  {:>7}  {:>12.3}  {:>7.3}",
+            result.generation,
+            result.vocabulary_size,
+            result.unique_prefixes,
+            result.unique_ngrams,
+            result.entropy,
+            result.kl_divergence
+        );
+    }
+    println!(
+        "\nSuccessfully wrote {} generations (seed {}) to '{}'",
+        results.len(),
+        seed,
+        args.output.display()
+    );
+
+    Ok(())
+}
+
+fn run_diff(args: &DiffArgs) -> Result<(), CliError> {
+    let is_model = |path: &Path| path.extension().is_some_and(|ext| ext == "json");
+    let model_a = if is_model(&args.a) {
+        Some(load_model(&args.a).map_err(CliError::Model)?)
+    } else {
+        None
+    };
+    let model_b = if is_model(&args.b) {
+        Some(load_model(&args.b).map_err(CliError::Model)?)
+    } else {
+        None
+    };
+
+    let invalid =
+        |message: String| CliError::Model(io::Error::new(io::ErrorKind::InvalidInput, message));
+    let models: Vec<&Model> = model_a.iter().chain(&model_b).collect();
+    for model in &models {
+        // The divergences compare distributions over all n-grams, which d10 scaling distorts
+        if model.scaling != Scaling::Raw {
+            return Err(invalid(format!(
+                "{} has d10-scaled counts: build it with --raw to compare it",
+                model.path.display()
+            )));
+        }
+        if let Some(n) = args.n
+            && n != model.metadata.n
+        {
+            return Err(invalid(format!(
+                "--n {} doesn't match {}, which has n = {}",
+                n,
+                model.path.display(),
+                model.metadata.n
+            )));
+        }
+    }
+    if let [a, b] = models.as_slice() {
+        if a.metadata.n != b.metadata.n {
+            return Err(invalid(format!(
+                "{} has n = {}, but {} has n = {}",
+                a.path.display(),
+                a.metadata.n,
+                b.path.display(),
+                b.metadata.n
+            )));
+        }
+        if a.metadata.context_positions != b.metadata.context_positions {
+            return Err(invalid(format!(
+                "{} uses context positions {:?}, but {} uses {:?}",
+                a.path.display(),
+                a.metadata.context_positions,
+                b.path.display(),
+                b.metadata.context_positions
+            )));
+        }
+    }
+
+    let n = args.n.or(models.first().map(|m| m.metadata.n)).unwrap_or(2);
+    // Text files are counted at the same positions as a skip-gram model they're compared with
+    let positions: Option<Vec<usize>> = models
+        .first()
+        .filter(|model| !model.metadata.context_positions.is_empty())
+        .map(|model| {
+            model
+                .metadata
+                .context_positions
+                .iter()
+                .map(|p| p.unsigned_abs())
+                .collect()
+        });
+    let entries = |model: Option<Model>, path: &Path| -> Result<Vec<WordFollowEntry>, CliError> {
+        match model {
+            Some(model) => Ok(model.entries),
+            None => {
+                let punctuation = args.punctuation.chars().collect();
+                let mut counter = match &positions {
+                    Some(positions) => NGramCounter::with_positions(positions, punctuation),
+                    None => NGramCounter::new(n, punctuation),
+                }
+                .map_err(CliError::Processing)?;
+                counter.process_file(path).map_err(CliError::Processing)?;
+                Ok(counter.get_entries())
+            }
+        }
+    };
+    let a = entries(model_a, &args.a)?;
+    let b = entries(model_b, &args.b)?;
+
+    let diff = diff_models(&a, &b, args.shifts);
+
+    println!("A: {}", args.a.display());
+    println!("B: {}\n", args.b.display());
+    diff.write_summary(&mut io::stdout().lock(), args.top)
+        .map_err(CliError::Model)?;
+
+    if let Some(output) = &args.output {
+        let file = std::fs::File::create(output).map_err(CliError::Model)?;
+        serde_json::to_writer_pretty(file, &diff).map_err(|e| CliError::Model(e.into()))?;
+        println!("\nSuccessfully wrote comparison to '{}'", output.display());
+    }
+
+    Ok(())
+}
+
+fn run_grid(args: &GridArgs) -> Result<(), CliError> {
+    let text = match (&args.text, &args.input) {
+        (Some(text), _) => text.clone(),
+        (None, Some(path)) => std::fs::read_to_string(path).map_err(CliError::Processing)?,
+        (None, None) => unreachable!("clap requires either an input file or --text"),
+    };
+
+    let mut grid = training_grid(&text, args.punctuation.chars().collect());
+    if let Some(classes) = args.context.word_classes().map_err(CliError::Processing)? {
+        grid = grid.with_context(classes);
+    }
+    let file = std::fs::File::create(&args.output).map_err(CliError::Model)?;
+    serde_json::to_writer_pretty(file, &grid).map_err(|e| CliError::Model(e.into()))?;
+
+    println!(
+        "Successfully wrote {}x{} grid ({} tokens) to '{}'",
+        grid.words.len(),
+        grid.words.len(),
+        grid.tokens.len(),
+        args.output.display()
+    );
 
     if args.run_typst {
-        run_typst(&written, args.num_books).map_err(CliError::Typst)?;
+        let templates = TemplateDir::new(&args.output).map_err(CliError::Typst)?;
+        let template = args
+            .template
+            .clone()
+            .unwrap_or_else(|| templates.path("grid.typ"));
+        for mode in ["blank", "key"] {
+            let stem = args
+                .output
+                .file_stem()
+                .and_then(|s| s.to_str())
+                .unwrap_or("grid");
+            let pdf_file = args.output.with_file_name(format!("{}-{}.pdf", stem, mode));
+            compile_typst(
+                &template,
+                &pdf_file,
+                &[
+                    ("mode", mode.to_string()),
+                    ("labels", args.labels.to_string()),
+                ],
+                &[("grid_path", &args.output)],
+            )
+            .map_err(CliError::Typst)?;
+            println!("Successfully created PDF: {}", pdf_file.display());
+        }
     }
 
     Ok(())
@@ -104,21 +1382,18 @@ fn write_books(
     metadata: Option<&Metadata>,
     stats: &ProcessingStats,
     raw: bool,
+    format: &BookletFormat,
 ) -> io::Result<Vec<(String, PathBuf)>> {
-    let output_stem = output
-        .file_stem()
-        .unwrap_or_default()
-        .to_str()
-        .unwrap_or("model");
-    let output_dir = output.parent().unwrap_or(Path::new("."));
-
     let mut written = Vec::new();
 
     for (index, (range, entries)) in books.iter().enumerate() {
         let output_file = if books.len() == 1 {
             output.to_path_buf()
         } else {
-            output_dir.join(format!("{}_book_{}.json", output_stem, index + 1))
+            book_file(
+                output,
+                &format!("book_{}.{}", index + 1, format.extension()),
+            )
         };
 
         let book_metadata = if books.len() > 1 {
@@ -127,7 +1402,8 @@ fn write_books(
             metadata.cloned()
         };
 
-        save_to_json(
+        save_booklet(
+            format,
             entries,
             &output_file,
             book_metadata.as_ref(),
@@ -152,6 +1428,31 @@ fn write_books(
         written.push((range.clone(), output_file));
     }
 
+    if books.len() > 1 {
+        let files: Vec<String> = written
+            .iter()
+            .map(|(_, path)| {
+                path.file_name()
+                    .unwrap_or_default()
+                    .to_string_lossy()
+                    .into_owned()
+            })
+            .collect();
+        let manifest_path = book_file(output, "manifest.json");
+        BookManifest::new(books, &files, metadata).save(&manifest_path)?;
+        println!(
+            "Successfully wrote book manifest to '{}'",
+            manifest_path.display()
+        );
+
+        let index_path = book_file(output, "index.json");
+        book_index(books, metadata).save(&index_path)?;
+        println!(
+            "Successfully wrote book index to '{}'",
+            index_path.display()
+        );
+    }
+
     if raw {
         println!("Output raw counts without scaling");
     } else {
@@ -161,12 +1462,23 @@ fn write_books(
     Ok(written)
 }
 
+/// A file written next to the books split from `output`, e.g. `model_manifest.json`
+fn book_file(output: &Path, suffix: &str) -> PathBuf {
+    let output_stem = output
+        .file_stem()
+        .unwrap_or_default()
+        .to_str()
+        .unwrap_or("model");
+    let output_dir = output.parent().unwrap_or(Path::new("."));
+    output_dir.join(format!("{}_{}", output_stem, suffix))
+}
+
 fn multi_book_metadata(base: &Metadata, range: &str, index: usize, total_books: usize) -> Metadata {
     let mut clone = base.clone();
     let formatted_range = range.replace('-', "–");
     clone.subtitle = format!(
-        "A {} language model: {} (Book {} of {})",
-        llms_unplugged::model_type_str(base.n),
+        "{}: {} (Book {} of {})",
+        llms_unplugged::model_subtitle(base.n, &base.context_positions),
         formatted_range,
         index + 1,
         total_books
@@ -174,55 +1486,447 @@ fn multi_book_metadata(base: &Metadata, range: &str, index: usize, total_books:
     clone
 }
 
-fn run_typst(written: &[(String, PathBuf)], num_books: usize) -> Result<(), String> {
+/// Builds each target of a build manifest with the same pipeline as the top-level command,
+/// recording what was built so that unchanged targets are skipped next time
+fn run_build(args: &BuildArgs) -> Result<(), CliError> {
+    let manifest = BuildManifest::load(&args.manifest).map_err(CliError::Processing)?;
+    for dir in [
+        manifest.json_dir(),
+        manifest.stats_dir(),
+        manifest.pdf_dir(),
+    ] {
+        std::fs::create_dir_all(dir).map_err(CliError::Processing)?;
+    }
+    let mut state = BuildState::load(manifest.state_path()).map_err(CliError::Processing)?;
+
+    let mut built = 0;
+    for target in &manifest.targets {
+        let name = target.name();
+        let fingerprint = manifest.fingerprint(target).map_err(CliError::Processing)?;
+        if !args.force && state.is_up_to_date(&name, &fingerprint) {
+            println!("{} is up to date", name);
+            continue;
+        }
+
+        // Remove the previous outputs, so a changed number of books leaves no stale files
+        if let Some(record) = state.targets.remove(&name) {
+            for path in record.outputs {
+                remove_stale(&path)?;
+            }
+        }
+        remove_stale(&manifest.json_dir().join(format!("{}_manifest.json", name)))?;
+        state
+            .save(manifest.state_path())
+            .map_err(CliError::Processing)?;
+
+        println!("\nBuilding {}...", name);
+        run(&build_target_args(&manifest, target, &name, args))?;
+
+        let outputs = build_outputs(&manifest, target, &name);
+        state.targets.insert(
+            name,
+            BuildRecord {
+                fingerprint,
+                outputs,
+            },
+        );
+        state
+            .save(manifest.state_path())
+            .map_err(CliError::Processing)?;
+        built += 1;
+    }
+
+    println!(
+        "\nBuilt {} of {} targets ({} up to date)",
+        built,
+        manifest.targets.len(),
+        manifest.targets.len() - built
+    );
+    Ok(())
+}
+
+/// Removes an output of an earlier build, if it is still there
+fn remove_stale(path: &Path) -> Result<(), CliError> {
+    match std::fs::remove_file(path) {
+        Err(e) if e.kind() != io::ErrorKind::NotFound => Err(CliError::Processing(io::Error::new(
+            e.kind(),
+            format!("Error removing {}: {}", path.display(), e),
+        ))),
+        _ => Ok(()),
+    }
+}
+
+/// The command-line options equivalent to a build target (the manifest leaves out the same
+/// defaults as the command line)
+fn build_target_args(
+    manifest: &BuildManifest,
+    target: &BuildTarget,
+    name: &str,
+    args: &BuildArgs,
+) -> Args {
+    Args {
+        command: None,
+        input: Some(manifest.corpus_path(target)),
+        output: Some(manifest.json_dir().join(format!("{}.json", name))),
+        n: Orders {
+            min: target.n,
+            max: target.n,
+        },
+        positions: None,
+        books: BookArgs {
+            num_books: target.books,
+            split_at: target.split_at.unwrap_or_default(),
+            balance_pages: false,
+            pages_per_book: target.pages_per_book,
+            paper: target.paper.clone().unwrap_or_else(|| "a4".to_string()),
+            font_size: target.font_size.unwrap_or(8.0),
+            columns: target.columns.unwrap_or(4),
+            template: manifest.template.clone().filter(|_| target.pdf),
+            jobs: args.jobs.filter(|_| target.pdf),
+            pdf_dir: target.pdf.then(|| manifest.pdf_dir()),
+        },
+        run_typst: target.pdf,
+        raw: target.dice == Dice::Raw,
+        punctuation: target
+            .punctuation
+            .clone()
+            .unwrap_or_else(|| ",.".to_string()),
+        stats_json: Some(manifest.stats_dir().join(format!("{}.json", name))),
+        format: OutputFormat::Json,
+        matrix_counts: MatrixCounts::Cumulative,
+        matrix_order: MatrixOrder::Alphabetical,
+        max_vocab: None,
+        zero_cells: false,
+        trace: None,
+        trace_format: TraceFormat::Jsonl,
+        trace_limit: None,
+        prune: PruneArgs {
+            prune_vocab: target.prune_vocab,
+            min_count: target.min_count,
+            max_followers: target.max_followers,
+            max_pages: target.max_pages,
+        },
+        context: ContextArgs {
+            context_columns: false,
+            word_classes: None,
+        },
+        combine_context: false,
+    }
+}
+
+/// The files written for a build target: its model JSON (one per book, plus the book manifest
+/// and index if split), statistics and PDFs
+fn build_outputs(manifest: &BuildManifest, target: &BuildTarget, name: &str) -> Vec<PathBuf> {
+    let json_dir = manifest.json_dir();
+    let book_manifest = json_dir.join(format!("{}_manifest.json", name));
+    let split = std::fs::File::open(&book_manifest)
+        .ok()
+        .and_then(|file| serde_json::from_reader::<_, BookManifest>(file).ok());
+    let mut models: Vec<PathBuf> = match &split {
+        Some(books) => books
+            .books
+            .iter()
+            .map(|book| json_dir.join(&book.file))
+            .chain([json_dir.join(format!("{}_index.json", name))])
+            .collect(),
+        None => vec![json_dir.join(format!("{}.json", name))],
+    };
+
+    let mut outputs = vec![manifest.stats_dir().join(format!("{}.json", name))];
+    if target.pdf {
+        let pdf_dir = manifest.pdf_dir();
+        outputs.extend(models.iter().map(|model| pdf_path(model, Some(&pdf_dir))));
+    }
+    if split.is_some() {
+        outputs.push(book_manifest);
+    }
+    outputs.append(&mut models);
+    outputs
+}
+
+/// Where the PDF for a model JSON file goes: in `pdf_dir` if given, otherwise next to it
+fn pdf_path(json_file: &Path, pdf_dir: Option<&Path>) -> PathBuf {
+    let pdf_file = json_file.with_extension("pdf");
+    match (pdf_dir, pdf_file.file_name()) {
+        (Some(dir), Some(file_name)) => dir.join(file_name),
+        _ => pdf_file,
+    }
+}
+
+/// Compiles each written book with the book template, and the book index with `index.typ` if
+/// the model was split (`output` is the path the books were split from)
+fn run_typst(written: &[(String, PathBuf)], output: &Path, books: &BookArgs) -> Result<(), String> {
     println!("\nRunning typst compile...");
+    let num_books = written.len();
+    let templates = TemplateDir::new(output)?;
+    let book_template = books
+        .template
+        .clone()
+        .unwrap_or_else(|| templates.path("book.typ"));
 
-    for (index, (range, json_file)) in written.iter().enumerate() {
-        let output_dir = json_file.parent().unwrap_or(Path::new("."));
-        let pdf_file = json_file.with_extension("pdf");
+    let mut jobs: Vec<TypstJob> = written
+        .iter()
+        .enumerate()
+        .map(|(index, (range, json_file))| {
+            let subtitle = if num_books > 1 {
+                format!("{} (book {} of {})", range, index + 1, num_books)
+            } else {
+                load_model(json_file)
+                    .map(|model| model.metadata.subtitle)
+                    .unwrap_or_default()
+            };
+            TypstJob {
+                template: book_template.clone(),
+                pdf_file: pdf_path(json_file, books.pdf_dir.as_deref()),
+                inputs: vec![
+                    ("subtitle", subtitle),
+                    ("paper_size", books.paper.to_lowercase()),
+                    ("font_size", format!("{}pt", books.font_size)),
+                    ("columns", books.columns.to_string()),
+                ],
+                files: vec![("json_path", json_file.clone())],
+            }
+        })
+        .collect();
 
-        let subtitle = if num_books > 1 {
-            format!("{} (book {} of {})", range, index + 1, num_books)
-        } else {
-            String::new()
+    if num_books > 1 {
+        let index_path = book_file(output, "index.json");
+        // A customised index.typ next to a customised book template goes with it
+        let mut index_template = book_template.with_file_name("index.typ");
+        if !index_template.exists() {
+            index_template = templates.path("index.typ");
+        }
+        jobs.push(TypstJob {
+            template: index_template,
+            pdf_file: pdf_path(&index_path, books.pdf_dir.as_deref()),
+            inputs: vec![("paper_size", books.paper.to_lowercase())],
+            files: vec![("index_path", index_path)],
+        });
+    }
+
+    let max_jobs = books
+        .jobs
+        .or_else(|| std::thread::available_parallelism().ok())
+        .map_or(1, NonZeroUsize::get);
+    run_typst_jobs(&jobs, max_jobs)
+}
+
+/// The bundled templates, written to a temporary directory for typst (and removed afterwards)
+struct TemplateDir {
+    dir: tempfile::TempDir,
+}
+
+impl TemplateDir {
+    /// Writes the templates to a hidden directory next to `output`, so that typst's project
+    /// root (see [`compile_typst`]) can be the output directory rather than somewhere above
+    /// both it and the system temporary directory
+    fn new(output: &Path) -> Result<Self, String> {
+        let parent = match output.parent() {
+            Some(dir) if !dir.as_os_str().is_empty() => dir,
+            _ => Path::new("."),
         };
+        let dir = tempfile::Builder::new()
+            .prefix(".llms_unplugged-templates-")
+            .tempdir_in(parent)
+            .map_err(|e| {
+                format!(
+                    "Error creating a directory for the templates in {}: {}",
+                    parent.display(),
+                    e
+                )
+            })?;
+        export_templates(dir.path(), false)
+            .map_err(|e| format!("Error writing templates to {}: {}", dir.path().display(), e))?;
+        Ok(TemplateDir { dir })
+    }
 
-        let model_json_path = output_dir.join("model.json");
-        if json_file != &model_json_path {
-            std::fs::copy(json_file, &model_json_path).map_err(|e| {
-                format!("Error copying {} to model.json: {}", json_file.display(), e)
+    fn path(&self, name: &str) -> PathBuf {
+        self.dir.path().join(name)
+    }
+}
+
+fn run_templates(args: &TemplatesArgs) -> Result<(), CliError> {
+    match &args.command {
+        TemplatesCommand::Export(export) => {
+            let written = export_templates(&export.dir, export.force).map_err(|e| {
+                if e.kind() == io::ErrorKind::AlreadyExists {
+                    CliError::Processing(io::Error::new(
+                        e.kind(),
+                        format!("{} (use --force to replace it)", e),
+                    ))
+                } else {
+                    CliError::Processing(e)
+                }
             })?;
+            for path in &written {
+                println!("Wrote {}", path.display());
+            }
+            println!(
+                "\nUse a customised copy with e.g. --template {}",
+                export.dir.join("book.typ").display()
+            );
+            Ok(())
         }
+    }
+}
+
+/// One `typst compile` run (see [`compile_typst`])
+struct TypstJob {
+    template: PathBuf,
+    pdf_file: PathBuf,
+    inputs: Vec<(&'static str, String)>,
+    files: Vec<(&'static str, PathBuf)>,
+}
 
-        let mut typst_cmd = Command::new("typst");
-        typst_cmd.arg("compile");
+/// Runs the jobs with at most `max_jobs` at once. Every job is run even if some fail, and the
+/// failures are reported together.
+fn run_typst_jobs(jobs: &[TypstJob], max_jobs: usize) -> Result<(), String> {
+    let next = AtomicUsize::new(0);
+    let results: Mutex<Vec<Option<Result<(), String>>>> =
+        Mutex::new((0..jobs.len()).map(|_| None).collect());
 
-        if !subtitle.is_empty() {
-            typst_cmd.arg("--input");
-            typst_cmd.arg(format!("subtitle={}", subtitle));
+    std::thread::scope(|scope| {
+        for _ in 0..max_jobs.clamp(1, jobs.len().max(1)) {
+            scope.spawn(|| {
+                loop {
+                    let index = next.fetch_add(1, Ordering::Relaxed);
+                    let Some(job) = jobs.get(index) else {
+                        break;
+                    };
+                    let files: Vec<(&str, &Path)> = job
+                        .files
+                        .iter()
+                        .map(|(key, path)| (*key, path.as_path()))
+                        .collect();
+                    let result = compile_typst(&job.template, &job.pdf_file, &job.inputs, &files);
+                    if result.is_ok() {
+                        println!("Successfully created PDF: {}", job.pdf_file.display());
+                    }
+                    results.lock().expect("no thread panics holding the lock")[index] =
+                        Some(result);
+                }
+            });
         }
+    });
 
-        typst_cmd.arg("book.typ");
-        typst_cmd.arg(&pdf_file);
-
-        let output = typst_cmd
-            .output()
-            .map_err(|e| format!("Failed to run typst for {}: {}", json_file.display(), e))?;
+    let errors: Vec<String> = results
+        .into_inner()
+        .expect("no thread panics holding the lock")
+        .into_iter()
+        .filter_map(|result| result.and_then(Result::err))
+        .collect();
+    if errors.is_empty() {
+        Ok(())
+    } else {
+        Err(format!(
+            "{} of {} typst runs failed:\n{}",
+            errors.len(),
+            jobs.len(),
+            errors.join("\n")
+        ))
+    }
+}
 
-        if !output.status.success() {
-            let stderr = String::from_utf8_lossy(&output.stderr);
-            return Err(format!(
-                "Typst compile failed for {}: {}",
-                pdf_file.display(),
-                stderr
-            ));
+/// Compiles a template to `pdf_file`, passing each key/value pair as a `sys.inputs` entry: with
+/// the typst library if built with the `embed-typst` feature, otherwise with `typst compile`.
+///
+/// Each of `files` is passed as a path typst can read: typst only reads files inside its
+/// project root, so the root is set to the closest directory containing the template and
+/// every file. Files with nothing but the filesystem root in common with the template are
+/// refused rather than opening the whole filesystem to typst.
+fn compile_typst(
+    template: &Path,
+    pdf_file: &Path,
+    inputs: &[(&str, String)],
+    files: &[(&str, &Path)],
+) -> Result<(), String> {
+    let canonical = |path: &Path| {
+        path.canonicalize()
+            .map_err(|e| format!("Error finding {}: {}", path.display(), e))
+    };
+    let template_dir = match template.parent() {
+        Some(dir) if !dir.as_os_str().is_empty() => dir,
+        _ => Path::new("."),
+    };
+    let mut root = canonical(template_dir)?;
+    let mut file_paths = Vec::new();
+    for (key, path) in files {
+        let path = canonical(path)?;
+        while !path.starts_with(&root) {
+            root = match root.parent() {
+                Some(parent) if parent.parent().is_some() => parent.to_path_buf(),
+                _ => {
+                    return Err(format!(
+                        "{} and the template {} have no directory in common, so typst can't \
+                         read both (put the template next to the file)",
+                        path.display(),
+                        template.display()
+                    ));
+                }
+            };
         }
+        file_paths.push((key, path));
+    }
+
+    let mut inputs = inputs.to_vec();
+    for (key, path) in file_paths {
+        // Paths starting with "/" are relative to the project root
+        let relative: Vec<String> = path
+            .strip_prefix(&root)
+            .unwrap_or(&path)
+            .components()
+            .map(|c| c.as_os_str().to_string_lossy().into_owned())
+            .collect();
+        inputs.push((key, format!("/{}", relative.join("/"))));
+    }
 
-        if json_file != &model_json_path && model_json_path.exists() {
-            let _ = std::fs::remove_file(&model_json_path);
+    #[cfg(feature = "embed-typst")]
+    {
+        let typeset =
+            llms_unplugged::typeset_pdf(template, &root, &inputs).map_err(|e| e.to_string())?;
+        for warning in &typeset.warnings {
+            eprintln!("{}", warning);
         }
+        std::fs::write(pdf_file, &typeset.pdf)
+            .map_err(|e| format!("Error writing {}: {}", pdf_file.display(), e))
+    }
+
+    #[cfg(not(feature = "embed-typst"))]
+    run_typst_command(template, pdf_file, &root, &inputs)
+}
+
+/// Runs the `typst` executable on PATH
+#[cfg(not(feature = "embed-typst"))]
+fn run_typst_command(
+    template: &Path,
+    pdf_file: &Path,
+    root: &Path,
+    inputs: &[(&str, String)],
+) -> Result<(), String> {
+    let mut typst_cmd = std::process::Command::new("typst");
+    typst_cmd.arg("compile");
+    typst_cmd.arg("--root");
+    typst_cmd.arg(root);
 
-        println!("Successfully created PDF: {}", pdf_file.display());
+    for (key, value) in inputs {
+        typst_cmd.arg("--input");
+        typst_cmd.arg(format!("{}={}", key, value));
+    }
+
+    typst_cmd.arg(template);
+    typst_cmd.arg(pdf_file);
+
+    let output = typst_cmd
+        .output()
+        .map_err(|e| format!("Failed to run typst for {}: {}", pdf_file.display(), e))?;
+
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        return Err(format!(
+            "Typst compile failed for {}: {}",
+            pdf_file.display(),
+            stderr
+        ));
     }
 
     Ok(())
@@ -235,6 +1939,14 @@ fn print_summary(stats: &ProcessingStats, metadata: Option<&Metadata>, n: usize,
         println!("Title: {}", meta.title);
         println!("Author: {}", meta.author);
         println!("URL: {}", meta.url);
+        if !meta.context_positions.is_empty() {
+            let positions: Vec<String> = meta
+                .context_positions
+                .iter()
+                .map(|p| p.to_string())
+                .collect();
+            println!("Context positions: {}", positions.join(", "));
+        }
     }
 
     println!("\nSummary Statistics:");
@@ -262,6 +1974,34 @@ fn print_summary(stats: &ProcessingStats, metadata: Option<&Metadata>, n: usize,
         );
     }
 
+    if let Some(corpus) = &stats.corpus {
+        println!("Vocabulary size: {}", corpus.vocabulary_size);
+        println!("Type/token ratio: {:.3}", corpus.type_token_ratio);
+        println!("Hapax legomena: {}", corpus.hapax_legomena);
+        if let Some(slope) = corpus.zipf_slope {
+            println!("Zipf slope: {:.3}", slope);
+        }
+        println!(
+            "Followers per prefix: {:.2} mean, {} max",
+            corpus.branching.mean, corpus.branching.max
+        );
+        println!(
+            "Follower entropy: {:.2} bits mean, {:.2} bits max",
+            corpus.entropy.mean, corpus.entropy.max
+        );
+    }
+
+    if let Some(pruning) = &stats.pruning {
+        println!(
+            "Pruned from {} prefixes: {:.1}% of {}-gram occurrences removed, {:.1}% merged into <unk>",
+            pruning.unique_prefixes_before,
+            pruning.removed_mass * 100.0,
+            n,
+            pruning.unknown_mass * 100.0
+        );
+        println!("Estimated booklet pages: {}", pruning.estimated_pages);
+    }
+
     if raw {
         println!("\nRaw counts emitted (no dice scaling).");
     } else {
@@ -283,6 +2023,7 @@ mod tests {
             subtitle: "A bigram language model".to_string(),
             version: "test".to_string(),
             stats: None,
+            ..Default::default()
         }
     }
 
@@ -293,6 +2034,7 @@ mod tests {
             total_ngram_occurrences: 0,
             most_common_ngram: None,
             most_popular_prefix: None,
+            ..Default::default()
         }
     }
 
@@ -327,7 +2069,15 @@ mod tests {
         ];
 
         let meta = stub_metadata();
-        let written = write_books(&books, &output_path, Some(&meta), &stub_stats(), true).unwrap();
+        let written = write_books(
+            &books,
+            &output_path,
+            Some(&meta),
+            &stub_stats(),
+            true,
+            &BookletFormat::Json,
+        )
+        .unwrap();
 
         assert_eq!(written.len(), 2);
         assert!(written[0].1.exists());
@@ -336,5 +2086,62 @@ mod tests {
             written[0].1.to_string_lossy().contains("_book_1"),
             "Multi-book outputs should get numbered filenames"
         );
+
+        let manifest: BookManifest = serde_json::from_reader(
+            std::fs::File::open(temp_dir.path().join("model_manifest.json")).unwrap(),
+        )
+        .unwrap();
+        assert_eq!(manifest.books[1].file, "model_book_2.json");
+        assert_eq!(manifest.books[1].range, "D-F");
+        assert_eq!(manifest.books[1].first_prefix, "d");
+
+        let index: llms_unplugged::BookIndex = serde_json::from_reader(
+            std::fs::File::open(temp_dir.path().join("model_index.json")).unwrap(),
+        )
+        .unwrap();
+        assert_eq!(index.words["d"], vec![2]);
+    }
+
+    #[test]
+    fn parses_single_sizes_and_ranges() {
+        assert_eq!(parse_orders("3"), Ok(Orders { min: 3, max: 3 }));
+        assert_eq!(parse_orders("2..4"), Ok(Orders { min: 2, max: 4 }));
+        assert_eq!(parse_orders("2..=4"), Ok(Orders { min: 2, max: 4 }));
+        assert!(parse_orders("4..2").is_err());
+        assert!(parse_orders("two").is_err());
+
+        assert_eq!(parse_position("-2"), Ok(2));
+        assert_eq!(parse_position(" -1 "), Ok(1));
+        assert!(parse_position("0").is_err());
+        assert!(parse_position("2").is_err());
+        assert!(parse_position("+2").is_err());
+        assert!(parse_position("back").is_err());
+
+        assert_eq!(
+            order_path(Path::new("out/model.json"), 3),
+            PathBuf::from("out/model-3.json")
+        );
+    }
+
+    #[test]
+    fn adds_an_extension_only_if_missing() {
+        assert_eq!(output_path(None, "json"), PathBuf::from("model.json"));
+        assert_eq!(output_path(None, "md"), PathBuf::from("model.md"));
+        assert_eq!(
+            output_path(Some(Path::new("out/grid")), "tsv"),
+            PathBuf::from("out/grid.tsv")
+        );
+        assert_eq!(
+            output_path(Some(Path::new("out/grid.txt")), "tsv"),
+            PathBuf::from("out/grid.txt")
+        );
+    }
+
+    #[test]
+    fn parses_font_sizes_with_or_without_units() {
+        assert_eq!(parse_font_size("9pt"), Ok(9.0));
+        assert_eq!(parse_font_size("10.5"), Ok(10.5));
+        assert!(parse_font_size("0pt").is_err());
+        assert!(parse_font_size("large").is_err());
     }
 }
@@ -0,0 +1,273 @@
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs::File;
+use std::io;
+use std::path::Path;
+
+use crate::{Metadata, WordFollowEntry};
+
+/// The books a model was split into, so scripts and templates can find each book (and say
+/// which book covers which words) without relying on the file names
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct BookManifest {
+    #[serde(default)]
+    pub title: String,
+    #[serde(default)]
+    pub author: String,
+    pub n: usize,
+    /// [`counts_hash`] of all the books' counts together: the same however the model is split,
+    /// and different if any count differs (it identifies the counts, not the corpus or the
+    /// settings they came from)
+    pub counts_hash: String,
+    pub books: Vec<ManifestBook>,
+}
+
+/// One book of a [`BookManifest`]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ManifestBook {
+    /// Book number, starting from 1
+    pub book: usize,
+    /// The book's model JSON file, relative to the manifest
+    pub file: String,
+    /// The book's range label, e.g. "A-F"
+    pub range: String,
+    /// Number of prefix entries in the book
+    pub entries: usize,
+    /// Total (unscaled) follower count of the book's entries
+    pub weight: usize,
+    /// The first and last prefixes in the book (its guide words)
+    pub first_prefix: String,
+    pub last_prefix: String,
+}
+
+impl BookManifest {
+    /// The manifest for `books`, written to `files` (in the same order)
+    pub fn new(
+        books: &[(String, Vec<WordFollowEntry>)],
+        files: &[String],
+        metadata: Option<&Metadata>,
+    ) -> Self {
+        let all_entries: Vec<WordFollowEntry> = books
+            .iter()
+            .flat_map(|(_, entries)| entries.iter().cloned())
+            .collect();
+        let prefix =
+            |entry: Option<&WordFollowEntry>| entry.map(|e| e.prefix.join(" ")).unwrap_or_default();
+
+        BookManifest {
+            title: metadata.map(|m| m.title.clone()).unwrap_or_default(),
+            author: metadata.map(|m| m.author.clone()).unwrap_or_default(),
+            n: metadata.map_or_else(
+                || all_entries.first().map_or(0, |e| e.prefix.len() + 1),
+                |m| m.n,
+            ),
+            counts_hash: counts_hash(&all_entries),
+            books: books
+                .iter()
+                .zip(files)
+                .enumerate()
+                .map(|(index, ((range, entries), file))| ManifestBook {
+                    book: index + 1,
+                    file: file.clone(),
+                    range: range.clone(),
+                    entries: entries.len(),
+                    weight: entries.iter().map(crate::entry_weight).sum(),
+                    first_prefix: prefix(entries.first()),
+                    last_prefix: prefix(entries.last()),
+                })
+                .collect(),
+        }
+    }
+
+    /// Writes the manifest as pretty-printed JSON
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> io::Result<()> {
+        let file = File::create(path)?;
+        serde_json::to_writer_pretty(file, self)?;
+        Ok(())
+    }
+}
+
+/// Which book to look in for each first word of a model split into several books
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct BookIndex {
+    #[serde(default)]
+    pub title: String,
+    /// Number of books
+    pub books: usize,
+    /// Runs of first words found in the same books, in book order: a compact index for printing
+    pub ranges: Vec<IndexRange>,
+    /// The books holding entries for each first word (more than one if a word was split)
+    pub words: BTreeMap<String, Vec<usize>>,
+}
+
+/// First words `from` to `to` (inclusive) are all in `books`
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct IndexRange {
+    pub from: String,
+    pub to: String,
+    pub books: Vec<usize>,
+}
+
+/// Indexes split books (e.g. from [`crate::split_entries_into_books`]) by the first word of
+/// each prefix, numbering the books from 1
+pub fn book_index(
+    books: &[(String, Vec<WordFollowEntry>)],
+    metadata: Option<&Metadata>,
+) -> BookIndex {
+    let mut words: BTreeMap<String, Vec<usize>> = BTreeMap::new();
+    let mut order: Vec<String> = Vec::new();
+    for (index, (_, entries)) in books.iter().enumerate() {
+        for entry in entries {
+            let word = entry.prefix.first().cloned().unwrap_or_default();
+            let word_books = words.entry(word.clone()).or_insert_with(|| {
+                order.push(word);
+                Vec::new()
+            });
+            if word_books.last() != Some(&(index + 1)) {
+                word_books.push(index + 1);
+            }
+        }
+    }
+
+    let mut ranges: Vec<IndexRange> = Vec::new();
+    for word in order {
+        let word_books = &words[&word];
+        match ranges.last_mut() {
+            Some(range) if range.books == *word_books => range.to = word,
+            _ => ranges.push(IndexRange {
+                from: word.clone(),
+                to: word,
+                books: word_books.clone(),
+            }),
+        }
+    }
+
+    BookIndex {
+        title: metadata.map(|m| m.title.clone()).unwrap_or_default(),
+        books: books.len(),
+        ranges,
+        words,
+    }
+}
+
+impl BookIndex {
+    /// Writes the index as pretty-printed JSON
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> io::Result<()> {
+        let file = File::create(path)?;
+        serde_json::to_writer_pretty(file, self)?;
+        Ok(())
+    }
+}
+
+/// A short fingerprint of a model's counts (16 hex digits of FNV-1a), for checking that books
+/// were split from the same counts
+pub fn counts_hash(entries: &[WordFollowEntry]) -> String {
+    let mut hash = FNV_OFFSET;
+    for entry in entries {
+        for word in &entry.prefix {
+            hash = fnv1a(hash, word.as_bytes());
+            hash = fnv1a(hash, b" ");
+        }
+        hash = fnv1a(hash, b"\t");
+        for (word, count) in &entry.followers {
+            hash = fnv1a(hash, word.as_bytes());
+            hash = fnv1a(hash, &(*count as u64).to_le_bytes());
+        }
+        hash = fnv1a(hash, b"\n");
+    }
+    format!("{:016x}", hash)
+}
+
+pub(crate) const FNV_OFFSET: u64 = 0xcbf2_9ce4_8422_2325;
+const FNV_PRIME: u64 = 0x0000_0100_0000_01b3;
+
+pub(crate) fn fnv1a(mut hash: u64, bytes: &[u8]) -> u64 {
+    for byte in bytes {
+        hash ^= u64::from(*byte);
+        hash = hash.wrapping_mul(FNV_PRIME);
+    }
+    hash
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(prefix: &str, followers: &[(&str, usize)]) -> WordFollowEntry {
+        WordFollowEntry {
+            prefix: vec![prefix.to_string()],
+            followers: followers.iter().map(|(w, c)| (w.to_string(), *c)).collect(),
+        }
+    }
+
+    #[test]
+    fn lists_each_book() {
+        let books = vec![
+            (
+                "A-B".to_string(),
+                vec![
+                    entry("ant", &[("hill", 3), ("nest", 1)]),
+                    entry("bee", &[("hive", 2)]),
+                ],
+            ),
+            ("C".to_string(), vec![entry("cat", &[("nap", 5)])]),
+        ];
+        let files = vec!["m_book_1.json".to_string(), "m_book_2.json".to_string()];
+        let manifest = BookManifest::new(&books, &files, None);
+        assert_eq!(manifest.n, 2);
+
+        assert_eq!(manifest.books.len(), 2);
+        assert_eq!(manifest.books[0].entries, 2);
+        assert_eq!(manifest.books[0].weight, 6);
+        assert_eq!(manifest.books[0].first_prefix, "ant");
+        assert_eq!(manifest.books[0].last_prefix, "bee");
+        assert_eq!(manifest.books[1].file, "m_book_2.json");
+
+        // The hash covers the whole model's counts, and changes with any count
+        let all: Vec<WordFollowEntry> = books.iter().flat_map(|(_, e)| e.clone()).collect();
+        assert_eq!(manifest.counts_hash, counts_hash(&all));
+        let mut changed = all.clone();
+        changed[2].followers[0].1 = 6;
+        assert_ne!(counts_hash(&changed), manifest.counts_hash);
+        assert_eq!(manifest.counts_hash.len(), 16);
+    }
+
+    #[test]
+    fn indexes_first_words_by_book() {
+        // "the" is split across books 1 and 2
+        let books = vec![
+            (
+                "A-T".to_string(),
+                vec![
+                    entry("ant", &[("hill", 1)]),
+                    entry("bee", &[("hive", 1)]),
+                    entry("the", &[("cat", 1)]),
+                ],
+            ),
+            (
+                "T-Z".to_string(),
+                vec![entry("the", &[("dog", 1)]), entry("zoo", &[("keeper", 1)])],
+            ),
+            ("Z".to_string(), vec![entry("zoom", &[("in", 1)])]),
+        ];
+        let index = book_index(&books, None);
+
+        assert_eq!(index.books, 3);
+        assert_eq!(index.words["the"], vec![1, 2]);
+        assert_eq!(index.words["zoo"], vec![2]);
+        let ranges: Vec<(&str, &str, Vec<usize>)> = index
+            .ranges
+            .iter()
+            .map(|r| (r.from.as_str(), r.to.as_str(), r.books.clone()))
+            .collect();
+        assert_eq!(
+            ranges,
+            vec![
+                ("ant", "bee", vec![1]),
+                ("the", "the", vec![1, 2]),
+                ("zoo", "zoo", vec![2]),
+                ("zoom", "zoom", vec![3]),
+            ]
+        );
+    }
+}
@@ -0,0 +1,261 @@
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use std::io;
+
+use crate::model::{Model, Scaling};
+use crate::{Metadata, WordFollowEntry, model_type_str};
+
+/// One of the models combined by [`merge_models`]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ModelSource {
+    #[serde(default)]
+    pub title: String,
+    #[serde(default)]
+    pub author: String,
+    #[serde(default)]
+    pub url: String,
+    /// Path the model was loaded from
+    #[serde(default)]
+    pub path: String,
+    /// The source's counts were multiplied by this before adding them
+    pub weight: f64,
+}
+
+/// Sums the counts of several models, multiplying each model's counts by its weight.
+///
+/// With every weight 1 this gives the model of all the texts together, without recounting
+/// them; other weights interpolate between the models. Weighted sums are rounded to whole
+/// counts (after adding), and followers are re-sorted as in [`crate::NGramCounter::get_entries`].
+///
+/// All models must have raw (not d10-scaled) counts, the same n, the same context positions
+/// and (where recorded) the same tokenization. The returned metadata lists every source; its
+/// `stats` are left for the caller to fill in.
+pub fn merge_models(models: &[(&Model, f64)]) -> io::Result<(Vec<WordFollowEntry>, Metadata)> {
+    let Some((first, _)) = models.first() else {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            "Need at least one model to merge",
+        ));
+    };
+    let n = first.metadata.n;
+    let tokenization = models
+        .iter()
+        .find_map(|(model, _)| model.metadata.tokenization.clone());
+
+    for (model, weight) in models {
+        // Scaled counts are already normalised per prefix, so adding them would be meaningless
+        if model.scaling != Scaling::Raw {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!(
+                    "{} has d10-scaled counts; build the models with --raw to merge them",
+                    model.path.display()
+                ),
+            ));
+        }
+        if model.metadata.n != n {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!(
+                    "{} has n = {}, but {} has n = {}",
+                    model.path.display(),
+                    model.metadata.n,
+                    first.path.display(),
+                    n
+                ),
+            ));
+        }
+        if let (Some(expected), Some(found)) = (&tokenization, &model.metadata.tokenization)
+            && expected != found
+        {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!(
+                    "{} was tokenized with punctuation {:?}, but other models used {:?}",
+                    model.path.display(),
+                    found.punctuation,
+                    expected.punctuation
+                ),
+            ));
+        }
+        if model.metadata.context_positions != first.metadata.context_positions {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!(
+                    "{} uses context positions {:?}, but {} uses {:?}",
+                    model.path.display(),
+                    model.metadata.context_positions,
+                    first.path.display(),
+                    first.metadata.context_positions
+                ),
+            ));
+        }
+        if !(weight.is_finite() && *weight >= 0.0) {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!(
+                    "Weight for {} must be zero or more (got {})",
+                    model.path.display(),
+                    weight
+                ),
+            ));
+        }
+    }
+
+    let mut sums: BTreeMap<Vec<String>, HashMap<String, f64>> = BTreeMap::new();
+    for (model, weight) in models {
+        for entry in &model.entries {
+            let followers = sums.entry(entry.prefix.clone()).or_default();
+            for (word, count) in &entry.followers {
+                *followers.entry(word.clone()).or_default() += *count as f64 * weight;
+            }
+        }
+    }
+
+    let merged: BTreeMap<Vec<String>, HashMap<String, usize>> = sums
+        .into_iter()
+        .map(|(prefix, followers)| {
+            let followers: HashMap<String, usize> = followers
+                .into_iter()
+                .map(|(word, sum)| (word, sum.round() as usize))
+                .filter(|(_, count)| *count > 0)
+                .collect();
+            (prefix, followers)
+        })
+        .filter(|(_, followers)| !followers.is_empty())
+        .collect();
+
+    let sources: Vec<ModelSource> = models
+        .iter()
+        .map(|(model, weight)| ModelSource {
+            title: model.metadata.title.clone(),
+            author: model.metadata.author.clone(),
+            url: model.metadata.url.clone(),
+            path: model.path.display().to_string(),
+            weight: *weight,
+        })
+        .collect();
+
+    let metadata = Metadata {
+        title: distinct(sources.iter().map(|s| s.title.as_str())).join(" + "),
+        author: distinct(sources.iter().map(|s| s.author.as_str())).join(", "),
+        url: match distinct(sources.iter().map(|s| s.url.as_str())).as_slice() {
+            [url] => url.to_string(),
+            _ => String::new(),
+        },
+        n,
+        subtitle: format!(
+            "A {} language model merged from {} models",
+            model_type_str(n),
+            sources.len()
+        ),
+        version: env!("CARGO_PKG_VERSION").to_string(),
+        stats: None,
+        context: None,
+        tokenization,
+        sources,
+        context_positions: first.metadata.context_positions.clone(),
+    };
+
+    Ok((crate::convert_to_entries(&merged), metadata))
+}
+
+/// Non-empty values in first-seen order, without repeats (split books share a title)
+fn distinct<'a>(values: impl Iterator<Item = &'a str>) -> Vec<&'a str> {
+    let mut seen = Vec::new();
+    for value in values {
+        if !value.is_empty() && !seen.contains(&value) {
+            seen.push(value);
+        }
+    }
+    seen
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{NGramCounter, Scaling, Tokenization};
+    use std::path::PathBuf;
+
+    fn model(path: &str, title: &str, text: &str, n: usize) -> Model {
+        let mut counter = NGramCounter::new(n, vec![',', '.']).unwrap();
+        counter.process_line(text);
+        Model {
+            path: PathBuf::from(path),
+            metadata: Metadata {
+                title: title.to_string(),
+                author: "Author".to_string(),
+                url: String::new(),
+                n,
+                subtitle: String::new(),
+                version: String::new(),
+                stats: None,
+                tokenization: Some(counter.tokenization()),
+                ..Default::default()
+            },
+            scaling: Scaling::Raw,
+            entries: counter.get_entries(),
+        }
+    }
+
+    #[test]
+    fn summed_counts_match_counting_both_texts() -> io::Result<()> {
+        let a = model("a.json", "A", "see spot run. see spot jump.", 2);
+        let b = model("b.json", "B", "run spot run.", 2);
+        let (merged, metadata) = merge_models(&[(&a, 1.0), (&b, 1.0)])?;
+
+        // Counting the texts separately loses only the "." -> "run" bigram across the join
+        let mut both = NGramCounter::new(2, vec![',', '.']).unwrap();
+        both.process_line("see spot run. see spot jump.");
+        both.process_line("run spot run.");
+        let mut expected = both.get_entries();
+        let full_stop = expected.iter_mut().find(|e| e.prefix == ["."]).unwrap();
+        full_stop.followers.retain(|(w, _)| w != "run");
+        assert_eq!(merged, expected);
+
+        assert_eq!(metadata.title, "A + B");
+        assert_eq!(metadata.author, "Author");
+        assert_eq!(metadata.sources.len(), 2);
+        assert_eq!(metadata.sources[1].path, "b.json");
+        assert_eq!(
+            metadata.tokenization,
+            Some(Tokenization {
+                punctuation: ",.".to_string()
+            })
+        );
+
+        // Weighting: "spot" -> run is 2 * 0.5 + 1 * 2 = 3, "spot" -> jump is 1 * 0.5 -> 1 (rounded)
+        let (weighted, _) = merge_models(&[(&a, 0.5), (&b, 2.0)])?;
+        let spot = weighted.iter().find(|e| e.prefix == ["spot"]).unwrap();
+        assert_eq!(
+            spot.followers,
+            vec![("run".to_string(), 3), ("jump".to_string(), 1)]
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn refuses_mismatched_models() {
+        let a = model("a.json", "A", "see spot run.", 2);
+        let trigram = model("c.json", "C", "see spot run.", 3);
+        let err = merge_models(&[(&a, 1.0), (&trigram, 1.0)]).unwrap_err();
+        assert!(err.to_string().contains("c.json has n = 3"));
+
+        let mut other = model("d.json", "D", "see spot run.", 2);
+        other.metadata.tokenization = Some(Tokenization {
+            punctuation: ",.!?".to_string(),
+        });
+        let err = merge_models(&[(&a, 1.0), (&other, 1.0)]).unwrap_err();
+        assert!(err.to_string().contains("punctuation"));
+
+        // Older files without recorded tokenization can still be merged
+        other.metadata.tokenization = None;
+        assert!(merge_models(&[(&a, 1.0), (&other, 1.0)]).is_ok());
+
+        // Even a single scaled model is refused
+        other.scaling = Scaling::D10;
+        let err = merge_models(&[(&a, 1.0), (&other, 1.0)]).unwrap_err();
+        assert!(err.to_string().contains("d.json has d10-scaled counts"));
+    }
+}
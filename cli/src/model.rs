@@ -0,0 +1,177 @@
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeSet;
+use std::fs::File;
+use std::io::{self, BufReader};
+use std::path::{Path, PathBuf};
+
+use crate::{Metadata, WordFollowEntry};
+
+/// How the follower counts in a model file were written
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Scaling {
+    /// Raw counts straight from the text
+    Raw,
+    /// Counts scaled to the 10^k - 1 range for d10 dice
+    D10,
+}
+
+/// A model read back from a JSON file written by [`crate::save_to_json`]
+#[derive(Debug, Clone)]
+pub struct Model {
+    /// Path the model was loaded from
+    pub path: PathBuf,
+    pub metadata: Metadata,
+    /// Files written before the scaling was recorded are assumed to be d10-scaled
+    pub scaling: Scaling,
+    /// Entries with per-follower (not cumulative) counts
+    pub entries: Vec<WordFollowEntry>,
+}
+
+impl Model {
+    /// All tokens appearing in the model, either in a prefix or as a follower
+    pub fn vocabulary(&self) -> BTreeSet<String> {
+        vocabulary(&self.entries)
+    }
+}
+
+/// All tokens appearing in a set of entries, either in a prefix or as a follower
+pub fn vocabulary(entries: &[WordFollowEntry]) -> BTreeSet<String> {
+    let mut vocabulary = BTreeSet::new();
+    for entry in entries {
+        vocabulary.extend(entry.prefix.iter().cloned());
+        vocabulary.extend(entry.followers.iter().map(|(w, _)| w.clone()));
+    }
+    vocabulary
+}
+
+#[derive(Deserialize)]
+struct ModelFile {
+    metadata: Metadata,
+    #[serde(default)]
+    scaling: Option<Scaling>,
+    data: Vec<Vec<serde_json::Value>>,
+}
+
+/// Loads a model JSON file, converting the cumulative follower counts back into per-follower counts
+pub fn load_model<P: AsRef<Path>>(path: P) -> io::Result<Model> {
+    let path = path.as_ref();
+    let file = File::open(path)?;
+    let model_file: ModelFile = serde_json::from_reader(BufReader::new(file)).map_err(|e| {
+        io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("{} is not a valid model file: {e}", path.display()),
+        )
+    })?;
+
+    let entries = model_file
+        .data
+        .iter()
+        .enumerate()
+        .map(|(index, row)| {
+            parse_row(row).ok_or_else(|| {
+                io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    format!(
+                        "{}: data row {} should look like [\"prefix\", total, [\"word\", cumulative], ...]",
+                        path.display(),
+                        index + 1
+                    ),
+                )
+            })
+        })
+        .collect::<io::Result<Vec<_>>>()?;
+
+    Ok(Model {
+        path: path.to_path_buf(),
+        metadata: model_file.metadata,
+        scaling: model_file.scaling.unwrap_or(Scaling::D10),
+        entries,
+    })
+}
+
+fn parse_row(row: &[serde_json::Value]) -> Option<WordFollowEntry> {
+    let prefix_str = row.first()?.as_str()?;
+    row.get(1)?.as_u64()?;
+
+    let mut followers = Vec::new();
+    let mut previous = 0u64;
+    for follower in &row[2..] {
+        let pair = follower.as_array()?;
+        let word = pair.first()?.as_str()?;
+        let cumulative = pair.get(1)?.as_u64()?;
+        followers.push((
+            word.to_string(),
+            cumulative.saturating_sub(previous) as usize,
+        ));
+        previous = cumulative;
+    }
+
+    Some(WordFollowEntry {
+        // Unigram models have a single row with an empty prefix
+        prefix: prefix_str
+            .split(' ')
+            .filter(|w| !w.is_empty())
+            .map(str::to_string)
+            .collect(),
+        followers,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::save_to_json;
+    use tempfile::NamedTempFile;
+
+    #[test]
+    fn round_trips_raw_counts() -> io::Result<()> {
+        let entries = vec![
+            WordFollowEntry {
+                prefix: vec!["the".to_string(), "old".to_string()],
+                followers: vec![("man".to_string(), 3), ("sea".to_string(), 1)],
+            },
+            WordFollowEntry {
+                prefix: vec!["old".to_string(), "man".to_string()],
+                followers: vec![(".".to_string(), 2)],
+            },
+        ];
+        let metadata = Metadata {
+            title: "Round Trip".to_string(),
+            author: "Test".to_string(),
+            url: "https://example.com".to_string(),
+            n: 3,
+            subtitle: "A trigram language model".to_string(),
+            version: "test".to_string(),
+            stats: None,
+            ..Default::default()
+        };
+
+        let file = NamedTempFile::new()?;
+        save_to_json(&entries, file.path(), Some(&metadata), None, true)?;
+        let model = load_model(file.path())?;
+
+        assert_eq!(model.scaling, Scaling::Raw);
+        assert_eq!(model.metadata.title, "Round Trip");
+        assert_eq!(model.metadata.n, 3);
+        assert_eq!(model.entries, entries);
+        assert_eq!(model.vocabulary().len(), 5);
+
+        Ok(())
+    }
+
+    #[test]
+    fn rejects_malformed_rows() -> io::Result<()> {
+        let file = NamedTempFile::new()?;
+        std::fs::write(
+            file.path(),
+            r#"{"metadata": {"n": 2}, "data": [["the", 9, ["cat"]]]}"#,
+        )?;
+
+        let err = load_model(file.path()).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+        assert!(err.to_string().contains("data row 1"));
+
+        Ok(())
+    }
+}
@@ -0,0 +1,123 @@
+use std::fs::File;
+use std::io::{self, BufRead, BufReader};
+use std::ops::RangeInclusive;
+use std::path::Path;
+
+use crate::{NGramCounter, read_frontmatter};
+
+/// Counts several n-gram sizes (e.g. bigrams, trigrams and 4-grams) in one pass over a text.
+///
+/// Each line is tokenized once and the tokens are counted by one [`NGramCounter`] per size,
+/// so the counters match those built separately with [`NGramCounter::process_file`].
+#[derive(Debug)]
+pub struct MultiOrderCounter {
+    counters: Vec<NGramCounter>,
+}
+
+impl MultiOrderCounter {
+    /// Creates a counter for each n in `orders`
+    pub fn new(orders: RangeInclusive<usize>, punctuation: Vec<char>) -> io::Result<Self> {
+        if orders.is_empty() {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!(
+                    "N-gram size range {}..{} is empty",
+                    orders.start(),
+                    orders.end()
+                ),
+            ));
+        }
+
+        let counters = orders
+            .map(|n| NGramCounter::new(n, punctuation.clone()))
+            .collect::<io::Result<Vec<_>>>()?;
+        Ok(MultiOrderCounter { counters })
+    }
+
+    /// Process a single line of text for every n-gram size
+    pub fn process_line(&mut self, line: &str) {
+        let (first, rest) = self
+            .counters
+            .split_first_mut()
+            .expect("there is at least one n-gram size");
+        let words = first.normalizer.normalize_line(line);
+        for counter in rest {
+            counter.process_tokens(words.clone());
+        }
+        first.process_tokens(words);
+    }
+
+    /// Process a file containing text with frontmatter, reading it only once
+    pub fn process_file<P: AsRef<Path>>(&mut self, path: P) -> io::Result<()> {
+        let mut reader = BufReader::new(File::open(&path)?);
+        let frontmatter_raw = read_frontmatter(&mut reader)?;
+        for counter in &mut self.counters {
+            counter.set_frontmatter(&frontmatter_raw)?;
+        }
+
+        for line in reader.lines() {
+            self.process_line(&line?);
+        }
+
+        for counter in &mut self.counters {
+            counter.calculate_statistics();
+        }
+
+        Ok(())
+    }
+
+    /// The counters, smallest n first
+    pub fn counters(&self) -> &[NGramCounter] {
+        &self.counters
+    }
+
+    /// The counters, smallest n first (e.g. to enable tracing or context columns before counting)
+    pub fn counters_mut(&mut self) -> &mut [NGramCounter] {
+        &mut self.counters
+    }
+
+    /// Consumes the counter, returning the per-size counters (smallest n first)
+    pub fn into_counters(self) -> Vec<NGramCounter> {
+        self.counters
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Write;
+    use tempfile::NamedTempFile;
+
+    #[test]
+    fn matches_separate_counters() -> io::Result<()> {
+        let mut file = NamedTempFile::new()?;
+        writeln!(
+            file,
+            "---\ntitle: Orders\nauthor: Test\nurl: https://example.com\n---"
+        )?;
+        writeln!(file, "See Spot run. See Spot jump.")?;
+        writeln!(file, "Run, Spot, run.")?;
+
+        let mut multi = MultiOrderCounter::new(2..=4, vec![',', '.'])?;
+        multi.process_file(file.path())?;
+        assert_eq!(multi.counters().len(), 3);
+
+        for counter in multi.counters() {
+            let n = counter.get_metadata().unwrap().n;
+            let mut single = NGramCounter::new(n, vec![',', '.'])?;
+            single.process_file(file.path())?;
+            assert_eq!(counter.get_entries(), single.get_entries());
+            assert_eq!(
+                counter.get_stats().total_ngram_occurrences,
+                single.get_stats().total_ngram_occurrences
+            );
+        }
+
+        #[allow(clippy::reversed_empty_ranges)]
+        let empty = 4..=2;
+        assert!(MultiOrderCounter::new(empty, vec![]).is_err());
+        assert!(MultiOrderCounter::new(0..=2, vec![]).is_err());
+
+        Ok(())
+    }
+}
@@ -0,0 +1,325 @@
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::io;
+
+use crate::layout::BookLayout;
+
+/// Token that replaces words outside the kept vocabulary
+pub const UNKNOWN_TOKEN: &str = "<unk>";
+
+/// Ways to shrink a counted model down to a classroom-sized booklet.
+///
+/// The steps are applied in the order of the fields; `None` skips a step. Dropping a whole
+/// prefix also drops the followers that lead to it (and so on), so that every word in the
+/// pruned booklet can still be looked up.
+#[derive(Debug, Clone, Default)]
+pub struct PruneOptions {
+    /// Keep the V most frequent tokens, replacing the rest with [`UNKNOWN_TOKEN`]
+    pub vocabulary: Option<usize>,
+    /// Drop followers counted fewer than this many times
+    pub min_count: Option<usize>,
+    /// Keep at most K followers per prefix (the most frequent)
+    pub max_followers: Option<usize>,
+    /// Drop the least frequent prefixes until the estimated booklet fits in this many pages
+    pub max_pages: Option<usize>,
+    /// Layout used for the page estimates
+    pub layout: BookLayout,
+}
+
+impl PruneOptions {
+    /// Whether any pruning step is turned on
+    pub fn is_active(&self) -> bool {
+        self.vocabulary.is_some()
+            || self.min_count.is_some()
+            || self.max_followers.is_some()
+            || self.max_pages.is_some()
+    }
+}
+
+/// What pruning removed from a model
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PruningStats {
+    /// N-gram occurrences counted before pruning
+    pub ngram_occurrences_before: usize,
+    /// Distinct prefixes before pruning
+    pub unique_prefixes_before: usize,
+    /// Fraction of the n-gram occurrences (probability mass) merged into [`UNKNOWN_TOKEN`] n-grams
+    pub unknown_mass: f64,
+    /// Fraction of the n-gram occurrences (probability mass) dropped from the model
+    pub removed_mass: f64,
+    /// Estimated booklet pages after pruning (see [`BookLayout`])
+    pub estimated_pages: usize,
+}
+
+type PrefixMap = BTreeMap<Vec<String>, HashMap<String, usize>>;
+
+/// Prunes counts in place. `frequency_order` lists every token, most frequent first, and
+/// `gapped` is set for skip-gram models, whose next prefix can't be found from an entry.
+pub(crate) fn prune_counts(
+    prefix_map: &mut PrefixMap,
+    frequency_order: &[String],
+    options: &PruneOptions,
+    gapped: bool,
+) -> io::Result<PruningStats> {
+    let occurrences = |map: &PrefixMap| -> usize { map.values().flat_map(|f| f.values()).sum() };
+    let before = occurrences(prefix_map);
+    let unique_prefixes_before = prefix_map.len();
+    let mut unknown = 0;
+
+    if let Some(size) = options.vocabulary
+        && size < frequency_order.len()
+    {
+        let kept: HashSet<&str> = frequency_order[..size].iter().map(String::as_str).collect();
+        let map_token = |token: &String| -> String {
+            if kept.contains(token.as_str()) {
+                token.clone()
+            } else {
+                UNKNOWN_TOKEN.to_string()
+            }
+        };
+
+        let mut mapped = PrefixMap::new();
+        for (prefix, followers) in std::mem::take(prefix_map) {
+            let new_prefix: Vec<String> = prefix.iter().map(map_token).collect();
+            let prefix_unknown = new_prefix != prefix;
+            let row = mapped.entry(new_prefix).or_default();
+            for (follower, count) in followers {
+                let new_follower = map_token(&follower);
+                if prefix_unknown || new_follower != follower {
+                    unknown += count;
+                }
+                *row.entry(new_follower).or_default() += count;
+            }
+        }
+        *prefix_map = mapped;
+    }
+    let counted: HashSet<Vec<String>> = prefix_map.keys().cloned().collect();
+
+    if let Some(min_count) = options.min_count {
+        for followers in prefix_map.values_mut() {
+            followers.retain(|_, count| *count >= min_count);
+        }
+        prefix_map.retain(|_, followers| !followers.is_empty());
+    }
+
+    if let Some(max_followers) = options.max_followers {
+        for followers in prefix_map.values_mut() {
+            if followers.len() > max_followers {
+                let mut ranked: Vec<(String, usize)> = followers.drain().collect();
+                ranked.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+                ranked.truncate(max_followers);
+                followers.extend(ranked);
+            }
+        }
+    }
+
+    if let Some(max_pages) = options.max_pages {
+        fit_to_pages(prefix_map, max_pages, &options.layout)?;
+    }
+
+    if !gapped {
+        drop_dead_ends(prefix_map, &counted);
+    }
+
+    let fraction = |count: usize| count as f64 / before.max(1) as f64;
+    Ok(PruningStats {
+        ngram_occurrences_before: before,
+        unique_prefixes_before,
+        unknown_mass: fraction(unknown),
+        removed_mass: fraction(before - occurrences(prefix_map)),
+        estimated_pages: options
+            .layout
+            .estimate_pages(&crate::convert_to_entries(prefix_map)),
+    })
+}
+
+/// Drops the followers that lead to a prefix in `counted` that pruning removed, repeating
+/// until none are left (a row losing all its followers removes another prefix).
+///
+/// Prefixes that were never counted (e.g. after the last word of the text) are dead ends in
+/// the unpruned model too, so followers leading to them are kept.
+fn drop_dead_ends(prefix_map: &mut PrefixMap, counted: &HashSet<Vec<String>>) {
+    loop {
+        let removed: HashSet<&Vec<String>> = counted
+            .iter()
+            .filter(|prefix| !prefix_map.contains_key(*prefix))
+            .collect();
+        let mut dropped = Vec::new();
+        for (prefix, followers) in prefix_map.iter() {
+            // Unigram models have no next prefix to look up
+            if prefix.is_empty() {
+                continue;
+            }
+            for follower in followers.keys() {
+                let mut next = prefix[1..].to_vec();
+                next.push(follower.clone());
+                if removed.contains(&next) {
+                    dropped.push((prefix.clone(), follower.clone()));
+                }
+            }
+        }
+        if dropped.is_empty() {
+            return;
+        }
+
+        for (prefix, follower) in dropped {
+            if let Some(followers) = prefix_map.get_mut(&prefix) {
+                followers.remove(&follower);
+            }
+        }
+        prefix_map.retain(|_, followers| !followers.is_empty());
+    }
+}
+
+/// Drops the least frequent prefixes until the estimated page count is at most `max_pages`
+fn fit_to_pages(
+    prefix_map: &mut PrefixMap,
+    max_pages: usize,
+    layout: &BookLayout,
+) -> io::Result<()> {
+    if max_pages <= layout.front_matter_pages {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!(
+                "A booklet needs more than {} pages for its front matter (got a maximum of {})",
+                layout.front_matter_pages, max_pages
+            ),
+        ));
+    }
+    let available = (max_pages - layout.front_matter_pages) as f64 * layout.lines_per_page();
+
+    let entries = crate::convert_to_entries(prefix_map);
+    let mut rows: Vec<(usize, f64, &[String])> = entries
+        .iter()
+        .map(|entry| {
+            let total = entry.followers.iter().map(|(_, count)| count).sum();
+            (total, layout.entry_lines(entry), entry.prefix.as_slice())
+        })
+        .collect();
+    // Least frequent first (ties: later prefixes first), so the most useful rows are kept
+    rows.sort_by(|a, b| a.0.cmp(&b.0).then_with(|| b.2.cmp(a.2)));
+
+    let mut lines: f64 = rows.iter().map(|(_, lines, _)| lines).sum();
+    for (_, row_lines, prefix) in rows {
+        if lines <= available {
+            break;
+        }
+        prefix_map.remove(prefix);
+        lines -= row_lines;
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{NGramCounter, PruneOptions, UNKNOWN_TOKEN};
+
+    fn counter(text: &str) -> NGramCounter {
+        let mut counter = NGramCounter::new(2, vec![',', '.']).unwrap();
+        counter.process_line(text);
+        counter
+    }
+
+    #[test]
+    fn replaces_rare_words_with_unknown() {
+        // the: 4, cat: 2, sat: 2, ".": 2, dog: 1, ran: 1
+        let mut counter = counter("the cat sat. the dog ran. the cat sat the");
+        let options = PruneOptions {
+            vocabulary: Some(4),
+            ..PruneOptions::default()
+        };
+        counter.prune(&options).unwrap();
+
+        let entries = counter.get_entries();
+        let unknown = entries
+            .iter()
+            .find(|e| e.prefix == [UNKNOWN_TOKEN])
+            .unwrap();
+        assert_eq!(
+            unknown.followers,
+            vec![(".".to_string(), 1), (UNKNOWN_TOKEN.to_string(), 1)]
+        );
+        let the = entries.iter().find(|e| e.prefix == ["the"]).unwrap();
+        assert_eq!(
+            the.followers,
+            vec![("cat".to_string(), 2), (UNKNOWN_TOKEN.to_string(), 1)]
+        );
+
+        let pruning = counter.get_stats().pruning.clone().unwrap();
+        // "the dog", "dog ran" and "ran ." out of 11 bigrams; nothing dropped
+        assert_eq!(pruning.ngram_occurrences_before, 11);
+        assert!((pruning.unknown_mass - 3.0 / 11.0).abs() < 1e-9);
+        assert_eq!(pruning.removed_mass, 0.0);
+        assert_eq!(counter.get_stats().total_ngram_occurrences, 11);
+        assert_eq!(counter.corpus_stats(5).vocabulary_size, 5);
+    }
+
+    #[test]
+    fn drops_rare_followers_and_caps_rows() {
+        let mut counter = counter("a b. a b. a e. a f. b a");
+        let options = PruneOptions {
+            min_count: Some(2),
+            max_followers: Some(1),
+            ..PruneOptions::default()
+        };
+        counter.prune(&options).unwrap();
+
+        let entries = counter.get_entries();
+        // Only a -> b, b -> "." and "." -> a were seen at least twice
+        assert_eq!(entries.len(), 3);
+        assert_eq!(entries[1].followers, vec![("b".to_string(), 2)]);
+        let pruning = counter.get_stats().pruning.clone().unwrap();
+        assert_eq!(pruning.ngram_occurrences_before, 13);
+        assert!((pruning.removed_mass - 6.0 / 13.0).abs() < 1e-9);
+    }
+
+    /// Spells out a number with letters, since the tokenizer drops digits
+    fn letters(i: usize) -> String {
+        i.to_string()
+            .bytes()
+            .map(|d| (b'a' + d - b'0') as char)
+            .collect()
+    }
+
+    #[test]
+    fn fits_to_a_page_budget() {
+        let text = (0..400)
+            .map(|i| format!("w{} n{}", letters(i % 200), letters(i % 7)))
+            .collect::<Vec<_>>()
+            .join(" ");
+        let mut counter = counter(&text);
+        let options = PruneOptions::default();
+        let full = options.layout.estimate_pages(&counter.get_entries());
+        assert!(full > 5);
+
+        let options = PruneOptions {
+            max_pages: Some(5),
+            ..PruneOptions::default()
+        };
+        counter.prune(&options).unwrap();
+        let pruning = counter.get_stats().pruning.clone().unwrap();
+        assert_eq!(pruning.estimated_pages, 5);
+        assert!(pruning.removed_mass > 0.0);
+
+        // Every follower in the pruned model can still be looked up
+        let entries = counter.get_entries();
+        assert!(entries.len() < 207);
+        for entry in &entries {
+            for (follower, _) in &entry.followers {
+                assert!(
+                    entries.iter().any(|e| e.prefix == [follower.as_str()]),
+                    "{:?} -> {} is a dead end",
+                    entry.prefix,
+                    follower
+                );
+            }
+        }
+
+        let options = PruneOptions {
+            max_pages: Some(4),
+            ..PruneOptions::default()
+        };
+        assert!(counter.prune(&options).is_err());
+    }
+}
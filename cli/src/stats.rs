@@ -0,0 +1,338 @@
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+
+use crate::WordFollowEntry;
+
+/// Number of items kept in each of the top-N lists by default
+pub const DEFAULT_TOP_N: usize = 10;
+
+/// Richer corpus statistics, used for the summary PDF and for comparing corpora
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CorpusStats {
+    /// Number of distinct tokens (word types) in the text
+    pub vocabulary_size: usize,
+    /// Vocabulary size divided by the total number of tokens
+    pub type_token_ratio: f64,
+    /// Number of tokens which occur exactly once
+    pub hapax_legomena: usize,
+    /// Slope of the least-squares fit of log(frequency) against log(rank)
+    pub zipf_slope: Option<f64>,
+    /// Number of distinct followers per prefix
+    pub branching: BranchingStats,
+    /// Entropy (in bits) of each prefix's follower distribution
+    pub entropy: EntropyStats,
+    /// Most frequent tokens with their counts
+    pub top_tokens: Vec<(String, usize)>,
+    /// Prefixes with the most follower occurrences
+    pub top_prefixes: Vec<(Vec<String>, usize)>,
+    /// Most frequent complete n-grams (prefix, follower, count)
+    pub top_ngrams: Vec<(Vec<String>, String, usize)>,
+}
+
+/// Summary of the number of distinct followers per prefix
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BranchingStats {
+    /// Mean number of distinct followers over all prefixes
+    pub mean: f64,
+    /// Largest number of distinct followers of any prefix
+    pub max: usize,
+}
+
+/// Distribution of per-prefix follower entropy
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EntropyStats {
+    /// Unweighted mean over all prefixes
+    pub mean: f64,
+    /// Mean weighted by prefix frequency (i.e. the conditional entropy of the model)
+    pub weighted_mean: f64,
+    /// Median over all prefixes
+    pub median: f64,
+    /// Highest entropy of any prefix
+    pub max: f64,
+    /// Number of prefixes in each one-bit-wide entropy bucket, starting from zero
+    pub histogram: Vec<usize>,
+}
+
+/// Calculates corpus statistics from token frequencies and the counted n-gram entries
+pub fn corpus_stats(
+    token_counts: &HashMap<String, usize>,
+    entries: &[WordFollowEntry],
+    top_n: usize,
+) -> CorpusStats {
+    let total_tokens: usize = token_counts.values().sum();
+    let vocabulary_size = token_counts.len();
+
+    let mut tokens: Vec<(String, usize)> = token_counts
+        .iter()
+        .map(|(token, count)| (token.clone(), *count))
+        .collect();
+    tokens.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+
+    let type_token_ratio = if total_tokens == 0 {
+        0.0
+    } else {
+        vocabulary_size as f64 / total_tokens as f64
+    };
+    let hapax_legomena = tokens.iter().filter(|(_, count)| *count == 1).count();
+    let frequencies: Vec<usize> = tokens.iter().map(|(_, count)| *count).collect();
+    let zipf_slope = zipf_slope(&frequencies);
+
+    let mut prefixes: Vec<(Vec<String>, usize)> = entries
+        .iter()
+        .map(|entry| (entry.prefix.clone(), entry_total(entry)))
+        .collect();
+    prefixes.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+
+    let mut ngrams: Vec<(Vec<String>, String, usize)> = entries
+        .iter()
+        .flat_map(|entry| {
+            entry
+                .followers
+                .iter()
+                .map(|(follower, count)| (entry.prefix.clone(), follower.clone(), *count))
+        })
+        .collect();
+    ngrams.sort_by(|a, b| {
+        b.2.cmp(&a.2)
+            .then_with(|| a.0.cmp(&b.0))
+            .then_with(|| a.1.cmp(&b.1))
+    });
+
+    tokens.truncate(top_n);
+    prefixes.truncate(top_n);
+    ngrams.truncate(top_n);
+
+    CorpusStats {
+        vocabulary_size,
+        type_token_ratio,
+        hapax_legomena,
+        zipf_slope,
+        branching: branching_stats(entries),
+        entropy: entropy_stats(entries),
+        top_tokens: tokens,
+        top_prefixes: prefixes,
+        top_ngrams: ngrams,
+    }
+}
+
+/// Shannon entropy (in bits) of a distribution given as raw counts
+pub fn entropy_bits<I: IntoIterator<Item = usize>>(counts: I) -> f64 {
+    let counts: Vec<usize> = counts.into_iter().filter(|c| *c > 0).collect();
+    let total: usize = counts.iter().sum();
+    if total == 0 {
+        return 0.0;
+    }
+
+    // Folding from 0.0 rather than summing keeps a certain outcome from giving -0.0
+    counts.iter().fold(0.0, |sum, count| {
+        let p = *count as f64 / total as f64;
+        sum - p * p.log2()
+    })
+}
+
+/// KL divergence D(P || Q) in bits between the n-gram distributions of two models.
+///
+/// Each model is treated as a distribution over complete n-grams (prefix + follower). Q is
+/// add-one smoothed over the n-grams of both models, so n-grams missing from Q don't make
+/// the divergence infinite.
+pub fn kl_divergence(p: &[WordFollowEntry], q: &[WordFollowEntry]) -> f64 {
+    smoothed_kl_bits(&ngram_counts(p), &ngram_counts(q))
+}
+
+/// Jensen-Shannon divergence in bits (between 0 and 1) between the n-gram distributions of
+/// two models; unlike [`kl_divergence`] it is symmetric and needs no smoothing
+pub fn js_divergence(p: &[WordFollowEntry], q: &[WordFollowEntry]) -> f64 {
+    js_bits(&ngram_counts(p), &ngram_counts(q))
+}
+
+// Ordered maps keep the floating-point sums the same from run to run
+fn ngram_counts(entries: &[WordFollowEntry]) -> BTreeMap<(Vec<String>, String), usize> {
+    entries
+        .iter()
+        .flat_map(|entry| {
+            entry
+                .followers
+                .iter()
+                .map(|(w, c)| ((entry.prefix.clone(), w.clone()), *c))
+        })
+        .collect()
+}
+
+/// D(P || Q) for two count distributions, with Q add-one smoothed over the union of their keys
+pub(crate) fn smoothed_kl_bits<K: Ord>(p: &BTreeMap<K, usize>, q: &BTreeMap<K, usize>) -> f64 {
+    let p_total: usize = p.values().sum();
+    if p_total == 0 {
+        return 0.0;
+    }
+    let support: BTreeSet<&K> = p.keys().chain(q.keys()).collect();
+    let q_total = q.values().sum::<usize>() as f64 + support.len() as f64;
+
+    p.iter()
+        .map(|(key, count)| {
+            let p_x = *count as f64 / p_total as f64;
+            let q_x = (q.get(key).copied().unwrap_or(0) as f64 + 1.0) / q_total;
+            p_x * (p_x / q_x).log2()
+        })
+        .sum()
+}
+
+/// Jensen-Shannon divergence (bits) between two count distributions
+pub(crate) fn js_bits<K: Ord>(p: &BTreeMap<K, usize>, q: &BTreeMap<K, usize>) -> f64 {
+    let p_total = p.values().sum::<usize>() as f64;
+    let q_total = q.values().sum::<usize>() as f64;
+    if p_total == 0.0 || q_total == 0.0 {
+        return if p_total == q_total { 0.0 } else { 1.0 };
+    }
+
+    let support: BTreeSet<&K> = p.keys().chain(q.keys()).collect();
+    support
+        .into_iter()
+        .map(|key| {
+            let p_x = p.get(key).copied().unwrap_or(0) as f64 / p_total;
+            let q_x = q.get(key).copied().unwrap_or(0) as f64 / q_total;
+            let m = (p_x + q_x) / 2.0;
+            let term = |x: f64| if x > 0.0 { x * (x / m).log2() } else { 0.0 };
+            (term(p_x) + term(q_x)) / 2.0
+        })
+        .sum()
+}
+
+fn entry_total(entry: &WordFollowEntry) -> usize {
+    entry.followers.iter().map(|(_, count)| *count).sum()
+}
+
+fn branching_stats(entries: &[WordFollowEntry]) -> BranchingStats {
+    if entries.is_empty() {
+        return BranchingStats { mean: 0.0, max: 0 };
+    }
+
+    let total: usize = entries.iter().map(|e| e.followers.len()).sum();
+    BranchingStats {
+        mean: total as f64 / entries.len() as f64,
+        max: entries.iter().map(|e| e.followers.len()).max().unwrap_or(0),
+    }
+}
+
+fn entropy_stats(entries: &[WordFollowEntry]) -> EntropyStats {
+    let mut weighted_sum = 0.0;
+    let mut total_weight = 0usize;
+    let mut entropies: Vec<f64> = entries
+        .iter()
+        .map(|entry| {
+            let h = entropy_bits(entry.followers.iter().map(|(_, count)| *count));
+            let weight = entry_total(entry);
+            weighted_sum += h * weight as f64;
+            total_weight += weight;
+            h
+        })
+        .collect();
+
+    if entropies.is_empty() {
+        return EntropyStats {
+            mean: 0.0,
+            weighted_mean: 0.0,
+            median: 0.0,
+            max: 0.0,
+            histogram: Vec::new(),
+        };
+    }
+
+    entropies.sort_by(|a, b| a.total_cmp(b));
+    let mid = entropies.len() / 2;
+    let median = if entropies.len().is_multiple_of(2) {
+        (entropies[mid - 1] + entropies[mid]) / 2.0
+    } else {
+        entropies[mid]
+    };
+    let max = entropies[entropies.len() - 1];
+
+    let mut histogram = vec![0usize; max.floor() as usize + 1];
+    for h in &entropies {
+        histogram[h.floor() as usize] += 1;
+    }
+
+    EntropyStats {
+        mean: entropies.iter().sum::<f64>() / entropies.len() as f64,
+        weighted_mean: if total_weight == 0 {
+            0.0
+        } else {
+            weighted_sum / total_weight as f64
+        },
+        median,
+        max,
+        histogram,
+    }
+}
+
+/// Fits log(frequency) = a + b log(rank) and returns the slope b
+fn zipf_slope(sorted_frequencies: &[usize]) -> Option<f64> {
+    if sorted_frequencies.len() < 2 {
+        return None;
+    }
+
+    let points: Vec<(f64, f64)> = sorted_frequencies
+        .iter()
+        .enumerate()
+        .map(|(rank, freq)| (((rank + 1) as f64).ln(), (*freq as f64).ln()))
+        .collect();
+    let n = points.len() as f64;
+    let mean_x = points.iter().map(|(x, _)| x).sum::<f64>() / n;
+    let mean_y = points.iter().map(|(_, y)| y).sum::<f64>() / n;
+
+    let covariance: f64 = points
+        .iter()
+        .map(|(x, y)| (x - mean_x) * (y - mean_y))
+        .sum();
+    let variance: f64 = points.iter().map(|(x, _)| (x - mean_x).powi(2)).sum();
+
+    if variance == 0.0 {
+        None
+    } else {
+        Some(covariance / variance)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::NGramCounter;
+
+    #[test]
+    fn computes_vocabulary_and_entropy_stats() {
+        let mut counter = NGramCounter::new(2, vec![',', '.']).unwrap();
+        counter.process_line("the cat sat on the mat and the cat ate");
+        let stats = counter.corpus_stats(3);
+
+        // the(3) cat(2) sat on mat and ate (1 each)
+        assert_eq!(stats.vocabulary_size, 7);
+        assert_eq!(stats.hapax_legomena, 5);
+        assert!((stats.type_token_ratio - 0.7).abs() < 1e-9);
+        assert_eq!(
+            stats.top_tokens,
+            vec![
+                ("the".to_string(), 3),
+                ("cat".to_string(), 2),
+                ("and".to_string(), 1)
+            ]
+        );
+
+        // "the" -> cat(2), mat(1) and "cat" -> sat(1), ate(1); every other prefix is deterministic
+        assert_eq!(stats.branching.max, 2);
+        assert!((stats.branching.mean - 8.0 / 6.0).abs() < 1e-9);
+        assert_eq!(stats.entropy.max, 1.0);
+        assert_eq!(stats.entropy.histogram, vec![5, 1]);
+        assert_eq!(
+            stats.top_ngrams[0],
+            (vec!["the".to_string()], "cat".to_string(), 2)
+        );
+        assert!(stats.zipf_slope.unwrap() < 0.0);
+    }
+
+    #[test]
+    fn entropy_of_uniform_distribution() {
+        assert_eq!(entropy_bits([1, 1, 1, 1]), 2.0);
+        assert_eq!(entropy_bits([5]), 0.0);
+        assert!(entropy_bits([5]).is_sign_positive());
+        assert_eq!(entropy_bits(Vec::new()), 0.0);
+    }
+}
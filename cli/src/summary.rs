@@ -0,0 +1,178 @@
+use serde::Serialize;
+use std::collections::{BTreeSet, HashMap};
+use std::io;
+
+use crate::model::{Model, Scaling, vocabulary};
+use crate::{StatsSummary, WordFollowEntry};
+
+/// Identifies a model in the summary by its title and n
+#[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize)]
+pub struct ModelKey {
+    pub title: String,
+    pub n: usize,
+}
+
+/// Comparison of one model (`a`) against another (`b`)
+#[derive(Debug, Clone, Serialize)]
+pub struct ModelComparison {
+    pub a: ModelKey,
+    pub b: ModelKey,
+    /// Number of tokens found in both vocabularies
+    pub shared_vocabulary: usize,
+    /// Jaccard similarity of the two vocabularies (shared / union)
+    pub vocabulary_overlap: f64,
+    /// Perplexity of model `a` on the n-grams of model `b` (add-one smoothed), if both use the same n
+    pub perplexity: Option<f64>,
+    /// Whether the perplexity was computed from counts scaled for d10 dice, and so is only
+    /// an approximation of the perplexity on the raw counts
+    pub approximate: bool,
+}
+
+/// Summary of a set of models, as read by `summary.typ`
+#[derive(Debug, Clone, Serialize)]
+pub struct Summary {
+    pub models: Vec<StatsSummary>,
+    pub comparisons: Vec<ModelComparison>,
+}
+
+/// Summarises a set of loaded models.
+///
+/// Files sharing a title and n (e.g. the books of a split model) are treated as one model.
+pub fn summarize(models: &[Model]) -> io::Result<Summary> {
+    // Each model's key, statistics, entries and whether any of its files were d10-scaled
+    let mut grouped: Vec<(ModelKey, StatsSummary, Vec<WordFollowEntry>, bool)> = Vec::new();
+    let mut index_by_key: HashMap<ModelKey, usize> = HashMap::new();
+
+    for model in models {
+        let key = ModelKey {
+            title: model.metadata.title.clone(),
+            n: model.metadata.n,
+        };
+
+        match index_by_key.get(&key) {
+            Some(&index) => {
+                grouped[index].2.extend(model.entries.iter().cloned());
+                grouped[index].3 |= model.scaling == Scaling::D10;
+            }
+            None => {
+                let stats = model.metadata.stats.as_ref().ok_or_else(|| {
+                    io::Error::new(
+                        io::ErrorKind::InvalidData,
+                        format!("{} has no statistics in its metadata", model.path.display()),
+                    )
+                })?;
+                index_by_key.insert(key.clone(), grouped.len());
+                grouped.push((
+                    key,
+                    StatsSummary::new(Some(&model.metadata), model.metadata.n, stats),
+                    model.entries.clone(),
+                    model.scaling == Scaling::D10,
+                ));
+            }
+        }
+    }
+
+    grouped.sort_by(|a, b| a.0.cmp(&b.0));
+
+    let vocabularies: Vec<BTreeSet<String>> = grouped
+        .iter()
+        .map(|(_, _, entries, _)| vocabulary(entries))
+        .collect();
+
+    let mut comparisons = Vec::new();
+    for (i, (key_a, _, entries_a, scaled_a)) in grouped.iter().enumerate() {
+        for (j, (key_b, _, entries_b, scaled_b)) in grouped.iter().enumerate() {
+            if i == j {
+                continue;
+            }
+
+            let shared = vocabularies[i].intersection(&vocabularies[j]).count();
+            let union = vocabularies[i].union(&vocabularies[j]).count();
+            let perplexity = (key_a.n == key_b.n).then(|| perplexity(entries_a, entries_b, union));
+
+            comparisons.push(ModelComparison {
+                a: key_a.clone(),
+                b: key_b.clone(),
+                shared_vocabulary: shared,
+                vocabulary_overlap: if union == 0 {
+                    0.0
+                } else {
+                    shared as f64 / union as f64
+                },
+                perplexity,
+                approximate: perplexity.is_some() && (*scaled_a || *scaled_b),
+            });
+        }
+    }
+
+    Ok(Summary {
+        models: grouped
+            .into_iter()
+            .map(|(_, summary, _, _)| summary)
+            .collect(),
+        comparisons,
+    })
+}
+
+/// Perplexity of `model` on the n-gram counts in `test`, using add-one smoothing over `vocabulary_size` words
+pub fn perplexity(
+    model: &[WordFollowEntry],
+    test: &[WordFollowEntry],
+    vocabulary_size: usize,
+) -> f64 {
+    let lookup: HashMap<&[String], &WordFollowEntry> = model
+        .iter()
+        .map(|entry| (entry.prefix.as_slice(), entry))
+        .collect();
+    let vocabulary_size = vocabulary_size.max(1) as f64;
+
+    let mut log_prob_sum = 0.0;
+    let mut total = 0usize;
+
+    for entry in test {
+        let model_entry = lookup.get(entry.prefix.as_slice());
+        let prefix_total: usize = model_entry
+            .map(|e| e.followers.iter().map(|(_, c)| *c).sum())
+            .unwrap_or(0);
+
+        for (word, count) in &entry.followers {
+            let follower_count = model_entry
+                .and_then(|e| e.followers.iter().find(|(w, _)| w == word))
+                .map(|(_, c)| *c)
+                .unwrap_or(0);
+            let p = (follower_count as f64 + 1.0) / (prefix_total as f64 + vocabulary_size);
+            log_prob_sum += *count as f64 * p.log2();
+            total += count;
+        }
+    }
+
+    if total == 0 {
+        return 1.0;
+    }
+
+    2f64.powf(-log_prob_sum / total as f64)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(prefix: &str, followers: &[(&str, usize)]) -> WordFollowEntry {
+        WordFollowEntry {
+            prefix: vec![prefix.to_string()],
+            followers: followers.iter().map(|(w, c)| (w.to_string(), *c)).collect(),
+        }
+    }
+
+    #[test]
+    fn perplexity_is_lower_on_own_data() {
+        let a = vec![
+            entry("see", &[("spot", 2)]),
+            entry("spot", &[("run", 1), ("jump", 1)]),
+        ];
+        let b = vec![entry("see", &[("jane", 2)]), entry("jane", &[("run", 2)])];
+        let vocab = vocabulary(&a).union(&vocabulary(&b)).count();
+
+        assert!(perplexity(&a, &a, vocab) < perplexity(&a, &b, vocab));
+    }
+}
@@ -0,0 +1,77 @@
+use std::io;
+use std::path::{Path, PathBuf};
+
+/// The typst templates built into the binary, and the files they use
+pub const TEMPLATE_FILES: &[(&str, &[u8])] = &[
+    ("book.typ", include_bytes!("../book.typ")),
+    ("index.typ", include_bytes!("../index.typ")),
+    ("grid.typ", include_bytes!("../grid.typ")),
+    ("summary.typ", include_bytes!("../summary.typ")),
+    ("socy-logo-bw.svg", include_bytes!("../socy-logo-bw.svg")),
+];
+
+/// The contents of a bundled template file, e.g. "book.typ"
+pub fn template_file(name: &str) -> Option<&'static [u8]> {
+    TEMPLATE_FILES
+        .iter()
+        .find(|(file, _)| *file == name)
+        .map(|(_, contents)| *contents)
+}
+
+/// Writes the bundled templates to `dir` (creating it if needed), e.g. to customise a copy.
+///
+/// Existing files are only replaced if `overwrite` is set; otherwise nothing is written if any
+/// of them exist.
+pub fn export_templates(dir: &Path, overwrite: bool) -> io::Result<Vec<PathBuf>> {
+    let paths: Vec<PathBuf> = TEMPLATE_FILES
+        .iter()
+        .map(|(name, _)| dir.join(name))
+        .collect();
+    if !overwrite && let Some(existing) = paths.iter().find(|path| path.exists()) {
+        return Err(io::Error::new(
+            io::ErrorKind::AlreadyExists,
+            format!("{} already exists", existing.display()),
+        ));
+    }
+
+    std::fs::create_dir_all(dir)?;
+    for (path, (_, contents)) in paths.iter().zip(TEMPLATE_FILES) {
+        std::fs::write(path, contents)?;
+    }
+    Ok(paths)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn exports_without_overwriting() -> io::Result<()> {
+        let dir = TempDir::new()?;
+        let templates = dir.path().join("templates");
+        let written = export_templates(&templates, false)?;
+        assert_eq!(written.len(), TEMPLATE_FILES.len());
+        assert_eq!(
+            std::fs::read(templates.join("book.typ"))?,
+            template_file("book.typ").unwrap()
+        );
+        assert!(template_file("missing.typ").is_none());
+
+        // A customised copy is kept unless overwriting
+        std::fs::write(templates.join("book.typ"), "// custom\n")?;
+        let err = export_templates(&templates, false).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::AlreadyExists);
+        assert_eq!(
+            std::fs::read_to_string(templates.join("book.typ"))?,
+            "// custom\n"
+        );
+        export_templates(&templates, true)?;
+        assert_eq!(
+            std::fs::read(templates.join("book.typ"))?,
+            template_file("book.typ").unwrap()
+        );
+
+        Ok(())
+    }
+}
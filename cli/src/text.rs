@@ -35,6 +35,10 @@ impl Normalizer {
         Self { config }
     }
 
+    pub fn config(&self) -> &NormalizerConfig {
+        &self.config
+    }
+
     pub fn normalize_line(&self, line: &str) -> Vec<String> {
         let mut tokens = Vec::new();
         let mut current = String::new();
@@ -0,0 +1,105 @@
+use serde::Serialize;
+use std::io::{self, Write};
+
+/// One update to the n-gram counts, in the order training happens by hand
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct TraceStep {
+    /// Step number, starting at 1
+    pub step: usize,
+    /// Position of the follower in the preprocessed token stream, starting at 1
+    pub token_index: usize,
+    /// The prefix words (the row of the grid)
+    pub prefix: Vec<String>,
+    /// The word that followed the prefix (the column of the grid)
+    pub follower: String,
+    /// Count for this prefix/follower pair after the update
+    pub count: usize,
+}
+
+/// Writes each trace step as one JSON object per line
+pub fn write_trace_jsonl<W: Write>(steps: &[TraceStep], writer: &mut W) -> io::Result<()> {
+    for step in steps {
+        serde_json::to_writer(&mut *writer, step)?;
+        writeln!(writer)?;
+    }
+    Ok(())
+}
+
+/// Writes the trace as a plain-text table with aligned columns, for printing
+pub fn write_trace_table<W: Write>(steps: &[TraceStep], writer: &mut W) -> io::Result<()> {
+    let header = ["step", "token", "prefix", "follower", "count"].map(String::from);
+    let rows: Vec<[String; 5]> = steps
+        .iter()
+        .map(|step| {
+            [
+                step.step.to_string(),
+                step.token_index.to_string(),
+                step.prefix.join(" "),
+                step.follower.clone(),
+                step.count.to_string(),
+            ]
+        })
+        .collect();
+
+    let mut widths = header.clone().map(|h| h.chars().count());
+    for row in &rows {
+        for (width, cell) in widths.iter_mut().zip(row) {
+            *width = (*width).max(cell.chars().count());
+        }
+    }
+
+    for row in std::iter::once(&header).chain(&rows) {
+        let line = row
+            .iter()
+            .zip(widths)
+            .map(|(cell, width)| format!("{:<width$}", cell, width = width))
+            .collect::<Vec<_>>()
+            .join("  ");
+        writeln!(writer, "{}", line.trim_end())?;
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::NGramCounter;
+
+    #[test]
+    fn records_each_update_in_order() {
+        let mut counter = NGramCounter::new(2, vec![',', '.']).unwrap();
+        counter.enable_trace(Some(4));
+        counter.process_line("See Spot run.");
+        counter.process_line("See Spot jump.");
+
+        let trace = counter.get_trace();
+        assert_eq!(trace.len(), 4);
+        assert_eq!(trace[0].prefix, vec!["see"]);
+        assert_eq!(trace[0].follower, "spot");
+        assert_eq!(trace[0].token_index, 2);
+        // The window carries over between lines: "." is followed by "see"
+        assert_eq!(trace[3].prefix, vec!["."]);
+        assert_eq!(trace[3].follower, "see");
+        assert_eq!(trace[3].token_index, 5);
+
+        let mut table = Vec::new();
+        write_trace_table(trace, &mut table).unwrap();
+        let table = String::from_utf8(table).unwrap();
+        assert_eq!(
+            table.lines().next(),
+            Some("step  token  prefix  follower  count")
+        );
+        assert_eq!(
+            table.lines().nth(4),
+            Some("4     5      .       see       1")
+        );
+
+        // The second "see spot" pair brings that count up to 2
+        let mut counter = NGramCounter::new(2, vec![',', '.']).unwrap();
+        counter.enable_trace(None);
+        counter.process_line("See Spot run. See Spot jump.");
+        assert_eq!(counter.get_trace().len(), 7);
+        assert_eq!(counter.get_trace()[4].count, 2);
+    }
+}
@@ -0,0 +1,371 @@
+use std::fmt;
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+use std::time::{SystemTime, UNIX_EPOCH};
+use typst::diag::{FileError, FileResult, Severity, SourceDiagnostic, Warned};
+use typst::foundations::{Bytes, Datetime, Dict, IntoValue};
+use typst::syntax::{FileId, Source, VirtualPath};
+use typst::text::{Font, FontBook};
+use typst::utils::LazyHash;
+use typst::{Library, World};
+use typst_pdf::{PdfOptions, Timestamp};
+
+use crate::templates::template_file;
+
+/// A PDF compiled by [`typeset_pdf`], with any warnings typst gave
+#[derive(Debug, Clone)]
+pub struct Typeset {
+    pub pdf: Vec<u8>,
+    pub warnings: Vec<TypstDiagnostic>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DiagnosticSeverity {
+    Error,
+    Warning,
+}
+
+/// An error or warning from typst, located in the file that caused it (if any)
+#[derive(Debug, Clone, PartialEq)]
+pub struct TypstDiagnostic {
+    pub severity: DiagnosticSeverity,
+    pub message: String,
+    /// The file, relative to the project root
+    pub file: Option<PathBuf>,
+    /// 1-based line and column
+    pub line: Option<usize>,
+    pub column: Option<usize>,
+    pub hints: Vec<String>,
+}
+
+/// The errors that stopped a template from compiling
+#[derive(Debug, Clone, PartialEq)]
+pub struct TypesetError {
+    pub template: PathBuf,
+    pub diagnostics: Vec<TypstDiagnostic>,
+}
+
+impl fmt::Display for TypstDiagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if let Some(file) = &self.file {
+            write!(f, "{}", file.display())?;
+            if let (Some(line), Some(column)) = (self.line, self.column) {
+                write!(f, ":{}:{}", line, column)?;
+            }
+            write!(f, ": ")?;
+        }
+        let severity = match self.severity {
+            DiagnosticSeverity::Error => "error",
+            DiagnosticSeverity::Warning => "warning",
+        };
+        write!(f, "{}: {}", severity, self.message)?;
+        for hint in &self.hints {
+            write!(f, "\n  hint: {}", hint)?;
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Display for TypesetError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Typst compile failed for {}", self.template.display())?;
+        for diagnostic in &self.diagnostics {
+            write!(f, "\n{}", diagnostic)?;
+        }
+        Ok(())
+    }
+}
+
+impl std::error::Error for TypesetError {}
+
+/// Compiles `template` to PDF in-process, with `inputs` available through `sys.inputs`.
+///
+/// Files are read relative to `root` (which must contain the template), falling back to the
+/// bundled templates. Fonts are the ones bundled with the typst CLI (including Libertinus Serif)
+/// plus any found in the directories listed in `TYPST_FONT_PATHS`.
+pub fn typeset_pdf(
+    template: &Path,
+    root: &Path,
+    inputs: &[(&str, String)],
+) -> Result<Typeset, TypesetError> {
+    let error = |message: String| TypesetError {
+        template: template.to_path_buf(),
+        diagnostics: vec![TypstDiagnostic {
+            severity: DiagnosticSeverity::Error,
+            message,
+            file: None,
+            line: None,
+            column: None,
+            hints: Vec::new(),
+        }],
+    };
+
+    let template_dir = match template.parent() {
+        Some(dir) if !dir.as_os_str().is_empty() => dir,
+        _ => Path::new("."),
+    };
+    let template_dir = template_dir
+        .canonicalize()
+        .map_err(|e| error(format!("Error finding {}: {}", template_dir.display(), e)))?;
+    let file_name = template
+        .file_name()
+        .ok_or_else(|| error(format!("{} is not a file", template.display())))?;
+    let main_path =
+        VirtualPath::within_root(&template_dir.join(file_name), root).ok_or_else(|| {
+            error(format!(
+                "{} is outside {}",
+                template.display(),
+                root.display()
+            ))
+        })?;
+
+    let inputs: Dict = inputs
+        .iter()
+        .map(|(key, value)| ((*key).into(), value.as_str().into_value()))
+        .collect();
+    let world = TypesetWorld {
+        root: root.to_path_buf(),
+        template_dir,
+        main: FileId::new(None, main_path),
+        library: LazyHash::new(Library::builder().with_inputs(inputs).build()),
+        fonts: fonts(),
+    };
+
+    let failed = |errors: &[SourceDiagnostic]| TypesetError {
+        template: template.to_path_buf(),
+        diagnostics: errors.iter().map(|d| world.diagnostic(d)).collect(),
+    };
+    let Warned { output, warnings } = typst::compile(&world);
+    let document = output.map_err(|errors| failed(&errors))?;
+    let options = PdfOptions {
+        timestamp: world.today(Some(0)).map(Timestamp::new_utc),
+        ..Default::default()
+    };
+    let pdf = typst_pdf::pdf(&document, &options).map_err(|errors| failed(&errors))?;
+
+    Ok(Typeset {
+        pdf,
+        warnings: warnings.iter().map(|d| world.diagnostic(d)).collect(),
+    })
+}
+
+/// The fonts typst can use, loaded once and shared by every compile
+struct Fonts {
+    book: LazyHash<FontBook>,
+    fonts: Vec<Font>,
+}
+
+fn fonts() -> &'static Fonts {
+    static FONTS: OnceLock<Fonts> = OnceLock::new();
+    FONTS.get_or_init(|| {
+        let mut fonts: Vec<Font> = typst_assets::fonts()
+            .flat_map(|data| Font::iter(Bytes::new(data)))
+            .collect();
+        if let Some(paths) = std::env::var_os("TYPST_FONT_PATHS") {
+            for dir in std::env::split_paths(&paths) {
+                load_font_dir(&dir, &mut fonts);
+            }
+        }
+        Fonts {
+            book: LazyHash::new(FontBook::from_fonts(&fonts)),
+            fonts,
+        }
+    })
+}
+
+fn load_font_dir(dir: &Path, fonts: &mut Vec<Font>) {
+    let Ok(entries) = std::fs::read_dir(dir) else {
+        return;
+    };
+    for path in entries.flatten().map(|entry| entry.path()) {
+        if path.is_dir() {
+            load_font_dir(&path, fonts);
+            continue;
+        }
+        let is_font = path.extension().and_then(|e| e.to_str()).is_some_and(|e| {
+            matches!(
+                e.to_ascii_lowercase().as_str(),
+                "ttf" | "otf" | "ttc" | "otc"
+            )
+        });
+        if is_font && let Ok(data) = std::fs::read(&path) {
+            fonts.extend(Font::iter(Bytes::new(data)));
+        }
+    }
+}
+
+struct TypesetWorld {
+    root: PathBuf,
+    template_dir: PathBuf,
+    main: FileId,
+    library: LazyHash<Library>,
+    fonts: &'static Fonts,
+}
+
+impl TypesetWorld {
+    fn read(&self, id: FileId) -> FileResult<Bytes> {
+        if id.package().is_some() {
+            return Err(FileError::Other(Some(
+                "packages are not available when compiling in-process".into(),
+            )));
+        }
+        let path = id
+            .vpath()
+            .resolve(&self.root)
+            .ok_or(FileError::AccessDenied)?;
+        match std::fs::read(&path) {
+            Ok(data) => Ok(Bytes::new(data)),
+            Err(e) => {
+                // Templates missing from the template directory are served from the bundled ones
+                let bundled = (path.parent() == Some(self.template_dir.as_path()))
+                    .then(|| path.file_name().and_then(|name| name.to_str()))
+                    .flatten()
+                    .and_then(template_file);
+                match bundled {
+                    Some(data) => Ok(Bytes::new(data)),
+                    None if path.is_dir() => Err(FileError::IsDirectory),
+                    None => Err(FileError::from_io(e, &path)),
+                }
+            }
+        }
+    }
+
+    fn diagnostic(&self, diagnostic: &SourceDiagnostic) -> TypstDiagnostic {
+        let mut located = TypstDiagnostic {
+            severity: match diagnostic.severity {
+                Severity::Error => DiagnosticSeverity::Error,
+                Severity::Warning => DiagnosticSeverity::Warning,
+            },
+            message: diagnostic.message.to_string(),
+            file: None,
+            line: None,
+            column: None,
+            hints: diagnostic.hints.iter().map(|h| h.to_string()).collect(),
+        };
+        if let Some(id) = diagnostic.span.id() {
+            located.file = Some(id.vpath().as_rootless_path().to_path_buf());
+            if let Ok(source) = self.source(id)
+                && let Some(range) = source.range(diagnostic.span)
+            {
+                located.line = source.byte_to_line(range.start).map(|line| line + 1);
+                located.column = source.byte_to_column(range.start).map(|column| column + 1);
+            }
+        }
+        located
+    }
+}
+
+impl World for TypesetWorld {
+    fn library(&self) -> &LazyHash<Library> {
+        &self.library
+    }
+
+    fn book(&self) -> &LazyHash<FontBook> {
+        &self.fonts.book
+    }
+
+    fn main(&self) -> FileId {
+        self.main
+    }
+
+    fn source(&self, id: FileId) -> FileResult<Source> {
+        let bytes = self.read(id)?;
+        let text = std::str::from_utf8(&bytes).map_err(|_| FileError::InvalidUtf8)?;
+        Ok(Source::new(
+            id,
+            text.trim_start_matches('\u{feff}').to_string(),
+        ))
+    }
+
+    fn file(&self, id: FileId) -> FileResult<Bytes> {
+        self.read(id)
+    }
+
+    fn font(&self, index: usize) -> Option<Font> {
+        self.fonts.fonts.get(index).cloned()
+    }
+
+    fn today(&self, offset: Option<i64>) -> Option<Datetime> {
+        let seconds = SystemTime::now().duration_since(UNIX_EPOCH).ok()?.as_secs() as i64;
+        let days = (seconds + offset.unwrap_or(0) * 3600).div_euclid(86_400);
+        let (year, month, day) = civil_from_days(days);
+        Datetime::from_ymd(year, month, day)
+    }
+}
+
+/// The (proleptic Gregorian) date `days` after 1970-01-01
+fn civil_from_days(days: i64) -> (i32, u8, u8) {
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let day_of_era = z.rem_euclid(146_097);
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let mp = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = year_of_era + era * 400 + i64::from(month <= 2);
+    (year as i32, month as u8, day as u8)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn compiles_templates_with_inputs_and_reports_errors() -> std::io::Result<()> {
+        let dir = TempDir::new()?;
+        let root = dir.path().canonicalize()?;
+        std::fs::write(root.join("data.json"), r#"{"word": "spot"}"#)?;
+        let template = root.join("test.typ");
+        std::fs::write(
+            &template,
+            "#set text(font: \"Libertinus Serif\")\n\
+             #let data = json(sys.inputs.at(\"data_path\"))\n\
+             = #sys.inputs.at(\"title\") \n See #data.word run.\n",
+        )?;
+
+        let inputs = [
+            ("title", "Test".to_string()),
+            ("data_path", "/data.json".to_string()),
+        ];
+        let typeset = typeset_pdf(&template, &root, &inputs).expect("template compiles");
+        assert!(typeset.pdf.starts_with(b"%PDF"));
+        // Including no unknown font warning: Libertinus Serif is built in, as in the typst CLI
+        assert!(typeset.warnings.is_empty(), "{:?}", typeset.warnings);
+
+        // A missing input is reported at its line in the template
+        let err = typeset_pdf(&template, &root, &inputs[..1]).unwrap_err();
+        assert_eq!(err.diagnostics.len(), 1);
+        let diagnostic = &err.diagnostics[0];
+        assert_eq!(diagnostic.severity, DiagnosticSeverity::Error);
+        assert_eq!(diagnostic.file, Some(PathBuf::from("test.typ")));
+        assert_eq!(diagnostic.line, Some(2));
+        assert!(diagnostic.message.contains("data_path"), "{}", diagnostic);
+        assert!(err.to_string().contains("test.typ:2:"), "{}", err);
+
+        // The bundled templates stand in for missing ones
+        std::fs::write(
+            root.join("index.json"),
+            r#"{"title": "Spot", "books": 2, "ranges": [{"from": "run", "to": "see", "books": [1]}, {"from": "spot", "to": "spot", "books": [2]}], "words": {}}"#,
+        )?;
+        let index = typeset_pdf(
+            &root.join("index.typ"),
+            &root,
+            &[("index_path", "/index.json".to_string())],
+        )
+        .expect("bundled index template compiles");
+        assert!(index.pdf.starts_with(b"%PDF"));
+
+        Ok(())
+    }
+
+    #[test]
+    fn converts_days_to_dates() {
+        assert_eq!(civil_from_days(0), (1970, 1, 1));
+        assert_eq!(civil_from_days(59), (1970, 3, 1));
+        assert_eq!(civil_from_days(20_742), (2026, 10, 16));
+        assert_eq!(civil_from_days(-1), (1969, 12, 31));
+    }
+}
@@ -8,7 +8,8 @@
   subtitle: "Summary of all generated language model books",
 )
 
-#let summary_data = json("out/summary.json")
+#let summary = json(sys.inputs.at("summary_path", default: "out/summary.json"))
+#let summary_data = summary.models
 
 #let model-type(n) = {
   if n == 2 {
@@ -48,6 +49,14 @@
   }
 }
 
+#let format-decimal(num, digits: 2) = {
+  if num == none {
+    "N/A"
+  } else {
+    str(calc.round(num, digits: digits))
+  }
+}
+
 #let format-prefix(prefix_data) = {
   if prefix_data == none {
     "N/A"
@@ -79,3 +88,72 @@
     ))
     .flatten(),
 )
+
+== Corpus statistics
+
+#table(
+  columns: (2fr, 1fr, 1fr, 1fr, 1fr, 1fr, 1fr, 1fr),
+  table.header(
+    [*Title*],
+    [*Type*],
+    align(right, [*Vocabulary*]),
+    align(right, [*Type/Token*]),
+    align(right, [*Hapax*]),
+    align(right, [*Zipf Slope*]),
+    align(right, [*Followers (mean/max)*]),
+    align(right, [*Entropy (bits)*]),
+  ),
+  ..summary_data
+    .map(entry => (
+      entry.title,
+      model-type(entry.n),
+      align(right, format-number(entry.at("vocabulary_size", default: none))),
+      align(right, format-decimal(entry.at("type_token_ratio", default: none), digits: 3)),
+      align(right, format-number(entry.at("hapax_legomena", default: none))),
+      align(right, format-decimal(entry.at("zipf_slope", default: none))),
+      align(right, {
+        let branching = entry.at("branching", default: none)
+        if branching == none {
+          "N/A"
+        } else {
+          [#format-decimal(branching.mean) / #branching.max]
+        }
+      }),
+      align(right, {
+        let entropy = entry.at("entropy", default: none)
+        if entropy == none { "N/A" } else { format-decimal(entropy.weighted_mean) }
+      }),
+    ))
+    .flatten(),
+)
+
+== Model comparisons
+
+Vocabulary overlap is the proportion of the combined vocabulary shared by both
+models. Perplexity measures how surprised model A is by the n-grams of model B
+(lower means B looks more like A's training text); values marked ≈ were computed
+from counts scaled for the dice and are only approximate.
+
+#table(
+  columns: (2fr, 2fr, 1fr, 1fr, 1fr),
+  table.header(
+    [*Model A*],
+    [*Model B*],
+    align(right, [*Shared Words*]),
+    align(right, [*Vocabulary Overlap*]),
+    align(right, [*Perplexity*]),
+  ),
+  ..summary
+    .comparisons
+    .map(c => (
+      [#c.a.title (#model-type(c.a.n))],
+      [#c.b.title (#model-type(c.b.n))],
+      align(right, format-number(c.shared_vocabulary)),
+      align(right, format-decimal(c.vocabulary_overlap, digits: 3)),
+      align(right, {
+        if c.at("approximate", default: false) [≈]
+        format-decimal(c.perplexity, digits: 1)
+      }),
+    ))
+    .flatten(),
+)
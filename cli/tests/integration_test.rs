@@ -1,9 +1,25 @@
 use std::fs::File;
 use std::io::{self, BufReader, Write};
-use std::path::Path; // Import Path
+use std::path::{Path, PathBuf};
 use std::process::Command;
 use tempfile::TempDir;
 
+/// The CLI binary cargo built for these tests
+fn cli_exe() -> PathBuf {
+    PathBuf::from(env!("CARGO_BIN_EXE_llms_unplugged"))
+}
+
+/// Writes a corpus file with the frontmatter the CLI requires
+fn write_corpus(path: &Path, title: &str, text: &str) -> io::Result<()> {
+    let mut file = File::create(path)?;
+    writeln!(file, "---")?;
+    writeln!(file, "title: {}", title)?;
+    writeln!(file, "author: Test Author")?;
+    writeln!(file, "url: https://test.com")?;
+    writeln!(file, "---")?;
+    writeln!(file, "{}", text)
+}
+
 // Helper function to run the full pipeline for a given n
 fn run_cli_and_typst_test(n: usize, exe_path: &Path, temp_dir: &TempDir) -> io::Result<()> {
     let input_path = temp_dir.path().join(format!("input_n{}.txt", n));
@@ -95,23 +111,7 @@ fn test_frontmatter_errors() -> io::Result<()> {
     // Create a temporary directory
     let temp_dir = TempDir::new()?;
 
-    // Get the path to the binary
-    let mut exe_path = std::env::current_dir()?;
-    exe_path.push("target");
-    exe_path.push("debug");
-    exe_path.push("llms_unplugged");
-    if cfg!(windows) {
-        exe_path.set_extension("exe");
-    }
-
-    // Skip if binary doesn't exist
-    if !exe_path.exists() {
-        println!(
-            "Skipping test_frontmatter_errors: Binary not found at {:?}",
-            exe_path
-        );
-        return Ok(());
-    }
+    let exe_path = cli_exe();
 
     // Test 1: Missing frontmatter completely
     {
@@ -289,21 +289,7 @@ fn test_cli_raw_flag() -> io::Result<()> {
     let output_path_raw = temp_dir.path().join("output_raw.json");
     let output_path_scaled = temp_dir.path().join("output_scaled.json");
 
-    // Get the path to the binary
-    let mut exe_path = std::env::current_dir()?;
-    exe_path.push("target");
-    exe_path.push("debug");
-    exe_path.push("llms_unplugged");
-
-    if cfg!(windows) {
-        exe_path.set_extension("exe");
-    }
-
-    // Skip the test if the binary doesn't exist
-    if !exe_path.exists() {
-        println!("Skipping test: Binary not found at {:?}", exe_path);
-        return Ok(());
-    }
+    let exe_path = cli_exe();
 
     // Run with --raw flag
     let status_raw = Command::new(&exe_path)
@@ -385,21 +371,7 @@ fn test_cli_incompatible_flags() -> io::Result<()> {
     writeln!(input_file, "Test text.")?;
     input_file.flush()?;
 
-    // Get the path to the binary
-    let mut exe_path = std::env::current_dir()?;
-    exe_path.push("target");
-    exe_path.push("debug");
-    exe_path.push("llms_unplugged");
-
-    if cfg!(windows) {
-        exe_path.set_extension("exe");
-    }
-
-    // Skip if binary doesn't exist
-    if !exe_path.exists() {
-        println!("Skipping test: Binary not found at {:?}", exe_path);
-        return Ok(());
-    }
+    let exe_path = cli_exe();
 
     // Test that --raw flag works
     let output = Command::new(&exe_path)
@@ -417,6 +389,7 @@ fn test_cli_incompatible_flags() -> io::Result<()> {
 }
 
 #[test]
+#[allow(clippy::needless_range_loop)]
 fn test_cli_end_to_end() -> io::Result<()> {
     // Create a temporary directory for test files
     let temp_dir = TempDir::new()?;
@@ -439,22 +412,7 @@ fn test_cli_end_to_end() -> io::Result<()> {
     // Create path for the output file
     let output_path = temp_dir.path().join("output.json"); // For default d10 scaling
 
-    // Get the path to the binary directory
-    let mut exe_path = std::env::current_dir()?;
-    exe_path.push("target");
-    exe_path.push("debug");
-    exe_path.push("llms_unplugged"); // Add the binary name
-
-    // On Windows, add .exe extension
-    if cfg!(windows) {
-        exe_path.set_extension("exe");
-    }
-
-    // Skip the test if the binary doesn't exist yet
-    if !exe_path.exists() {
-        println!("Skipping test: Binary not found at {:?}", exe_path);
-        return Ok(());
-    }
+    let exe_path = cli_exe();
 
     // Run CLI with default d10 scaling
     let status = Command::new(&exe_path)
@@ -708,23 +666,7 @@ fn test_cli_to_typst_pdf() -> io::Result<()> {
     // Create a temporary directory
     let temp_dir = TempDir::new()?;
 
-    // Get the path to the binary
-    let mut exe_path = std::env::current_dir()?;
-    exe_path.push("target");
-    exe_path.push("debug");
-    exe_path.push("llms_unplugged");
-    if cfg!(windows) {
-        exe_path.set_extension("exe");
-    }
-
-    // Skip if binary doesn't exist
-    if !exe_path.exists() {
-        println!(
-            "Skipping test_cli_to_typst_pdf: Binary not found at {:?}",
-            exe_path
-        );
-        return Ok(());
-    }
+    let exe_path = cli_exe();
 
     // Skip if typst command is not found
     if Command::new("typst").arg("--version").output().is_err() {
@@ -744,3 +686,1544 @@ fn test_cli_to_typst_pdf() -> io::Result<()> {
 
     Ok(())
 }
+
+#[test]
+fn test_cli_stats_json() -> io::Result<()> {
+    let temp_dir = TempDir::new()?;
+
+    let input_path = temp_dir.path().join("input.txt");
+    write_corpus(&input_path, "Stats Test", "See Spot run. See Spot jump.")?;
+
+    let exe_path = cli_exe();
+
+    let stats_path = temp_dir.path().join("stats.json");
+    let status = Command::new(&exe_path)
+        .arg(&input_path)
+        .arg("-o")
+        .arg(temp_dir.path().join("model.json"))
+        .arg("--stats-json")
+        .arg(&stats_path)
+        .status()?;
+    assert!(status.success(), "CLI command with --stats-json failed");
+
+    let stats: serde_json::Value =
+        serde_json::from_reader(BufReader::new(File::open(&stats_path)?))?;
+
+    // see spot run . see spot jump .
+    assert_eq!(stats["title"], serde_json::json!("Stats Test"));
+    assert_eq!(stats["n"], serde_json::json!(2));
+    assert_eq!(stats["total_tokens"], serde_json::json!(8));
+    assert_eq!(stats["unique_prefixes"], serde_json::json!(5));
+    assert_eq!(stats["vocabulary_size"], serde_json::json!(5));
+    assert_eq!(stats["hapax_legomena"], serde_json::json!(2));
+    assert_eq!(stats["branching"]["max"], serde_json::json!(2));
+    assert_eq!(stats["top_tokens"][0], serde_json::json!([".", 2]));
+
+    Ok(())
+}
+
+#[test]
+fn test_cli_summarize() -> io::Result<()> {
+    let temp_dir = TempDir::new()?;
+
+    let exe_path = cli_exe();
+
+    let input_path = temp_dir.path().join("input.txt");
+    write_corpus(
+        &input_path,
+        "Summary Test",
+        "The cat sat on the mat. The dog sat on the log.",
+    )?;
+
+    // A single-book bigram model and a two-book trigram model
+    let status = Command::new(&exe_path)
+        .arg(&input_path)
+        .arg("-o")
+        .arg(temp_dir.path().join("bigram.json"))
+        .status()?;
+    assert!(status.success(), "CLI command for bigram model failed");
+    let status = Command::new(&exe_path)
+        .arg(&input_path)
+        .args(["-n", "3", "-b", "2", "-o"])
+        .arg(temp_dir.path().join("trigram.json"))
+        .status()?;
+    assert!(status.success(), "CLI command for trigram books failed");
+
+    // As with `summarize json/*.json`, the split model's manifest and index are skipped
+    let summary_path = temp_dir.path().join("summary.json");
+    let output = Command::new(&exe_path)
+        .arg("summarize")
+        .arg(temp_dir.path().join("bigram.json"))
+        .arg(temp_dir.path().join("trigram_book_1.json"))
+        .arg(temp_dir.path().join("trigram_book_2.json"))
+        .arg(temp_dir.path().join("trigram_index.json"))
+        .arg(temp_dir.path().join("trigram_manifest.json"))
+        .arg("-o")
+        .arg(&summary_path)
+        .output()?;
+    assert!(
+        output.status.success(),
+        "summarize failed: {}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+
+    let summary: serde_json::Value =
+        serde_json::from_reader(BufReader::new(File::open(&summary_path)?))?;
+
+    // The two trigram books are summarised as a single model
+    let models = summary["models"].as_array().unwrap();
+    assert_eq!(models.len(), 2);
+    assert_eq!(models[0]["n"], serde_json::json!(2));
+    assert_eq!(models[1]["n"], serde_json::json!(3));
+    assert_eq!(models[0]["total_tokens"], serde_json::json!(14));
+    assert!(models[0]["unique_prefixes"].is_number());
+    assert!(models[0].get("most_common_ngram").is_some());
+
+    // Both models come from the same text, so the vocabularies overlap completely
+    let comparisons = summary["comparisons"].as_array().unwrap();
+    assert_eq!(comparisons.len(), 2);
+    assert_eq!(comparisons[0]["vocabulary_overlap"], serde_json::json!(1.0));
+    assert!(comparisons[0]["perplexity"].is_null());
+    assert_eq!(comparisons[0]["approximate"], serde_json::json!(false));
+
+    // Perplexities are only exact between raw models
+    let raw_input_path = temp_dir.path().join("raw.txt");
+    write_corpus(
+        &raw_input_path,
+        "Raw Test",
+        "The cat sat on the log. The dog sat on the mat.",
+    )?;
+    for (input, name) in [(&raw_input_path, "raw_a.json"), (&input_path, "raw_b.json")] {
+        let status = Command::new(&exe_path)
+            .arg(input)
+            .args(["--raw", "-o"])
+            .arg(temp_dir.path().join(name))
+            .status()?;
+        assert!(status.success(), "CLI command for {} failed", name);
+    }
+    for (other, approximate) in [("raw_b.json", false), ("bigram.json", true)] {
+        let output = Command::new(&exe_path)
+            .arg("summarize")
+            .arg(temp_dir.path().join("raw_a.json"))
+            .arg(temp_dir.path().join(other))
+            .arg("-o")
+            .arg(&summary_path)
+            .output()?;
+        assert!(output.status.success());
+        let summary: serde_json::Value =
+            serde_json::from_reader(BufReader::new(File::open(&summary_path)?))?;
+        let comparison = &summary["comparisons"][0];
+        assert!(comparison["perplexity"].is_number());
+        assert_eq!(comparison["approximate"], serde_json::json!(approximate));
+    }
+
+    // Non-model input produces an error rather than a silently broken summary
+    let output = Command::new(&exe_path)
+        .arg("summarize")
+        .arg(&input_path)
+        .output()?;
+    assert!(!output.status.success());
+
+    Ok(())
+}
+
+#[test]
+fn test_cli_grid() -> io::Result<()> {
+    let temp_dir = TempDir::new()?;
+
+    let exe_path = cli_exe();
+
+    let grid_path = temp_dir.path().join("grid.json");
+    let output = Command::new(&exe_path)
+        .args(["grid", "--text", "See Spot run. See Spot jump.", "-o"])
+        .arg(&grid_path)
+        .output()?;
+    assert!(
+        output.status.success(),
+        "grid failed: {}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+
+    let grid: serde_json::Value = serde_json::from_reader(BufReader::new(File::open(&grid_path)?))?;
+    assert_eq!(
+        grid["words"],
+        serde_json::json!(["see", "spot", "run", ".", "jump"])
+    );
+    // "see" is followed by "spot" twice; "." is followed by "see" once
+    assert_eq!(grid["counts"][0], serde_json::json!([0, 2, 0, 0, 0]));
+    assert_eq!(grid["counts"][3], serde_json::json!([1, 0, 0, 0, 0]));
+
+    // Either a file or --text is required
+    let output = Command::new(&exe_path).arg("grid").output()?;
+    assert!(!output.status.success());
+
+    Ok(())
+}
+
+#[test]
+fn test_cli_trace() -> io::Result<()> {
+    let temp_dir = TempDir::new()?;
+
+    let exe_path = cli_exe();
+
+    let input_path = temp_dir.path().join("input.txt");
+    write_corpus(&input_path, "Trace Test", "See Spot run. See Spot jump.")?;
+
+    let trace_path = temp_dir.path().join("trace.jsonl");
+    let status = Command::new(&exe_path)
+        .arg(&input_path)
+        .arg("-o")
+        .arg(temp_dir.path().join("model.json"))
+        .arg("--trace")
+        .arg(&trace_path)
+        .args(["--trace-limit", "5"])
+        .status()?;
+    assert!(status.success(), "CLI command with --trace failed");
+
+    let steps: Vec<serde_json::Value> = std::fs::read_to_string(&trace_path)?
+        .lines()
+        .map(|line| serde_json::from_str(line).unwrap())
+        .collect();
+    assert_eq!(steps.len(), 5);
+    assert_eq!(steps[0]["prefix"], serde_json::json!(["see"]));
+    assert_eq!(steps[0]["follower"], serde_json::json!("spot"));
+    assert_eq!(steps[4]["follower"], serde_json::json!("spot"));
+    assert_eq!(steps[4]["count"], serde_json::json!(2));
+
+    Ok(())
+}
+
+#[test]
+fn test_cli_context_columns() -> io::Result<()> {
+    let temp_dir = TempDir::new()?;
+
+    let exe_path = cli_exe();
+
+    let input_path = temp_dir.path().join("input.txt");
+    write_corpus(&input_path, "Context Test", "I run fast. You run to me.")?;
+
+    let classes_path = temp_dir.path().join("classes.yaml");
+    std::fs::write(&classes_path, "adverb: [fast]\n")?;
+
+    let output_path = temp_dir.path().join("model.json");
+    let status = Command::new(&exe_path)
+        .arg(&input_path)
+        .arg("-o")
+        .arg(&output_path)
+        .args([
+            "--raw",
+            "--context-columns",
+            "--combine-context",
+            "--word-classes",
+        ])
+        .arg(&classes_path)
+        .status()?;
+    assert!(status.success(), "CLI command with context columns failed");
+
+    let json: serde_json::Value =
+        serde_json::from_reader(BufReader::new(File::open(&output_path)?))?;
+    let context = &json["metadata"]["context"];
+    assert_eq!(context["classes"].as_array().unwrap().len(), 4);
+    assert_eq!(context["counts"]["verb"]["to"], serde_json::json!(1));
+    assert_eq!(context["counts"]["adverb"]["."], serde_json::json!(1));
+
+    // "run" is a verb, so its row combines run -> fast/to with the "after verb" column
+    let data = json["data"].as_array().unwrap();
+    let run = data.iter().find(|row| row[0] == "run").unwrap();
+    assert_eq!(run[1], serde_json::json!(4));
+
+    Ok(())
+}
+
+#[test]
+fn test_cli_embed() -> io::Result<()> {
+    let temp_dir = TempDir::new()?;
+
+    let exe_path = cli_exe();
+
+    let input_path = temp_dir.path().join("input.txt");
+    write_corpus(&input_path, "Embedding Test", "See Spot. Spot runs.")?;
+
+    let model_path = temp_dir.path().join("model.json");
+    let status = Command::new(&exe_path)
+        .arg(&input_path)
+        .arg("--raw")
+        .arg("-o")
+        .arg(&model_path)
+        .status()?;
+    assert!(status.success(), "CLI command for model failed");
+
+    let distances_path = temp_dir.path().join("distances.json");
+    let output = Command::new(&exe_path)
+        .arg("embed")
+        .arg(&model_path)
+        .args(["--format", "json", "--nearest", "see", "-k", "1", "-o"])
+        .arg(&distances_path)
+        .output()?;
+    assert!(
+        output.status.success(),
+        "embed failed: {}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    // "see" and "." are both only ever followed by "spot"
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains("Nearest neighbours of 'see'"));
+    assert!(stdout.contains(".                    0.000"));
+
+    let matrix: serde_json::Value =
+        serde_json::from_reader(BufReader::new(File::open(&distances_path)?))?;
+    assert_eq!(matrix["metric"], serde_json::json!("l1"));
+    assert_eq!(
+        matrix["words"],
+        serde_json::json!([".", "runs", "see", "spot"])
+    );
+    assert_eq!(matrix["distances"][2][3], serde_json::json!(3.0));
+
+    // Raw embeddings need raw counts, so a d10-scaled model is refused
+    let scaled_path = temp_dir.path().join("scaled.json");
+    let status = Command::new(&exe_path)
+        .arg(&input_path)
+        .arg("-o")
+        .arg(&scaled_path)
+        .status()?;
+    assert!(status.success());
+    let output = Command::new(&exe_path)
+        .arg("embed")
+        .arg(&scaled_path)
+        .arg("-o")
+        .arg(&distances_path)
+        .output()?;
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("--raw"));
+    let status = Command::new(&exe_path)
+        .arg("embed")
+        .arg(&scaled_path)
+        .args(["--values", "normalised", "-o"])
+        .arg(&distances_path)
+        .status()?;
+    assert!(status.success());
+
+    Ok(())
+}
+
+#[test]
+fn test_cli_adapt() -> io::Result<()> {
+    let temp_dir = TempDir::new()?;
+
+    let exe_path = cli_exe();
+
+    let write_input = |name: &str, title: &str, text: &str| -> io::Result<PathBuf> {
+        let path = temp_dir.path().join(name);
+        write_corpus(&path, title, text)?;
+        Ok(path)
+    };
+    let base_input = write_input("base.txt", "Base", "They saw the dog. We saw a red cat.")?;
+    let new_input = write_input("new.txt", "Domain", "I saw a red cat. I saw the red dog.")?;
+
+    let base_path = temp_dir.path().join("base.json");
+    let status = Command::new(&exe_path)
+        .arg(&base_input)
+        .arg("--raw")
+        .arg("-o")
+        .arg(&base_path)
+        .status()?;
+    assert!(status.success(), "CLI command for base model failed");
+
+    let adapted_path = temp_dir.path().join("adapted.json");
+    let output = Command::new(&exe_path)
+        .arg("adapt")
+        .arg(&base_path)
+        .arg(&new_input)
+        .args(["--raw", "--scale", "2", "-o"])
+        .arg(&adapted_path)
+        .output()?;
+    assert!(
+        output.status.success(),
+        "adapt failed: {}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+
+    // "i" isn't in the base model, so the adapter has no row (or column) for it
+    let adapter: serde_json::Value = serde_json::from_reader(BufReader::new(File::open(
+        temp_dir.path().join("adapted_adapter.json"),
+    )?))?;
+    let adapter_rows = adapter["data"].as_array().unwrap();
+    assert!(adapter_rows.iter().all(|row| row[0] != "i"));
+    assert!(adapter_rows.iter().any(|row| row[0] == "saw"));
+
+    // saw: the 1 + a 1 from the base, plus twice the adapter's a 1 + the 1
+    let adapted: serde_json::Value =
+        serde_json::from_reader(BufReader::new(File::open(&adapted_path)?))?;
+    let saw = adapted["data"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .find(|row| row[0] == "saw")
+        .unwrap();
+    assert_eq!(saw[1], serde_json::json!(6));
+    assert!(
+        adapted["metadata"]["subtitle"]
+            .as_str()
+            .unwrap()
+            .contains("adapted to Domain")
+    );
+
+    // A scaled base can't have raw counts added to it
+    let scaled_path = temp_dir.path().join("scaled.json");
+    let status = Command::new(&exe_path)
+        .arg(&base_input)
+        .arg("-o")
+        .arg(&scaled_path)
+        .status()?;
+    assert!(status.success());
+    let output = Command::new(&exe_path)
+        .arg("adapt")
+        .arg(&scaled_path)
+        .arg(&new_input)
+        .arg("-o")
+        .arg(temp_dir.path().join("scaled_adapted.json"))
+        .output()?;
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("d10-scaled"));
+
+    // Nor can a base tokenized differently from the adapter
+    let output = Command::new(&exe_path)
+        .arg("adapt")
+        .arg(&base_path)
+        .arg(&new_input)
+        .args(["-p", ",.!?", "-o"])
+        .arg(temp_dir.path().join("punct_adapted.json"))
+        .output()?;
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("punctuation"));
+
+    // A skip-gram base is adapted with skip-gram counts: "saw _" predicts from two words back
+    let skip_path = temp_dir.path().join("skip.json");
+    let status = Command::new(&exe_path)
+        .arg(&base_input)
+        .args(["--raw", "--positions", "-2", "-o"])
+        .arg(&skip_path)
+        .status()?;
+    assert!(status.success());
+    let skip_adapted_path = temp_dir.path().join("skip_adapted.json");
+    let output = Command::new(&exe_path)
+        .arg("adapt")
+        .arg(&skip_path)
+        .arg(&new_input)
+        .arg("-o")
+        .arg(&skip_adapted_path)
+        .arg("--raw")
+        .output()?;
+    assert!(
+        output.status.success(),
+        "adapt failed: {}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    let adapted: serde_json::Value =
+        serde_json::from_reader(BufReader::new(File::open(&skip_adapted_path)?))?;
+    assert_eq!(
+        adapted["metadata"]["context_positions"],
+        serde_json::json!([-2])
+    );
+    let rows = adapted["data"].as_array().unwrap();
+    assert!(!rows.is_empty());
+    assert!(
+        rows.iter()
+            .all(|row| row[0].as_str().unwrap().ends_with(" _"))
+    );
+
+    Ok(())
+}
+
+#[test]
+fn test_cli_collapse() -> io::Result<()> {
+    let temp_dir = TempDir::new()?;
+
+    let exe_path = cli_exe();
+
+    let input_path = temp_dir.path().join("input.txt");
+    write_corpus(
+        &input_path,
+        "Collapse Test",
+        "See Spot run. See Spot jump. Run, Spot, run.",
+    )?;
+
+    let run = |output: &Path| -> io::Result<serde_json::Value> {
+        let result = Command::new(&exe_path)
+            .arg("collapse")
+            .arg(&input_path)
+            .args(["-g", "3", "-t", "40", "--seed", "42", "-o"])
+            .arg(output)
+            .output()?;
+        assert!(
+            result.status.success(),
+            "collapse failed: {}",
+            String::from_utf8_lossy(&result.stderr)
+        );
+        Ok(serde_json::from_reader(BufReader::new(File::open(
+            output,
+        )?))?)
+    };
+
+    let first = run(&temp_dir.path().join("first.json"))?;
+    let generations = first["generations"].as_array().unwrap();
+    assert_eq!(generations.len(), 4);
+    assert_eq!(generations[0]["vocabulary_size"], serde_json::json!(6));
+    assert!(generations[3]["kl_divergence"].as_f64().unwrap() > 0.0);
+
+    // The same seed reproduces the same run
+    let second = run(&temp_dir.path().join("second.json"))?;
+    assert_eq!(first, second);
+
+    // Joker mode starts from a random grid over the given words
+    let joker_path = temp_dir.path().join("joker.json");
+    let status = Command::new(&exe_path)
+        .args([
+            "collapse",
+            "--joker",
+            "pizza robot moon dance",
+            "-g",
+            "2",
+            "-o",
+        ])
+        .arg(&joker_path)
+        .status()?;
+    assert!(status.success(), "collapse --joker failed");
+    let joker: serde_json::Value =
+        serde_json::from_reader(BufReader::new(File::open(&joker_path)?))?;
+    assert_eq!(joker["joker"], serde_json::json!(true));
+    assert_eq!(
+        joker["generations"][0]["vocabulary_size"],
+        serde_json::json!(4)
+    );
+
+    Ok(())
+}
+
+#[test]
+fn test_cli_diff() -> io::Result<()> {
+    let temp_dir = TempDir::new()?;
+
+    let exe_path = cli_exe();
+
+    let write_input = |name: &str, text: &str| -> io::Result<PathBuf> {
+        let path = temp_dir.path().join(name);
+        write_corpus(&path, "Diff Test", text)?;
+        Ok(path)
+    };
+    let original = write_input("original.txt", "See Spot run. See Spot jump.")?;
+    let synthetic = write_input("synthetic.txt", "See run. Run spot. Spot run run.")?;
+
+    // Compare a model JSON file against a text file
+    let model_path = temp_dir.path().join("original.json");
+    let status = Command::new(&exe_path)
+        .arg(&original)
+        .arg("--raw")
+        .arg("-o")
+        .arg(&model_path)
+        .status()?;
+    assert!(status.success(), "CLI command for model failed");
+
+    let diff_path = temp_dir.path().join("diff.json");
+    let output = Command::new(&exe_path)
+        .arg("diff")
+        .arg(&model_path)
+        .arg(&synthetic)
+        .arg("-o")
+        .arg(&diff_path)
+        .output()?;
+    assert!(
+        output.status.success(),
+        "diff failed: {}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains("Only in A (1): jump"));
+
+    let diff: serde_json::Value = serde_json::from_reader(BufReader::new(File::open(&diff_path)?))?;
+    assert_eq!(diff["vocabulary_only_in_a"], serde_json::json!(["jump"]));
+    assert_eq!(diff["prefixes_only_in_b"], serde_json::json!([]));
+    let js = diff["js_divergence"].as_f64().unwrap();
+    assert!(js > 0.0 && js <= 1.0);
+    assert_eq!(
+        diff["shared_prefixes"].as_array().unwrap().len(),
+        4,
+        "., see, spot and run are in both models"
+    );
+
+    // Models must have raw counts and the same n
+    let scaled_path = temp_dir.path().join("scaled.json");
+    let trigram_path = temp_dir.path().join("trigram.json");
+    for (path, args) in [
+        (&scaled_path, vec![]),
+        (&trigram_path, vec!["--raw", "-n", "3"]),
+    ] {
+        let status = Command::new(&exe_path)
+            .arg(&original)
+            .args(args)
+            .arg("-o")
+            .arg(path)
+            .status()?;
+        assert!(status.success());
+    }
+    for (a, b, args, message) in [
+        (&scaled_path, &synthetic, vec![], "--raw"),
+        (&model_path, &trigram_path, vec![], "n = 3"),
+        (&model_path, &synthetic, vec!["-n", "3"], "--n 3"),
+    ] {
+        let output = Command::new(&exe_path)
+            .arg("diff")
+            .arg(a)
+            .arg(b)
+            .args(args)
+            .output()?;
+        assert!(!output.status.success());
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        assert!(stderr.contains(message), "{}", stderr);
+    }
+
+    // A text file is counted at a skip-gram model's positions
+    let skip_path = temp_dir.path().join("skip.json");
+    let status = Command::new(&exe_path)
+        .arg(&original)
+        .args(["--raw", "--positions", "-2", "-o"])
+        .arg(&skip_path)
+        .status()?;
+    assert!(status.success());
+    let output = Command::new(&exe_path)
+        .arg("diff")
+        .arg(&skip_path)
+        .arg(&original)
+        .arg("-o")
+        .arg(&diff_path)
+        .output()?;
+    assert!(output.status.success());
+    let diff: serde_json::Value = serde_json::from_reader(BufReader::new(File::open(&diff_path)?))?;
+    assert_eq!(diff["js_divergence"], serde_json::json!(0.0));
+
+    Ok(())
+}
+
+#[test]
+fn test_cli_merge() -> io::Result<()> {
+    let temp_dir = TempDir::new()?;
+
+    let exe_path = cli_exe();
+
+    let build_model = |name: &str, title: &str, text: &str, args: &[&str]| -> io::Result<PathBuf> {
+        let input = temp_dir.path().join(format!("{}.txt", name));
+        write_corpus(&input, title, text)?;
+
+        let output = temp_dir.path().join(format!("{}.json", name));
+        let status = Command::new(&exe_path)
+            .arg(&input)
+            .arg("--raw")
+            .arg("-o")
+            .arg(&output)
+            .args(args)
+            .status()?;
+        assert!(status.success(), "CLI command for {} failed", name);
+        Ok(output)
+    };
+    let first = build_model("first", "First Book", "See Spot run. See Spot jump.", &[])?;
+    let second = build_model("second", "Second Book", "Run Spot run.", &[])?;
+
+    let merged_path = temp_dir.path().join("merged.json");
+    let output = Command::new(&exe_path)
+        .arg("merge")
+        .arg(&first)
+        .arg(&second)
+        .arg("--weights")
+        .arg("1,2")
+        .arg("--raw")
+        .arg("-o")
+        .arg(&merged_path)
+        .output()?;
+    assert!(
+        output.status.success(),
+        "merge failed: {}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+
+    let merged: serde_json::Value =
+        serde_json::from_reader(BufReader::new(File::open(&merged_path)?))?;
+    assert_eq!(merged["metadata"]["title"], "First Book + Second Book");
+    assert_eq!(merged["metadata"]["tokenization"]["punctuation"], ",.");
+    let sources = merged["metadata"]["sources"].as_array().unwrap();
+    assert_eq!(sources.len(), 2);
+    assert_eq!(sources[1]["weight"], 2.0);
+    // "spot" -> run: 1 + 2 * 1, "spot" -> jump: 1
+    let spot = merged["data"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .find(|row| row[0] == "spot")
+        .unwrap();
+    assert_eq!(spot[1], 4);
+    assert_eq!(spot[2], serde_json::json!(["run", 3]));
+
+    // Models with different punctuation settings are refused
+    let other = build_model("other", "Other Book", "Run, Spot!", &["-p", ",.!"])?;
+    let output = Command::new(&exe_path)
+        .arg("merge")
+        .arg(&first)
+        .arg(&other)
+        .arg("-o")
+        .arg(temp_dir.path().join("refused.json"))
+        .output()?;
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("punctuation"));
+
+    Ok(())
+}
+
+#[test]
+fn test_cli_prune() -> io::Result<()> {
+    let temp_dir = TempDir::new()?;
+
+    let exe_path = cli_exe();
+
+    let input_path = temp_dir.path().join("input.txt");
+    write_corpus(
+        &input_path,
+        "Prune Test",
+        "The cat sat on the mat. The dog sat on the log. The cat saw the dog.",
+    )?;
+
+    let output_path = temp_dir.path().join("pruned.json");
+    let output = Command::new(&exe_path)
+        .arg(&input_path)
+        .arg("--raw")
+        .arg("--prune-vocab")
+        .arg("5")
+        .arg("--max-followers")
+        .arg("2")
+        .arg("-o")
+        .arg(&output_path)
+        .output()?;
+    assert!(
+        output.status.success(),
+        "prune failed: {}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    assert!(String::from_utf8_lossy(&output.stdout).contains("Estimated booklet pages: 5"));
+
+    let model: serde_json::Value =
+        serde_json::from_reader(BufReader::new(File::open(&output_path)?))?;
+    let data = model["data"].as_array().unwrap();
+    // the, ".", cat, sat, on are kept; everything else is <unk>
+    assert_eq!(data.len(), 6);
+    assert!(data.iter().any(|row| row[0] == "<unk>"));
+    assert!(data.iter().all(|row| row.as_array().unwrap().len() <= 4));
+
+    let pruning = &model["metadata"]["stats"]["pruning"];
+    assert_eq!(pruning["ngram_occurrences_before"], 19);
+    assert!(pruning["removed_mass"].as_f64().unwrap() > 0.0);
+    assert!(pruning["unknown_mass"].as_f64().unwrap() > 0.0);
+
+    Ok(())
+}
+
+#[test]
+fn test_cli_unigram() -> io::Result<()> {
+    let temp_dir = TempDir::new()?;
+
+    let exe_path = cli_exe();
+
+    let input_path = temp_dir.path().join("input.txt");
+    write_corpus(
+        &input_path,
+        "Unigram Test",
+        "The cat sat on the mat. The dog sat on the log.",
+    )?;
+
+    let output_path = temp_dir.path().join("unigram.json");
+    let status = Command::new(&exe_path)
+        .arg(&input_path)
+        .arg("-n")
+        .arg("1")
+        .arg("-o")
+        .arg(&output_path)
+        .status()?;
+    assert!(status.success(), "CLI command for unigram model failed");
+
+    let model: serde_json::Value =
+        serde_json::from_reader(BufReader::new(File::open(&output_path)?))?;
+    assert_eq!(model["metadata"]["n"], 1);
+    assert_eq!(model["metadata"]["subtitle"], "A unigram language model");
+    let data = model["data"].as_array().unwrap();
+    assert_eq!(data.len(), 1, "a unigram model has a single row");
+    assert_eq!(data[0][0], "");
+    // 14 tokens, so the row total is scaled to 99 for two d10s
+    assert_eq!(data[0][1], 99);
+    assert_eq!(data[0][2][0], "the");
+
+    // n = 0 is an error rather than a silent fallback
+    let output = Command::new(&exe_path)
+        .arg(&input_path)
+        .arg("-n")
+        .arg("0")
+        .arg("-o")
+        .arg(temp_dir.path().join("invalid.json"))
+        .output()?;
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("1 or greater"));
+
+    Ok(())
+}
+
+#[test]
+fn test_cli_multiple_orders() -> io::Result<()> {
+    let temp_dir = TempDir::new()?;
+
+    let exe_path = cli_exe();
+
+    let input_path = temp_dir.path().join("input.txt");
+    write_corpus(
+        &input_path,
+        "Orders Test",
+        "The cat sat on the mat. The dog sat on the log.",
+    )?;
+
+    let output_path = temp_dir.path().join("model.json");
+    let status = Command::new(&exe_path)
+        .arg(&input_path)
+        .arg("-n")
+        .arg("2..4")
+        .arg("--raw")
+        .arg("-o")
+        .arg(&output_path)
+        .status()?;
+    assert!(status.success(), "CLI command for n = 2..4 failed");
+    assert!(!output_path.exists());
+
+    for n in 2..=4 {
+        let path = temp_dir.path().join(format!("model-{}.json", n));
+        let model: serde_json::Value = serde_json::from_reader(BufReader::new(File::open(&path)?))?;
+        assert_eq!(model["metadata"]["n"], n);
+        let data = model["data"].as_array().unwrap();
+        assert!(
+            data.iter()
+                .all(|row| row[0].as_str().unwrap().split(' ').count() == n - 1)
+        );
+        // 14 tokens give 14 - (n - 1) n-grams
+        let total: u64 = data.iter().map(|row| row[1].as_u64().unwrap()).sum();
+        assert_eq!(total, 14 - (n as u64 - 1));
+    }
+
+    Ok(())
+}
+
+#[test]
+fn test_cli_skip_gram() -> io::Result<()> {
+    let temp_dir = TempDir::new()?;
+
+    let exe_path = cli_exe();
+
+    let input_path = temp_dir.path().join("input.txt");
+    write_corpus(&input_path, "Skip-gram Test", "The cat sat on the mat.")?;
+
+    let output_path = temp_dir.path().join("skip.json");
+    let status = Command::new(&exe_path)
+        .arg(&input_path)
+        .arg("--positions")
+        .arg("-3,-1")
+        .arg("--raw")
+        .arg("-o")
+        .arg(&output_path)
+        .status()?;
+    assert!(status.success(), "CLI command for skip-gram model failed");
+
+    let model: serde_json::Value =
+        serde_json::from_reader(BufReader::new(File::open(&output_path)?))?;
+    assert_eq!(model["metadata"]["n"], 3);
+    assert_eq!(
+        model["metadata"]["context_positions"],
+        serde_json::json!([-3, -1])
+    );
+    let prefixes: Vec<&str> = model["data"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|row| row[0].as_str().unwrap())
+        .collect();
+    assert_eq!(
+        prefixes,
+        vec!["cat _ on", "on _ mat", "sat _ the", "the _ sat"]
+    );
+
+    // Split books keep the skip-gram subtitle
+    let status = Command::new(&exe_path)
+        .arg(&input_path)
+        .args(["--positions", "-3,-1", "--raw", "-b", "2", "-o"])
+        .arg(&output_path)
+        .status()?;
+    assert!(status.success());
+    let book: serde_json::Value = serde_json::from_reader(BufReader::new(File::open(
+        temp_dir.path().join("skip_book_1.json"),
+    )?))?;
+    let subtitle = book["metadata"]["subtitle"].as_str().unwrap();
+    assert!(
+        subtitle.starts_with("A skip-gram language model (context positions -3, -1): "),
+        "{}",
+        subtitle
+    );
+    assert!(subtitle.ends_with("(Book 1 of 2)"), "{}", subtitle);
+
+    // --positions replaces -n
+    let output = Command::new(&exe_path)
+        .arg(&input_path)
+        .arg("--positions")
+        .arg("-2")
+        .arg("-n")
+        .arg("3")
+        .arg("-o")
+        .arg(&output_path)
+        .output()?;
+    assert!(!output.status.success());
+
+    Ok(())
+}
+
+#[test]
+fn test_cli_split_at_letters() -> io::Result<()> {
+    let temp_dir = TempDir::new()?;
+
+    let exe_path = cli_exe();
+
+    let input_path = temp_dir.path().join("input.txt");
+    let text = "The quick brown fox jumps over the lazy dog. A big bear sat by the tall tree.\n\
+                Every good boy deserves fruit, and every happy hen eats grain near the pond.\n"
+        .repeat(3);
+    write_corpus(&input_path, "Split Test", text.trim_end())?;
+
+    let output_path = temp_dir.path().join("model.json");
+    let status = Command::new(&exe_path)
+        .arg(&input_path)
+        .arg("-n")
+        .arg("3")
+        .arg("-b")
+        .arg("3")
+        .arg("--split-at")
+        .arg("letter")
+        .arg("-o")
+        .arg(&output_path)
+        .status()?;
+    assert!(status.success(), "CLI command for letter splitting failed");
+
+    // Every first letter appears in exactly one book
+    let mut seen: Vec<char> = Vec::new();
+    for book in 1..=3 {
+        let path = temp_dir.path().join(format!("model_book_{}.json", book));
+        let model: serde_json::Value = serde_json::from_reader(BufReader::new(File::open(&path)?))?;
+        let mut letters: Vec<char> = model["data"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .filter_map(|row| row[0].as_str().unwrap().chars().next())
+            .collect();
+        letters.dedup();
+        for letter in letters {
+            assert!(!seen.contains(&letter), "'{}' is in two books", letter);
+            seen.push(letter);
+        }
+    }
+
+    Ok(())
+}
+
+#[test]
+fn test_cli_page_aware_splitting() -> io::Result<()> {
+    let temp_dir = TempDir::new()?;
+
+    let exe_path = cli_exe();
+
+    let input_path = temp_dir.path().join("input.txt");
+    write_corpus(
+        &input_path,
+        "Pages Test",
+        "The quick brown fox jumps over the lazy dog. A big bear sat by the tall tree.",
+    )?;
+
+    let output_path = temp_dir.path().join("model.json");
+    let output = Command::new(&exe_path)
+        .arg(&input_path)
+        .arg("-b")
+        .arg("2")
+        .arg("--balance-pages")
+        .arg("--paper")
+        .arg("a5")
+        .arg("-o")
+        .arg(&output_path)
+        .output()?;
+    assert!(
+        output.status.success(),
+        "CLI command for page balancing failed"
+    );
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains("Estimated pages (a5 8pt, 4 columns): 5 ("));
+    assert!(temp_dir.path().join("model_book_2.json").exists());
+
+    // A small model fits in one book of 6 pages
+    let output = Command::new(&exe_path)
+        .arg(&input_path)
+        .arg("--pages-per-book")
+        .arg("6")
+        .arg("-o")
+        .arg(&output_path)
+        .output()?;
+    assert!(output.status.success());
+    assert!(
+        String::from_utf8_lossy(&output.stdout)
+            .contains("Estimated pages (a4 8pt, 4 columns): 5\n")
+    );
+
+    // No room after the front matter
+    let output = Command::new(&exe_path)
+        .arg(&input_path)
+        .arg("--pages-per-book")
+        .arg("4")
+        .arg("-o")
+        .arg(&output_path)
+        .output()?;
+    assert!(!output.status.success());
+
+    // Unknown paper sizes are rejected
+    let output = Command::new(&exe_path)
+        .arg(&input_path)
+        .arg("--paper")
+        .arg("b5")
+        .arg("-o")
+        .arg(&output_path)
+        .output()?;
+    assert!(!output.status.success());
+
+    Ok(())
+}
+
+// Uses a stand-in `typst` executable, which the `embed-typst` build doesn't run
+#[cfg(all(unix, not(feature = "embed-typst")))]
+#[test]
+fn test_cli_typst_page_setup() -> io::Result<()> {
+    use std::os::unix::fs::PermissionsExt;
+
+    let temp_dir = TempDir::new()?;
+
+    let exe_path = cli_exe();
+
+    // A stand-in for typst that records its arguments
+    let bin_dir = temp_dir.path().join("bin");
+    std::fs::create_dir(&bin_dir)?;
+    let fake_typst = bin_dir.join("typst");
+    let args_log = temp_dir.path().join("typst-args.txt");
+    std::fs::write(
+        &fake_typst,
+        format!(
+            "#!/bin/sh\nfor arg in \"$@\"; do echo \"$arg\" >> '{}'; done\n",
+            args_log.display()
+        ),
+    )?;
+    std::fs::set_permissions(&fake_typst, std::fs::Permissions::from_mode(0o755))?;
+    let path = format!(
+        "{}:{}",
+        bin_dir.display(),
+        std::env::var("PATH").unwrap_or_default()
+    );
+
+    let templates = temp_dir.path().join("templates");
+    std::fs::create_dir(&templates)?;
+    let template = templates.join("custom.typ");
+    File::create(&template)?;
+
+    let input_path = temp_dir.path().join("input.txt");
+    write_corpus(&input_path, "Typst Test", "The cat sat on the mat.")?;
+
+    let out_dir = temp_dir.path().join("out");
+    std::fs::create_dir(&out_dir)?;
+    let status = Command::new(&exe_path)
+        .env("PATH", path)
+        .arg(&input_path)
+        .arg("--typst")
+        .arg("--paper")
+        .arg("a5")
+        .arg("--font-size")
+        .arg("9pt")
+        .arg("--columns")
+        .arg("3")
+        .arg("--template")
+        .arg(&template)
+        .arg("-o")
+        .arg(out_dir.join("cat.json"))
+        .status()?;
+    assert!(status.success(), "CLI command with --typst failed");
+
+    let args = std::fs::read_to_string(&args_log)?;
+    let args: Vec<&str> = args.lines().collect();
+    for expected in [
+        "paper_size=a5",
+        "font_size=9pt",
+        "columns=3",
+        "subtitle=A bigram language model",
+        // Relative to a root containing both the template and the model
+        "json_path=/out/cat.json",
+    ] {
+        assert!(
+            args.contains(&expected),
+            "missing {} in {:?}",
+            expected,
+            args
+        );
+    }
+    assert!(args.iter().any(|arg| arg.ends_with("custom.typ")));
+    // No copy of the model is left behind
+    assert!(!out_dir.join("model.json").exists());
+
+    Ok(())
+}
+
+// Uses a stand-in `typst` executable, which the `embed-typst` build doesn't run
+#[cfg(all(unix, not(feature = "embed-typst")))]
+#[test]
+fn test_cli_typst_jobs_report_every_failure() -> io::Result<()> {
+    use std::os::unix::fs::PermissionsExt;
+
+    let temp_dir = TempDir::new()?;
+
+    let exe_path = cli_exe();
+
+    // A stand-in for typst that fails for book 2 and otherwise creates the PDF
+    let bin_dir = temp_dir.path().join("bin");
+    std::fs::create_dir(&bin_dir)?;
+    let fake_typst = bin_dir.join("typst");
+    std::fs::write(
+        &fake_typst,
+        "#!/bin/sh\n\
+         for arg in \"$@\"; do\n\
+         case \"$arg\" in *book_2.json) echo \"error: bad book\" >&2; exit 1;; esac\n\
+         pdf=\"$arg\"\n\
+         done\n\
+         touch \"$pdf\"\n",
+    )?;
+    std::fs::set_permissions(&fake_typst, std::fs::Permissions::from_mode(0o755))?;
+    let path = format!(
+        "{}:{}",
+        bin_dir.display(),
+        std::env::var("PATH").unwrap_or_default()
+    );
+
+    let input_path = temp_dir.path().join("input.txt");
+    write_corpus(
+        &input_path,
+        "Jobs Test",
+        "The quick brown fox jumps over the lazy dog. A big bear sat by the tall tree.",
+    )?;
+
+    let output = Command::new(&exe_path)
+        .env("PATH", path)
+        .arg(&input_path)
+        .arg("-b")
+        .arg("3")
+        .arg("--typst")
+        .arg("--jobs")
+        .arg("2")
+        .arg("-o")
+        .arg(temp_dir.path().join("model.json"))
+        .output()?;
+    assert!(!output.status.success());
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("1 of 4 typst runs failed"), "{}", stderr);
+    assert!(stderr.contains("bad book"));
+
+    // The other books (and the index) were still compiled
+    for pdf in ["model_book_1.pdf", "model_book_3.pdf", "model_index.pdf"] {
+        assert!(temp_dir.path().join(pdf).exists(), "{} is missing", pdf);
+    }
+    assert!(!temp_dir.path().join("model_book_2.pdf").exists());
+
+    Ok(())
+}
+
+#[cfg(unix)]
+#[test]
+fn test_cli_build_manifest() -> io::Result<()> {
+    use std::os::unix::fs::PermissionsExt;
+
+    let temp_dir = TempDir::new()?;
+
+    let exe_path = cli_exe();
+
+    // A stand-in for typst that creates the PDF (its last argument)
+    let bin_dir = temp_dir.path().join("bin");
+    std::fs::create_dir(&bin_dir)?;
+    let fake_typst = bin_dir.join("typst");
+    std::fs::write(
+        &fake_typst,
+        "#!/bin/sh\nfor arg in \"$@\"; do pdf=\"$arg\"; done\ntouch \"$pdf\"\n",
+    )?;
+    std::fs::set_permissions(&fake_typst, std::fs::Permissions::from_mode(0o755))?;
+    let path = format!(
+        "{}:{}",
+        bin_dir.display(),
+        std::env::var("PATH").unwrap_or_default()
+    );
+
+    let data_dir = temp_dir.path().join("data");
+    std::fs::create_dir(&data_dir)?;
+    write_corpus(
+        &data_dir.join("fox.txt"),
+        "Build Test",
+        "The quick brown fox jumps over the lazy dog. A big bear sat by the tall tree.",
+    )?;
+    write_corpus(
+        &data_dir.join("spot.txt"),
+        "Build Test",
+        "See Spot run. See Spot jump.",
+    )?;
+    std::fs::write(temp_dir.path().join("book.typ"), "// template\n")?;
+
+    let manifest = temp_dir.path().join("booklets.toml");
+    std::fs::write(
+        &manifest,
+        "data_dir = \"data\"\ntemplate = \"book.typ\"\n\n\
+         [[book]]\ncorpus = \"fox.txt\"\nbooks = 2\n\n\
+         [[book]]\ncorpus = \"spot.txt\"\nn = 3\ndice = \"raw\"\npdf = false\n",
+    )?;
+    let build = || {
+        Command::new(&exe_path)
+            .env("PATH", &path)
+            .arg("build")
+            .arg(&manifest)
+            .output()
+    };
+
+    let output = build()?;
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(
+        output.status.success(),
+        "{}{}",
+        stdout,
+        String::from_utf8_lossy(&output.stderr)
+    );
+    assert!(
+        stdout.contains("Built 2 of 2 targets (0 up to date)"),
+        "{}",
+        stdout
+    );
+
+    let out_dir = temp_dir.path().join("out");
+    for file in [
+        "json/fox-2-2_book_1.json",
+        "json/fox-2-2_book_2.json",
+        "json/fox-2-2_manifest.json",
+        "json/fox-2-2_index.json",
+        "pdf/fox-2-2_book_1.pdf",
+        "pdf/fox-2-2_book_2.pdf",
+        "pdf/fox-2-2_index.pdf",
+        "stats/fox-2-2.json",
+        "json/spot-3-1.json",
+        "stats/spot-3-1.json",
+    ] {
+        assert!(out_dir.join(file).exists(), "{} was not built", file);
+    }
+    assert!(!out_dir.join("pdf/spot-3-1.pdf").exists());
+    let spot = std::fs::read_to_string(out_dir.join("json/spot-3-1.json"))?;
+    assert!(spot.contains("\"scaling\": \"raw\""), "{}", spot);
+
+    // Nothing changed, so nothing is rebuilt
+    let output = build()?;
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(output.status.success());
+    assert!(stdout.contains("fox-2-2 is up to date"), "{}", stdout);
+    assert!(
+        stdout.contains("Built 0 of 2 targets (2 up to date)"),
+        "{}",
+        stdout
+    );
+
+    // A changed corpus or a missing output rebuilds just that target
+    write_corpus(
+        &data_dir.join("spot.txt"),
+        "Build Test",
+        "See Spot run. See Spot jump. Run Spot run.",
+    )?;
+    std::fs::remove_file(out_dir.join("pdf/fox-2-2_book_2.pdf"))?;
+    let output = build()?;
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(
+        stdout.contains("Built 2 of 2 targets (0 up to date)"),
+        "{}",
+        stdout
+    );
+    assert!(out_dir.join("pdf/fox-2-2_book_2.pdf").exists());
+
+    // Changing the template rebuilds the typeset target only
+    std::fs::write(temp_dir.path().join("book.typ"), "// new template\n")?;
+    let output = build()?;
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains("spot-3-1 is up to date"), "{}", stdout);
+    assert!(
+        stdout.contains("Built 1 of 2 targets (1 up to date)"),
+        "{}",
+        stdout
+    );
+
+    Ok(())
+}
+
+#[cfg(feature = "embed-typst")]
+#[test]
+fn test_cli_embedded_typst() -> io::Result<()> {
+    let temp_dir = TempDir::new()?;
+
+    let exe_path = cli_exe();
+
+    let input_path = temp_dir.path().join("input.txt");
+    write_corpus(
+        &input_path,
+        "Embedded Test",
+        "The quick brown fox jumps over the lazy dog. A big bear sat by the tall tree.",
+    )?;
+
+    // No typst on PATH and no book.typ in the working directory: the bundled template and
+    // fonts are used
+    let output = Command::new(&exe_path)
+        .current_dir(temp_dir.path())
+        .env("PATH", "")
+        .arg(&input_path)
+        .arg("-b")
+        .arg("2")
+        .arg("--typst")
+        .arg("-o")
+        .arg(temp_dir.path().join("model.json"))
+        .output()?;
+    assert!(
+        output.status.success(),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    for pdf in ["model_book_1.pdf", "model_book_2.pdf", "model_index.pdf"] {
+        let bytes = std::fs::read(temp_dir.path().join(pdf))?;
+        assert!(bytes.starts_with(b"%PDF"), "{} is not a PDF", pdf);
+    }
+
+    // Typst errors say where in the template they are
+    let broken = temp_dir.path().join("broken.typ");
+    std::fs::write(&broken, "= Broken\n#let data = json(sys.inputs.missing)\n")?;
+    let output = Command::new(&exe_path)
+        .arg(&input_path)
+        .arg("--typst")
+        .arg("--template")
+        .arg(&broken)
+        .arg("-o")
+        .arg(temp_dir.path().join("broken.json"))
+        .output()?;
+    assert!(!output.status.success());
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("broken.typ:2:"), "{}", stderr);
+
+    Ok(())
+}
+
+// Uses a stand-in `typst` executable, which the `embed-typst` build doesn't run
+#[cfg(all(unix, not(feature = "embed-typst")))]
+#[test]
+fn test_cli_bundled_templates() -> io::Result<()> {
+    use std::os::unix::fs::PermissionsExt;
+
+    let temp_dir = TempDir::new()?;
+
+    let exe_path = cli_exe();
+
+    // A stand-in for typst that records the template it was given (the second-last argument)
+    // and its first line
+    let bin_dir = temp_dir.path().join("bin");
+    std::fs::create_dir(&bin_dir)?;
+    let fake_typst = bin_dir.join("typst");
+    let template_log = temp_dir.path().join("templates.txt");
+    std::fs::write(
+        &fake_typst,
+        format!(
+            "#!/bin/sh\n\
+             for arg in \"$@\"; do template=\"$pdf\"; pdf=\"$arg\"; done\n\
+             echo \"$template\" >> {log}\n\
+             head -n 1 \"$template\" >> {log}\n\
+             touch \"$pdf\"\n",
+            log = template_log.display()
+        ),
+    )?;
+    std::fs::set_permissions(&fake_typst, std::fs::Permissions::from_mode(0o755))?;
+    let path = format!(
+        "{}:{}",
+        bin_dir.display(),
+        std::env::var("PATH").unwrap_or_default()
+    );
+
+    let input_path = temp_dir.path().join("input.txt");
+    write_corpus(&input_path, "Template Test", "The cat sat on the mat.")?;
+
+    // Run from a directory without book.typ: the bundled template is used, then cleaned up
+    let work_dir = temp_dir.path().join("work");
+    std::fs::create_dir(&work_dir)?;
+    let status = Command::new(&exe_path)
+        .current_dir(&work_dir)
+        .env("PATH", &path)
+        .arg(&input_path)
+        .arg("--typst")
+        .arg("-o")
+        .arg("cat.json")
+        .status()?;
+    assert!(status.success());
+    let log = std::fs::read_to_string(&template_log)?;
+    let lines: Vec<&str> = log.lines().collect();
+    let bundled = Path::new(lines[0]);
+    assert_eq!(bundled.file_name().unwrap(), "book.typ");
+    assert_eq!(
+        lines[1],
+        include_str!("../book.typ").lines().next().unwrap()
+    );
+    assert!(!bundled.exists(), "bundled template was left behind");
+    assert!(work_dir.join("cat.pdf").exists());
+
+    // Export copies to customise, without overwriting them
+    let export = || {
+        Command::new(&exe_path)
+            .current_dir(&work_dir)
+            .arg("templates")
+            .arg("export")
+            .output()
+    };
+    let output = export()?;
+    assert!(output.status.success());
+    let templates = work_dir.join("templates");
+    for file in [
+        "book.typ",
+        "index.typ",
+        "grid.typ",
+        "summary.typ",
+        "socy-logo-bw.svg",
+    ] {
+        assert!(templates.join(file).exists(), "{} was not exported", file);
+    }
+    std::fs::write(templates.join("book.typ"), "// my booklet\n")?;
+    let output = export()?;
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("--force"));
+    assert_eq!(
+        std::fs::read_to_string(templates.join("book.typ"))?,
+        "// my booklet\n"
+    );
+
+    std::fs::remove_file(&template_log)?;
+    let status = Command::new(&exe_path)
+        .current_dir(&work_dir)
+        .env("PATH", &path)
+        .arg(&input_path)
+        .arg("--typst")
+        .arg("--template")
+        .arg("templates/book.typ")
+        .arg("-o")
+        .arg("cat.json")
+        .status()?;
+    assert!(status.success());
+    let log = std::fs::read_to_string(&template_log)?;
+    assert_eq!(
+        log.lines().collect::<Vec<_>>(),
+        ["templates/book.typ", "// my booklet"]
+    );
+
+    Ok(())
+}
+
+#[test]
+fn test_cli_html_and_markdown_booklets() -> io::Result<()> {
+    let temp_dir = TempDir::new()?;
+
+    let exe_path = cli_exe();
+
+    let input_path = temp_dir.path().join("input.txt");
+    write_corpus(
+        &input_path,
+        "Export Test",
+        "The quick brown fox jumps over the lazy dog. A big bear sat by the tall tree.",
+    )?;
+
+    // Split HTML books, named like the JSON books would be
+    let status = Command::new(&exe_path)
+        .arg(&input_path)
+        .arg("--format")
+        .arg("html")
+        .arg("-b")
+        .arg("2")
+        .arg("--split-at")
+        .arg("letter")
+        .arg("--paper")
+        .arg("a5")
+        .arg("-o")
+        .arg(temp_dir.path().join("model.html"))
+        .status()?;
+    assert!(status.success());
+    let book_1 = std::fs::read_to_string(temp_dir.path().join("model_book_1.html"))?;
+    let book_2 = std::fs::read_to_string(temp_dir.path().join("model_book_2.html"))?;
+    assert!(!temp_dir.path().join("model_book_1.json").exists());
+    assert!(book_1.contains("size: A5;"));
+    assert!(book_1.contains("(Book 1 of 2)"));
+    assert!(book_1.contains("<span class=\"prefix\"><span class=\"punct\">.</span></span>"));
+    assert!(book_2.contains("<span class=\"prefix\">the</span>"));
+    let manifest = std::fs::read_to_string(temp_dir.path().join("model_manifest.json"))?;
+    assert!(manifest.contains("model_book_2.html"), "{}", manifest);
+
+    // Markdown with raw counts: "the" is followed by quick, lazy and tall once each. An
+    // output path without an extension gets the format's extension.
+    let status = Command::new(&exe_path)
+        .arg(&input_path)
+        .arg("--format")
+        .arg("markdown")
+        .arg("--raw")
+        .arg("-o")
+        .arg(temp_dir.path().join("model"))
+        .status()?;
+    assert!(status.success());
+    let markdown = std::fs::read_to_string(temp_dir.path().join("model.md"))?;
+    assert!(markdown.starts_with("# Export Test\n"));
+    assert!(
+        markdown.contains("| **the** | ♦ | 1 lazy · 2 quick · 3 tall |"),
+        "{}",
+        markdown
+    );
+
+    // Any other output path is used as given
+    let status = Command::new(&exe_path)
+        .arg(&input_path)
+        .arg("--format")
+        .arg("tsv-matrix")
+        .arg("-o")
+        .arg(temp_dir.path().join("grid.txt"))
+        .status()?;
+    assert!(status.success());
+    assert!(temp_dir.path().join("grid.txt").exists());
+    assert!(!temp_dir.path().join("grid.tsv").exists());
+
+    // Only JSON booklets can be typeset, and the check comes before anything is written
+    for format in ["html", "tsv-matrix"] {
+        let output = Command::new(&exe_path)
+            .arg(&input_path)
+            .arg("--format")
+            .arg(format)
+            .arg("--typst")
+            .arg("--trace")
+            .arg(temp_dir.path().join("typst-trace.jsonl"))
+            .arg("--stats-json")
+            .arg(temp_dir.path().join("typst-stats.json"))
+            .arg("-o")
+            .arg(temp_dir.path().join("typst"))
+            .output()?;
+        assert!(!output.status.success());
+        assert!(String::from_utf8_lossy(&output.stderr).contains("--typst"));
+        assert!(!temp_dir.path().join("typst-trace.jsonl").exists());
+        assert!(!temp_dir.path().join("typst-stats.json").exists());
+        assert!(!temp_dir.path().join("typst.html").exists());
+        assert!(!temp_dir.path().join("typst.tsv").exists());
+    }
+
+    Ok(())
+}
@@ -32,7 +32,7 @@ fn lowercases_consistently_and_strips_quotes() -> io::Result<()> {
         file.flush()?;
     }
 
-    let mut counter = NGramCounter::new(2, vec![',', '.']);
+    let mut counter = NGramCounter::new(2, vec![',', '.']).unwrap();
     counter.process_file(&path)?;
     let tokens = collect_tokens(&counter);
 
@@ -61,7 +61,7 @@ fn keeps_allowlisted_pronouns_cased() -> io::Result<()> {
         file.flush()?;
     }
 
-    let mut counter = NGramCounter::new(2, vec![',', '.']);
+    let mut counter = NGramCounter::new(2, vec![',', '.']).unwrap();
     counter.process_file(&path)?;
     let tokens = collect_tokens(&counter);
 
@@ -93,7 +93,7 @@ fn filters_numbers_and_roman_numerals() -> io::Result<()> {
         file.flush()?;
     }
 
-    let mut counter = NGramCounter::new(2, vec![',', '.']);
+    let mut counter = NGramCounter::new(2, vec![',', '.']).unwrap();
     counter.process_file(&path)?;
     let tokens = collect_tokens(&counter);
 
@@ -123,7 +123,7 @@ fn preserves_contractions_and_possessives() -> io::Result<()> {
         file.flush()?;
     }
 
-    let mut counter = NGramCounter::new(2, vec![',', '.']);
+    let mut counter = NGramCounter::new(2, vec![',', '.']).unwrap();
     counter.process_file(&path)?;
     let tokens = collect_tokens(&counter);
 
@@ -156,7 +156,7 @@ fn only_configured_punctuation_is_kept() -> io::Result<()> {
         file.flush()?;
     }
 
-    let mut counter = NGramCounter::new(2, vec![',', '.']);
+    let mut counter = NGramCounter::new(2, vec![',', '.']).unwrap();
     counter.process_file(&path)?;
     let tokens = collect_tokens(&counter);
 